@@ -0,0 +1,346 @@
+//! Bitwise AND: bit decomposition against byte lookups
+//!
+//! Proves the same table of 16-bit ANDs with two AIRs and reports their column counts and proving
+//! times:
+//!
+//! - the bit-decomposition AIR splits both operands into 16 boolean columns each and computes
+//!   `sum(2^i * a_i * b_i)`;
+//! - the lookup AIR splits the operands and the result into bytes and looks up every byte triple
+//!   in the table of byte ANDs with a LogUp argument, which is how the ALU chip proves AND. Like
+//!   the bitwise chip, it keeps the table in periodic columns rather than in committed ones.
+//!
+//! The ALU chip looks up `NOT` in the same table, as the AND of its operand with `0xFFFF`.
+//!
+//! Run with `cargo bench --bench bitwise`, optionally followed by `-- <log2 rows>`. The table has
+//! 65536 entries and the running sum leaves out the last row, so there are at least `2^17` rows.
+
+use lc3_zkvm::air::lc3::{AUX_WIDTH, TRACE_WIDTH};
+use lc3_zkvm::air::{alu, bitwise};
+use lc3_zkvm::air::{Air, BoundaryConstraint, TraceTable};
+use lc3_zkvm::field::{batch_inverse, Felt, FieldElement, QuadExt};
+use lc3_zkvm::proof::ProofOptions;
+use lc3_zkvm::prover::prove_air;
+use lc3_zkvm::transcript::{Sha256Sponge, Transcript};
+use lc3_zkvm::verifier::verify_air;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+const TABLE_SIZE: usize = bitwise::TABLE_SIZE;
+
+fn felt(value: u64) -> Felt {
+    Felt::new(value)
+}
+
+/// `sum(radix^i * digits[i])`
+fn pack<E: FieldElement>(digits: &[E], radix: u64) -> E {
+    digits
+        .iter()
+        .rev()
+        .fold(E::ZERO, |acc, digit| acc * E::from(felt(radix)) + *digit)
+}
+
+/// Operand pairs from a xorshift generator, so that both AIRs prove the same table
+fn operands(rows: usize) -> Vec<(u16, u16)> {
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    (0..rows)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state as u16, (state >> 16) as u16)
+        })
+        .collect()
+}
+
+/// Columns `a`, `b`, `a & b`, then the bits of `a` and of `b`
+struct BitAir {
+    rows: usize,
+}
+
+impl BitAir {
+    const WIDTH: usize = 3 + 32;
+
+    fn table(&self) -> TraceTable {
+        let mut columns = vec![Vec::new(); Self::WIDTH];
+        for (a, b) in operands(self.rows) {
+            let mut row = vec![a as u64, b as u64, (a & b) as u64];
+            row.extend((0..16).map(|i| (a >> i & 1) as u64));
+            row.extend((0..16).map(|i| (b >> i & 1) as u64));
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(felt(value));
+            }
+        }
+        TraceTable { columns }
+    }
+}
+
+impl Air for BitAir {
+    fn trace_width(&self) -> usize {
+        Self::WIDTH
+    }
+
+    fn trace_length(&self) -> usize {
+        self.rows
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        32 + 3
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        2
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        _next: &[E],
+        _challenges: &[E],
+        result: &mut [E],
+    ) {
+        let (a_bits, b_bits) = current[3..].split_at(16);
+        for (out, bit) in result.iter_mut().zip(&current[3..]) {
+            *out = *bit * (*bit - E::ONE);
+        }
+        let and = a_bits
+            .iter()
+            .zip(b_bits)
+            .rev()
+            .fold(E::ZERO, |acc, (a, b)| acc + acc + *a * *b);
+        result[32] = current[0] - pack(a_bits, 2);
+        result[33] = current[1] - pack(b_bits, 2);
+        result[34] = current[2] - and;
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        let (a, b) = operands(1)[0];
+        vec![BoundaryConstraint {
+            column: 2,
+            row: 0,
+            value: Felt::from(a & b).into(),
+        }]
+    }
+}
+
+/// Columns `a`, `b`, `a & b` and their bytes, and the multiplicities of the byte table, which
+/// periodic columns hold. Auxiliary columns: the inverses of the pair of lookups, the table term and
+/// the running sum.
+struct LookupAir {
+    rows: usize,
+}
+
+impl LookupAir {
+    const BYTES: usize = 3;
+    const MULT: usize = Self::BYTES + 6;
+    const WIDTH: usize = Self::MULT + 1;
+    const INVERSES: usize = Self::WIDTH;
+    const TERM: usize = Self::INVERSES + 1;
+    const SUM: usize = Self::TERM + 1;
+    const TABLE: usize = Self::SUM + 1;
+
+    fn table(&self) -> TraceTable {
+        let mut columns = vec![vec![Felt::ZERO; self.rows]; Self::WIDTH];
+        let mut counts = vec![0u64; TABLE_SIZE];
+        for (row, (a, b)) in operands(self.rows).into_iter().enumerate() {
+            for (k, value) in [a, b, a & b].into_iter().enumerate() {
+                columns[k][row] = Felt::from(value);
+                for i in 0..2 {
+                    columns[Self::BYTES + 2 * k + i][row] = Felt::from(value >> (8 * i) & 0xFF);
+                }
+            }
+            if row + 1 < self.rows {
+                for i in 0..2 {
+                    counts
+                        [(a >> (8 * i) & 0xFF) as usize + 256 * (b >> (8 * i) & 0xFF) as usize] +=
+                        1;
+                }
+            }
+        }
+        for (entry, count) in counts.iter().enumerate() {
+            columns[Self::MULT][entry] = felt(*count);
+        }
+        TraceTable { columns }
+    }
+
+    /// `a + delta * b + delta^2 * c`
+    fn entry<E: FieldElement>(delta: E, a: E, b: E, c: E) -> E {
+        a + delta * (b + delta * c)
+    }
+
+    fn lookups<E: FieldElement>(row: &[E], delta: E) -> [E; 2] {
+        let byte = |k: usize, i: usize| row[Self::BYTES + 2 * k + i];
+        [0, 1].map(|i| Self::entry(delta, byte(0, i), byte(1, i), byte(2, i)))
+    }
+
+    fn table_entry<E: FieldElement>(row: &[E], delta: E) -> E {
+        let table = &row[Self::TABLE..Self::TABLE + 3];
+        Self::entry(delta, table[0], table[1], table[2])
+    }
+}
+
+impl Air for LookupAir {
+    fn trace_width(&self) -> usize {
+        Self::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        Self::SUM + 1 - Self::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        2
+    }
+
+    fn trace_length(&self) -> usize {
+        self.rows
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        3 + 1 + 2
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        cur: &[E],
+        nxt: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let (beta, delta) = (challenges[0], challenges[1]);
+        let mut out = result.iter_mut();
+        let mut emit = |value: E| *out.next().unwrap() = value;
+        for k in 0..3 {
+            let bytes = &cur[Self::BYTES + 2 * k..Self::BYTES + 2 * k + 2];
+            emit(cur[k] - pack(bytes, 256));
+        }
+
+        let [x, y] = Self::lookups(cur, delta).map(|value| beta - value);
+        emit(cur[Self::INVERSES] * x * y - x - y);
+        let term = cur[Self::TERM];
+        emit(term * (beta - Self::table_entry(cur, delta)) - cur[Self::MULT]);
+        emit(nxt[Self::SUM] - cur[Self::SUM] - cur[Self::INVERSES] + term);
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        let (a, b) = operands(1)[0];
+        let pin = |column: usize, row: usize, value: Felt| BoundaryConstraint {
+            column,
+            row,
+            value: value.into(),
+        };
+        vec![
+            pin(2, 0, Felt::from(a & b)),
+            pin(Self::SUM, 0, Felt::ZERO),
+            pin(Self::SUM, self.rows - 1, Felt::ZERO),
+        ]
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let (beta, delta) = (challenges[0], challenges[1]);
+        let periodic = self.periodic_columns();
+        let mut row = vec![QuadExt::ZERO; Self::TABLE + 3];
+        let mut differences = Vec::with_capacity(self.rows * 3);
+        for index in 0..self.rows {
+            table.read_row(index, &mut row);
+            for (value, column) in row[Self::TABLE..].iter_mut().zip(&periodic) {
+                *value = QuadExt::from(column[index % TABLE_SIZE]);
+            }
+            differences.extend(Self::lookups(&row, delta).map(|value| beta - value));
+            differences.push(beta - Self::table_entry(&row, delta));
+        }
+        let inverses = batch_inverse(&differences);
+        let mut columns = vec![Vec::new(); self.aux_width()];
+        let mut sum = QuadExt::ZERO;
+        for (index, row_inverses) in inverses.chunks(3).enumerate() {
+            columns[2].push(sum);
+            let value = row_inverses[0] + row_inverses[1];
+            columns[0].push(value);
+            sum += value;
+            let term = row_inverses[2].mul_base(table.columns[Self::MULT][index]);
+            columns[1].push(term);
+            sum -= term;
+        }
+        columns
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<Felt>> {
+        let column = |value: fn(u64, u64) -> u64| {
+            (0..TABLE_SIZE as u64)
+                .map(|entry| felt(value(entry & 0xFF, entry >> 8)))
+                .collect()
+        };
+        vec![column(|x, _| x), column(|_, y| y), column(|x, y| x & y)]
+    }
+}
+
+/// Prove and verify `table` against `air` `RUNS` times, returning the median proving time
+fn bench<A: Air>(name: &str, air: &A, table: &TraceTable) -> Duration {
+    let options = ProofOptions::default();
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let proof = prove_air(
+                air,
+                table,
+                &options,
+                &mut Transcript::<Sha256Sponge>::new(b"bitwise"),
+            )
+            .expect("proving failed");
+            let elapsed = start.elapsed();
+            verify_air(
+                air,
+                &proof,
+                &mut Transcript::<Sha256Sponge>::new(b"bitwise"),
+            )
+            .expect("invalid proof");
+            elapsed
+        })
+        .collect();
+    times.sort();
+    println!(
+        "{:<18} {:>5} main + {} aux columns  {:>10.1?}",
+        name,
+        air.trace_width(),
+        air.aux_width(),
+        times[RUNS / 2]
+    );
+    times[RUNS / 2]
+}
+
+fn main() {
+    let log_rows: u32 = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(17)
+        .max(17);
+    let rows = 1 << log_rows;
+    println!(
+        "{} ANDs of 16-bit operands, median of {} proofs",
+        rows, RUNS
+    );
+
+    let bits = BitAir { rows };
+    let bit_time = bench("bit decomposition", &bits, &bits.table());
+    let lookup = LookupAir { rows };
+    let lookup_time = bench("byte lookups", &lookup, &lookup.table());
+    println!(
+        "lookups take {:.2}x the proving time of bit decomposition",
+        lookup_time.as_secs_f64() / bit_time.as_secs_f64()
+    );
+    println!(
+        "LC3 AIR: {} main + {} aux columns; ALU chip: {} main + {} aux columns, one row per \
+         distinct request; bitwise chip: {} main + {} aux columns, {} rows",
+        TRACE_WIDTH,
+        AUX_WIDTH,
+        alu::col::WIDTH,
+        alu::col::FULL_WIDTH - alu::col::WIDTH,
+        bitwise::col::WIDTH,
+        bitwise::col::FULL_WIDTH - bitwise::col::WIDTH,
+        TABLE_SIZE
+    );
+}
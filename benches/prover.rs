@@ -0,0 +1,322 @@
+//! Prover performance on representative LC3 programs
+//!
+//! Runs three programs through every phase of proof generation and reports, per phase, the median
+//! time of several samples, the throughput in LC3 cycles per second and the peak heap memory
+//! allocated while the phase ran:
+//!
+//! - `loop`: an arithmetic loop summing a counter, the baseline ALU and branch workload;
+//! - `memcpy`: a word-by-word copy between two buffers, one load and one store per iteration,
+//!   which stresses the memory argument;
+//! - `echo`: reads every byte of an input tape with `GETC` and writes it back with `OUT`, which
+//!   stresses the I/O argument.
+//!
+//! The phases are:
+//!
+//! - `execute`: running the program and recording its execution trace;
+//! - `trace table`: building the padded trace table of the LC3 AIR from the execution trace;
+//! - `commit`: interpolating the main columns, evaluating them over the LDE domain and building
+//!   the Merkle tree over the LDE rows, as the first step of the prover does;
+//! - `prove`: all of [`prove`], from the execution trace to the proof, including the two phases
+//!   above.
+//!
+//! Like criterion, every phase runs once to warm up before its samples are timed. Peak memory is
+//! tracked by a counting global allocator and excludes what was allocated before the phase began.
+//!
+//! Run with `cargo bench --bench prover`, optionally followed by `-- <log2 cycles>` and the names of
+//! the programs to run.
+
+// Binary literals are grouped by LC3 instruction fields.
+#![allow(clippy::unusual_byte_groupings)]
+
+use lc3_zkvm::air::{build_padded_trace_table, TraceTable, MAX_CONSTRAINT_DEGREE};
+use lc3_zkvm::field::{evaluate_on_coset, intt, Felt};
+use lc3_zkvm::io::IoTape;
+use lc3_zkvm::memory::Memory;
+use lc3_zkvm::merkle::{hash_row, Digest, MerkleTree, Sha256Hasher};
+use lc3_zkvm::proof::ProofOptions;
+use lc3_zkvm::prover::prove;
+use lc3_zkvm::register::{Register, RegisterFile};
+use lc3_zkvm::trace::ExecutionTrace;
+use lc3_zkvm::utils::execute_program_with_io;
+use lc3_zkvm::verifier::verify;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const SAMPLES: usize = 5;
+const ORIGIN: u16 = 0x3000;
+
+/// The system allocator, keeping count of the bytes currently allocated and of their peak
+struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+impl PeakAllocator {
+    fn grow(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+
+    fn shrink(size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    /// Restart peak tracking, returning the bytes allocated at this point
+    fn reset() -> usize {
+        let allocated = ALLOCATED.load(Ordering::Relaxed);
+        PEAK.store(allocated, Ordering::Relaxed);
+        allocated
+    }
+
+    fn peak() -> usize {
+        PEAK.load(Ordering::Relaxed)
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::shrink(layout.size());
+            Self::grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+/// A program loaded at [`ORIGIN`], with its data and input tape
+struct Program {
+    name: &'static str,
+    code: Vec<u16>,
+    data: Vec<(u16, u16)>,
+    input: Vec<u8>,
+}
+
+impl Program {
+    /// Sum `iterations` down to 1 into R0
+    fn sum_loop(iterations: u16) -> Self {
+        Program {
+            name: "loop",
+            code: vec![
+                0b0101_000_000_1_00000,  // AND R0, R0, #0
+                0b0010_001_000000100,    // LD R1, #4 (iterations)
+                0b0001_000_000_0_00_001, // ADD R0, R0, R1
+                0b0001_001_001_1_11111,  // ADD R1, R1, #-1
+                0b0000_001_111111101,    // BRp #-3
+                0xF025,                  // HALT
+                iterations,
+            ],
+            data: Vec::new(),
+            input: Vec::new(),
+        }
+    }
+
+    /// Copy `words` words from x4000 to x5000
+    fn memcpy(words: u16) -> Self {
+        Program {
+            name: "memcpy",
+            code: vec![
+                0b0010_001_000001001,   // LD R1, #9 (source)
+                0b0010_010_000001001,   // LD R2, #9 (destination)
+                0b0010_011_000001001,   // LD R3, #9 (words)
+                0b0110_100_001_000000,  // LDR R4, R1, #0
+                0b0111_100_010_000000,  // STR R4, R2, #0
+                0b0001_001_001_1_00001, // ADD R1, R1, #1
+                0b0001_010_010_1_00001, // ADD R2, R2, #1
+                0b0001_011_011_1_11111, // ADD R3, R3, #-1
+                0b0000_001_111111010,   // BRp #-6
+                0xF025,                 // HALT
+                0x4000,
+                0x5000,
+                words,
+            ],
+            data: (0..words)
+                .map(|i| (0x4000 + i, i.wrapping_mul(0x9E37)))
+                .collect(),
+            input: Vec::new(),
+        }
+    }
+
+    /// Echo `bytes` bytes of the input tape to the journal
+    fn echo(bytes: u16) -> Self {
+        Program {
+            name: "echo",
+            code: vec![
+                0b0010_001_000000101,   // LD R1, #5 (bytes)
+                0xF020,                 // GETC
+                0xF021,                 // OUT
+                0b0001_001_001_1_11111, // ADD R1, R1, #-1
+                0b0000_001_111111100,   // BRp #-4
+                0xF025,                 // HALT
+                bytes,
+            ],
+            data: Vec::new(),
+            input: (0..bytes).map(|i| b'a' + (i % 26) as u8).collect(),
+        }
+    }
+
+    /// The three programs, sized to run for a little under `cycles` cycles
+    fn all(cycles: usize) -> Vec<Self> {
+        let iterations =
+            |steps_per_iteration: usize| (cycles * 15 / 16 / steps_per_iteration) as u16;
+        vec![
+            Program::sum_loop(iterations(3)),
+            Program::memcpy(iterations(6)),
+            Program::echo(iterations(4)),
+        ]
+    }
+
+    fn load(&self) -> (Memory, RegisterFile) {
+        let mut memory = Memory::new();
+        for (i, word) in self.code.iter().enumerate() {
+            memory.write(ORIGIN + i as u16, *word);
+        }
+        for (address, value) in &self.data {
+            memory.write(*address, *value);
+        }
+        let mut registers = RegisterFile::new();
+        registers.write(Register::PC, ORIGIN);
+        (memory, registers)
+    }
+
+    fn execute(&self) -> ExecutionTrace {
+        let (mut memory, mut registers) = self.load();
+        let mut tape = IoTape::new(self.input.clone());
+        execute_program_with_io(&mut memory, &mut registers, &mut tape).expect("execution failed")
+    }
+}
+
+/// Interpolate the main columns, evaluate them over the LDE domain and commit to the LDE rows
+fn commit(table: &TraceTable, options: &ProofOptions) -> Digest {
+    let lde_size = table.length() * options.blowup_factor;
+    let lde: Vec<Vec<Felt>> = table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut coefficients = column.clone();
+            intt(&mut coefficients);
+            evaluate_on_coset(&coefficients, Felt::GENERATOR, lde_size)
+        })
+        .collect();
+    let mut row = vec![Felt::new(0); lde.len()];
+    let leaves = (0..lde_size)
+        .map(|i| {
+            for (value, column) in row.iter_mut().zip(&lde) {
+                *value = column[i];
+            }
+            hash_row(&row)
+        })
+        .collect();
+    MerkleTree::<Sha256Hasher>::new(leaves).root()
+}
+
+/// Median time and peak memory of a phase over [`SAMPLES`] runs
+struct Measurement {
+    time: Duration,
+    peak_bytes: usize,
+}
+
+/// Run `phase` once to warm up, then [`SAMPLES`] times, returning its measurement and its output
+fn measure<T>(mut phase: impl FnMut() -> T) -> (Measurement, T) {
+    let mut output = phase();
+    let mut times = Vec::with_capacity(SAMPLES);
+    let mut peak_bytes = 0;
+    for _ in 0..SAMPLES {
+        drop(output);
+        let baseline = PeakAllocator::reset();
+        let start = Instant::now();
+        output = phase();
+        times.push(start.elapsed());
+        peak_bytes = peak_bytes.max(PeakAllocator::peak() - baseline);
+    }
+    times.sort();
+    let time = times[SAMPLES / 2];
+    (Measurement { time, peak_bytes }, output)
+}
+
+fn report(phase: &str, cycles: usize, measurement: &Measurement) {
+    println!(
+        "  {:<12} {:>10.1?}  {:>12.0} cycles/s  {:>9.1} MiB peak",
+        phase,
+        measurement.time,
+        cycles as f64 / measurement.time.as_secs_f64(),
+        measurement.peak_bytes as f64 / (1 << 20) as f64
+    );
+}
+
+fn bench(program: &Program, options: &ProofOptions) {
+    let initial_memory = program.load().0;
+    let (execute, trace) = measure(|| program.execute());
+    let cycles = trace.len();
+    let min_length = options.min_trace_length(MAX_CONSTRAINT_DEGREE);
+    let (build, table) =
+        measure(|| build_padded_trace_table(&trace, min_length).expect("invalid trace"));
+    let (commitment, _) = measure(|| commit(&table, options));
+    let (proving, (public_inputs, proof)) =
+        measure(|| prove(&initial_memory, &trace, options).expect("proving failed"));
+    verify(&public_inputs, &proof).expect("invalid proof");
+
+    println!(
+        "{}: {} cycles, {} rows x {} columns",
+        program.name,
+        cycles,
+        table.length(),
+        table.width()
+    );
+    report("execute", cycles, &execute);
+    report("trace table", cycles, &build);
+    report("commit", cycles, &commitment);
+    report("prove", cycles, &proving);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    let log_cycles: u32 = args
+        .iter()
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(12)
+        .clamp(9, 16);
+    let names: Vec<&String> = args
+        .iter()
+        .filter(|arg| arg.parse::<u32>().is_err())
+        .collect();
+
+    let options = ProofOptions::default();
+    println!(
+        "Median of {} samples, blowup factor {}, {} queries",
+        SAMPLES, options.blowup_factor, options.num_queries
+    );
+    for program in Program::all(1 << log_cycles) {
+        if names.is_empty() || names.iter().any(|name| *name == program.name) {
+            bench(&program, &options);
+        }
+    }
+}
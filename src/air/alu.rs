@@ -0,0 +1,550 @@
+//! ALU Chip
+//!
+//! This module proves the results of the LC3 ALU instructions, `ADD`, `AND` and `NOT`, in a table
+//! of its own, connected to the [LC3 table](super::lc3) by the [bus](super::bus). The LC3 table
+//! sends every ALU instruction as a request `(operation, a, b, result)`; this table answers every
+//! distinct request once, together with the number of times it was made.
+//!
+//! ## Design
+//! - A row holds a request: a one-hot selector of the operation, both operands, the result, the
+//!   carry of `ADD` and the multiplicity of the request. Rows selecting no operation pad the table
+//!   to a power of two, and answer nothing.
+//! - `ADD` is `a + b = result + 2^16 * carry`. The LC3 table range-checks the result, which it
+//!   writes to a register, so a boolean carry is enough to pin the sum.
+//! - `AND` and `NOT` are proven with lookups into the byte AND table of the
+//!   [bitwise chip](super::bitwise), which holds `(x, y, x & y)` for every pair of bytes. A row of
+//!   `AND` splits both operands into bytes and looks up the two triples of bytes at the same
+//!   position; the looked-up ANDs are the bytes of the result. A row of `NOT` looks up the bytes
+//!   of its operand against `0xFF`, which shows that they are bytes, and its result is
+//!   `0xFFFF - a`. The lookups are bus messages sent once per row, whatever the multiplicity of
+//!   the request.
+//! - The bus running sum adds the two lookups of a row through an auxiliary column holding the
+//!   sum of their inverse fingerprints, which keeps the constraint degree at 3.
+//! - The table grows with the number of distinct ALU requests of a run rather than with its
+//!   length, and the LC3 table keeps no column for the bytes of the operands.
+//! - That height would reveal how many distinct requests a run makes, so zero-knowledge proofs
+//!   pad the table to the height of the LC3 table instead, and prove it even for runs without
+//!   ALU instructions.
+
+use super::bus::{self, Message, MESSAGE_WIDTH};
+use super::{Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::opcode::Opcode;
+use crate::trace::{ExecutionTrace, Step};
+use alloc::collections::BTreeMap;
+use alloc::{vec, vec::Vec};
+
+/// Number of byte triples a row of `AND` or `NOT` looks up
+pub const LOOKUPS: usize = 2;
+
+/// Smallest table the prover builds
+pub const MIN_TRACE_LENGTH: usize = 8;
+
+/// Highest degree of the constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Column indices of the ALU trace table
+pub mod col {
+    /// One-hot selectors of the operation, all zero on padding rows
+    pub const ADD: usize = 0;
+    pub const AND: usize = ADD + 1;
+    pub const NOT: usize = AND + 1;
+    /// First operand
+    pub const A: usize = NOT + 1;
+    /// Second operand
+    pub const B: usize = A + 1;
+    /// Result of the operation
+    pub const RES: usize = B + 1;
+    /// For `AND` and `NOT`: bytes of the first operand and of the operand it is ANDed with, least
+    /// significant first. `NOT` ANDs with `0xFFFF`
+    pub const A_BYTES: usize = RES + 1;
+    pub const B_BYTES: usize = A_BYTES + super::LOOKUPS;
+    /// For `AND` and `NOT`: bytes of the AND looked up in the byte table
+    pub const AND_BYTES: usize = B_BYTES + super::LOOKUPS;
+    /// Carry out of `ADD`
+    pub const CARRY: usize = AND_BYTES + super::LOOKUPS;
+    /// Number of times the LC3 table makes the request of the row
+    pub const MULT: usize = CARRY + 1;
+    /// Number of main columns
+    pub const WIDTH: usize = MULT + 1;
+
+    /// Auxiliary: running sum of the bus
+    pub const BUS_SUM: usize = WIDTH;
+    /// Auxiliary: sum of the inverse fingerprints of the byte lookups of the row
+    pub const LOOKUP_INVERSES: usize = BUS_SUM + 1;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = LOOKUP_INVERSES + 1;
+}
+
+/// The ALU chip AIR
+pub struct AluAir {
+    trace_length: usize,
+    num_constraints: usize,
+}
+
+impl AluAir {
+    pub fn new(trace_length: usize) -> Self {
+        let mut num_constraints = 0;
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |_, _| num_constraints += 1);
+        AluAir {
+            trace_length,
+            num_constraints,
+        }
+    }
+}
+
+impl Air for AluAir {
+    fn trace_width(&self) -> usize {
+        col::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        col::FULL_WIDTH - col::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let mut index = 0;
+        evaluate(current, next, challenges, |_, value| {
+            result[index] = value;
+            index += 1;
+        });
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::with_capacity(self.num_constraints);
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |name, _| names.push(name));
+        names
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        vec![BoundaryConstraint {
+            column: col::BUS_SUM,
+            row: 0,
+            value: QuadExt::ZERO,
+        }]
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let mut row = vec![QuadExt::ZERO; col::WIDTH];
+        let mut messages = Vec::with_capacity(table.length() * (LOOKUPS + 1));
+        for index in 0..table.length() {
+            table.read_row(index, &mut row);
+            messages.push(message(&row));
+            messages.extend(lookups(&row));
+        }
+        let terms = bus::terms(challenges, &messages);
+        let rows = terms.chunks(LOOKUPS + 1);
+        let inverses: Vec<QuadExt> = rows
+            .clone()
+            .map(|terms| {
+                terms[1..]
+                    .iter()
+                    .fold(QuadExt::ZERO, |acc, term| acc + *term)
+            })
+            .collect();
+        let row_sums = rows
+            .zip(&inverses)
+            .map(|(terms, inverses)| terms[0] + *inverses);
+        vec![bus::running_sum(row_sums), inverses]
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+fn constant<E: FieldElement>(value: u64) -> E {
+    E::from(Felt::new(value))
+}
+
+/// `sum(256^i * bytes[i])`
+fn pack<E: FieldElement>(bytes: &[E]) -> E {
+    let radix = constant::<E>(256);
+    bytes
+        .iter()
+        .rev()
+        .fold(E::ZERO, |acc, byte| acc * radix + *byte)
+}
+
+/// The operand the first operand of an `AND` or `NOT` request is ANDed with in the byte table
+fn and_operand(selector: usize, b: u16) -> u16 {
+    if selector == col::AND {
+        b
+    } else {
+        0xFFFF
+    }
+}
+
+/// The byte triples a row looks up in the byte table, sent once by rows of `AND` and `NOT`
+fn lookups<E: FieldElement>(row: &[E]) -> [Message<E>; LOOKUPS] {
+    core::array::from_fn(|i| Message {
+        multiplicity: row[col::AND] + row[col::NOT],
+        values: [
+            constant(bus::BYTE_AND),
+            row[col::A_BYTES + i],
+            row[col::B_BYTES + i],
+            row[col::AND_BYTES + i],
+            E::ZERO,
+        ],
+    })
+}
+
+/// The request a row answers, received as many times as the LC3 table makes it
+fn message<E: FieldElement>(row: &[E]) -> Message<E> {
+    let tag = row[col::ADD] * constant(bus::ALU_ADD)
+        + row[col::AND] * constant(bus::ALU_AND)
+        + row[col::NOT] * constant(bus::ALU_NOT);
+    let values: [E; MESSAGE_WIDTH] = [tag, row[col::A], row[col::B], row[col::RES], E::ZERO];
+    Message {
+        multiplicity: -row[col::MULT],
+        values,
+    }
+}
+
+/// Evaluate all transition constraints, passing each to `emit` with its name
+fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    mut emit: F,
+) {
+    let one = E::ONE;
+    let (s_add, s_and, s_not) = (cur[col::ADD], cur[col::AND], cur[col::NOT]);
+    for selector in [s_add, s_and, s_not] {
+        emit("operation selector is boolean", selector * (selector - one));
+    }
+    let selected = s_add + s_and + s_not;
+    emit("at most one operation", selected * (selected - one));
+    emit(
+        "padding row answers nothing",
+        (one - selected) * cur[col::MULT],
+    );
+
+    let (a, b, res) = (cur[col::A], cur[col::B], cur[col::RES]);
+    let carry = cur[col::CARRY];
+    emit("carry is boolean", carry * (carry - one));
+    emit(
+        "ADD result",
+        s_add * (a + b - res - carry * constant(1 << 16)),
+    );
+    let bytes = |column: usize| pack(&cur[column..column + LOOKUPS]);
+    emit(
+        "operand A bytes",
+        (s_and + s_not) * (a - bytes(col::A_BYTES)),
+    );
+    emit("AND operand B bytes", s_and * (b - bytes(col::B_BYTES)));
+    for i in 0..LOOKUPS {
+        emit(
+            "NOT ANDs with 0xFF",
+            s_not * (cur[col::B_BYTES + i] - constant(0xFF)),
+        );
+    }
+    emit("AND result", s_and * (res - bytes(col::AND_BYTES)));
+    emit(
+        "NOT result",
+        s_not * (res + bytes(col::AND_BYTES) - constant(0xFFFF)),
+    );
+
+    let [x, y] = lookups(cur).map(|lookup| bus::fingerprint(challenges, &lookup.values));
+    let inverses = cur[col::LOOKUP_INVERSES];
+    emit(
+        "byte lookup inverses",
+        inverses * x * y - (s_and + s_not) * (x + y),
+    );
+    let message = message(cur);
+    emit(
+        "bus running sum",
+        (nxt[col::BUS_SUM] - cur[col::BUS_SUM] - inverses)
+            * bus::fingerprint(challenges, &message.values)
+            - message.multiplicity,
+    );
+}
+
+/// The request of an ALU instruction: its operation selector and operands
+fn request(step: &Step) -> Option<(usize, u16, u16)> {
+    let selector = match step.opcode {
+        Opcode::OP_ADD => col::ADD,
+        Opcode::OP_AND => col::AND,
+        Opcode::OP_NOT => col::NOT,
+        _ => return None,
+    };
+    let instruction = step.instruction;
+    let a = step.before.gprs[(instruction >> 6 & 0x7) as usize];
+    let b = if instruction >> 5 & 1 == 1 {
+        let imm = instruction & 0x1F;
+        if imm >> 4 == 1 {
+            imm | 0xFFE0
+        } else {
+            imm
+        }
+    } else {
+        step.before.gprs[(instruction & 0x7) as usize]
+    };
+    Some((selector, a, b))
+}
+
+/// The distinct ALU requests of a run, with the number of times each is made
+fn requests(trace: &ExecutionTrace) -> BTreeMap<(usize, u16, u16), u64> {
+    let mut requests = BTreeMap::new();
+    for request in trace.steps.iter().filter_map(request) {
+        *requests.entry(request).or_insert(0u64) += 1;
+    }
+    requests
+}
+
+/// The byte pairs the ALU table of a run looks up in the byte AND table, two for every distinct
+/// `AND` or `NOT` request
+pub(super) fn byte_lookups(trace: &ExecutionTrace) -> Vec<(u8, u8)> {
+    let mut pairs = Vec::new();
+    for (selector, a, b) in requests(trace).into_keys() {
+        if selector != col::ADD {
+            let b = and_operand(selector, b);
+            pairs.extend((0..LOOKUPS).map(|i| ((a >> (8 * i)) as u8, (b >> (8 * i)) as u8)));
+        }
+    }
+    pairs
+}
+
+/// Lay out the distinct ALU requests of a run as an ALU trace table of at least `min_length` rows,
+/// or return `None` if the run makes none
+pub fn build_trace_table(
+    trace: &ExecutionTrace,
+    min_length: usize,
+) -> Result<Option<TraceTable>, &'static str> {
+    let requests = requests(trace);
+    if requests.is_empty() {
+        return Ok(None);
+    }
+    // The last row must answer nothing
+    let length = (requests.len() + 1)
+        .next_power_of_two()
+        .max(min_length.next_power_of_two())
+        .max(MIN_TRACE_LENGTH);
+    lay_out(&requests, length).map(Some)
+}
+
+/// Lay out the ALU requests of a run as an ALU trace table of `length` rows, rounded up to a power
+/// of two, even if the run makes none.
+///
+/// The height of the table depends only on `length`, not on the requests, which is what a
+/// zero-knowledge proof needs: `length` is that of the LC3 table, which has a row for every step.
+pub fn build_hiding_trace_table(
+    trace: &ExecutionTrace,
+    length: usize,
+) -> Result<TraceTable, &'static str> {
+    let requests = requests(trace);
+    let length = length.next_power_of_two().max(MIN_TRACE_LENGTH);
+    if requests.len() >= length {
+        return Err("Too many ALU requests");
+    }
+    lay_out(&requests, length)
+}
+
+/// Write one row per request, followed by padding rows
+fn lay_out(
+    requests: &BTreeMap<(usize, u16, u16), u64>,
+    length: usize,
+) -> Result<TraceTable, &'static str> {
+    if length > super::MAX_TRACE_LENGTH {
+        return Err("Too many ALU requests");
+    }
+    let mut columns = vec![vec![Felt::ZERO; length]; col::WIDTH];
+    for (row, (&(selector, a, b), &count)) in requests.iter().enumerate() {
+        let (res, carry) = match selector {
+            col::ADD => a.overflowing_add(b),
+            col::AND => (a & b, false),
+            _ => (!a, false),
+        };
+        let mut set = |column: usize, value: u64| columns[column][row] = Felt::new(value);
+        set(selector, 1);
+        set(col::A, a as u64);
+        set(col::B, b as u64);
+        set(col::RES, res as u64);
+        set(col::CARRY, carry as u64);
+        set(col::MULT, count);
+        if selector != col::ADD {
+            let b = and_operand(selector, b);
+            for i in 0..LOOKUPS {
+                let byte = |word: u16| (word >> (8 * i) & 0xFF) as u64;
+                set(col::A_BYTES + i, byte(a));
+                set(col::B_BYTES + i, byte(b));
+                set(col::AND_BYTES + i, byte(a & b));
+            }
+        }
+    }
+    Ok(TraceTable { columns })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::lc3::{self, Lc3Air};
+    use crate::air::test_utils::{bus_balance, bus_sum, challenges, public_inputs};
+    use crate::air::{check_constraints, Chip, ConstraintViolation};
+    use crate::io::IoTape;
+    use crate::memory::Memory;
+    use crate::register::{Register, RegisterFile};
+    use crate::utils::execute_program_with_io;
+
+    /// Run a program using every ALU operation, with a carry and a repeated request
+    fn alu_run() -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let program = [
+            0b0010_001_000000110,    // 3000 LD R1, #6 -> 0x8001
+            0b0001_010_001_0_00_001, // 3001 ADD R2, R1, R1
+            0b0001_010_010_1_11111,  // 3002 ADD R2, R2, #-1
+            0b0101_011_001_0_00_010, // 3003 AND R3, R1, R2
+            0b1001_100_011_111111,   // 3004 NOT R4, R3
+            0b0001_010_001_0_00_001, // 3005 ADD R2, R1, R1
+            0xF025,                  // 3006 HALT
+            0x8001,
+        ];
+        let mut memory = Memory::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        let mut registers = RegisterFile::new();
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(Vec::new());
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        assert_eq!(registers.read(Register::R4), 0xFFFE);
+        (image, trace)
+    }
+
+    #[test]
+    fn test_chip_satisfies_constraints() {
+        let (image, trace) = alu_run();
+        let table = build_trace_table(&trace, 0).unwrap().unwrap();
+        assert_eq!(table.width(), col::WIDTH);
+        assert_eq!(table.length(), MIN_TRACE_LENGTH);
+        // The requests are sorted by operation, then operands: the repeated ADD comes second
+        assert_eq!(table.columns[col::A][1], Felt::new(0x8001));
+        assert_eq!(table.columns[col::RES][1], Felt::new(2));
+        assert_eq!(table.columns[col::CARRY][1], Felt::ONE);
+        assert_eq!(table.columns[col::MULT][1], Felt::new(2));
+        let air = AluAir::new(table.length());
+        assert_eq!(
+            air.transition_constraint_names().len(),
+            air.num_transition_constraints()
+        );
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        let lc3_table = lc3::build_trace_table(&trace).unwrap();
+        let lc3_air = Lc3Air::new(lc3_table.length(), public_inputs(&image, &trace));
+        assert_eq!(check_constraints(&lc3_air, &lc3_table), Ok(()));
+
+        // An AND result that does not match its bytes
+        let mut forged = table.clone();
+        assert_eq!(forged.columns[col::AND][2], Felt::ONE);
+        forged.columns[col::RES][2] += Felt::ONE;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "AND result");
+                assert_eq!(row, 2);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // A NOT operand that does not match its bytes
+        let mut forged = table.clone();
+        assert_eq!(forged.columns[col::NOT][3], Felt::ONE);
+        forged.columns[col::A][3] += Felt::ONE;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "operand A bytes");
+                assert_eq!(row, 3);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // A padding row answering a request
+        let mut forged = table.clone();
+        forged.columns[col::MULT][MIN_TRACE_LENGTH - 2] = Felt::ONE;
+        assert!(check_constraints(&air, &forged).is_err());
+
+        // A run without ALU instructions needs no chip
+        let mut other = trace.clone();
+        other.steps.retain(|step| request(step).is_none());
+        assert!(build_trace_table(&other, 0).unwrap().is_none());
+        assert!(byte_lookups(&other).is_empty());
+    }
+
+    #[test]
+    fn test_bus_links_the_tables() {
+        let (image, trace) = alu_run();
+        let table = build_trace_table(&trace, 0).unwrap().unwrap();
+        let air = AluAir::new(table.length());
+        let challenges = challenges();
+        // Everything else on the bus, the byte table included
+        let others = bus_balance(
+            &public_inputs(&image, &trace),
+            &trace,
+            Some(Chip::Alu),
+            &challenges,
+        );
+        assert_ne!(others, QuadExt::ZERO);
+        let balance = |table: &TraceTable| others + bus_sum(&air, table, &challenges);
+        assert_eq!(balance(&table), QuadExt::ZERO);
+
+        // Answering the ADD without its carry satisfies the chip constraints, but the result is
+        // not a word, so the LC3 table never requests it
+        let mut forged = table.clone();
+        forged.columns[col::RES][1] += Felt::new(1 << 16);
+        forged.columns[col::CARRY][1] = Felt::ZERO;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+
+        // Answering a request one time too few
+        let mut forged = table.clone();
+        forged.columns[col::MULT][1] -= Felt::ONE;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+
+        // A wrong AND result with matching bytes: 0x8001 & 1 is not 2
+        let mut forged = table.clone();
+        assert_eq!(forged.columns[col::AND_BYTES][2], Felt::ONE);
+        forged.columns[col::RES][2] += Felt::ONE;
+        forged.columns[col::AND_BYTES][2] += Felt::ONE;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+
+        // A wrong NOT result with matching bytes: !1 is not 0xFFFF
+        let mut forged = table.clone();
+        assert_eq!(forged.columns[col::RES][3], Felt::new(0xFFFE));
+        forged.columns[col::RES][3] += Felt::ONE;
+        forged.columns[col::AND_BYTES][3] -= Felt::ONE;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+
+        // Bytes out of range satisfy the packing, but are not in the byte table
+        let mut forged = table.clone();
+        forged.columns[col::A_BYTES][2] += Felt::new(256);
+        forged.columns[col::A_BYTES + 1][2] -= Felt::ONE;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+    }
+}
@@ -0,0 +1,293 @@
+//! Bitwise Chip
+//!
+//! This module holds the byte AND table, `(x, y, x & y)` for every pair of bytes `x` and `y`, in a
+//! table of its own connected to the [ALU chip](super::alu) by the [bus](super::bus). The ALU chip
+//! sends a lookup for every pair of bytes it ANDs; this table receives each lookup on the row of
+//! its pair, as many times as it is made.
+//!
+//! ## Design
+//! - The table is preprocessed: periodic columns with a period of [`TABLE_SIZE`] rows hold `x`,
+//!   `y` and `x & y` on row `x + 256 * y` of every period, so the verifier builds them itself and
+//!   the proof commits to nothing for them. The only main column counts the lookups of each row.
+//! - The bus running sum of this table includes its own row and restarts on the first row of
+//!   every period, flagged by a fourth periodic column. Unlike the sums that leave out the last
+//!   row, it can receive the entry `(0xFF, 0xFF, 0xFF)`, which the last row holds. The prover
+//!   counts the lookups in the last period; counts in earlier periods are left out of the claim.
+//! - Every table of this chip has at least [`TABLE_SIZE`] rows, so proofs only include it if the
+//!   run executes `AND` or `NOT`. Zero-knowledge proofs always include it, at the same height, so
+//!   that neither reveals whether the run does.
+
+use super::bus::{self, Message, MESSAGE_WIDTH};
+use super::{alu, Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::trace::ExecutionTrace;
+use alloc::{vec, vec::Vec};
+
+/// Number of entries of the byte AND table, and of rows over which it repeats
+pub const TABLE_SIZE: usize = 1 << 16;
+
+/// Smallest table the prover builds: one period of the byte AND table
+pub const MIN_TRACE_LENGTH: usize = TABLE_SIZE;
+
+/// Highest degree of the constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Column indices of the bitwise trace table
+pub mod col {
+    /// Number of lookups of the entry of the row
+    pub const MULT: usize = 0;
+    /// Number of main columns
+    pub const WIDTH: usize = MULT + 1;
+
+    /// Auxiliary: running sum of the bus
+    pub const BUS_SUM: usize = WIDTH;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = BUS_SUM + 1;
+
+    /// Periodic: the bytes `x` and `y` of the entry of the row, and `x & y`
+    pub const X: usize = FULL_WIDTH;
+    pub const Y: usize = X + 1;
+    pub const Z: usize = Y + 1;
+    /// Periodic: set on the first row of a period
+    pub const FIRST: usize = Z + 1;
+    /// Number of columns of the rows given to the constraints
+    pub const ROW_WIDTH: usize = FIRST + 1;
+}
+
+/// The bitwise chip AIR
+pub struct BitwiseAir {
+    trace_length: usize,
+}
+
+impl BitwiseAir {
+    pub fn new(trace_length: usize) -> Self {
+        BitwiseAir { trace_length }
+    }
+}
+
+impl Air for BitwiseAir {
+    fn trace_width(&self) -> usize {
+        col::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        col::FULL_WIDTH - col::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        2
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let received = |row: &[E]| {
+            let message = message(row);
+            row[col::BUS_SUM] * bus::fingerprint(challenges, &message.values) - message.multiplicity
+        };
+        result[0] = current[col::FIRST] * received(current);
+        let step = {
+            let message = message(next);
+            (next[col::BUS_SUM] - current[col::BUS_SUM])
+                * bus::fingerprint(challenges, &message.values)
+                - message.multiplicity
+        };
+        result[1] = (E::ONE - next[col::FIRST]) * step;
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        vec!["bus sum starts a period", "bus running sum"]
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        Vec::new()
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let periodic = periodic_columns();
+        let mut row = vec![QuadExt::ZERO; col::ROW_WIDTH];
+        let messages: Vec<Message<QuadExt>> = (0..table.length())
+            .map(|index| {
+                table.read_row(index, &mut row);
+                for (value, column) in row[col::X..].iter_mut().zip(&periodic) {
+                    *value = QuadExt::from(column[index % TABLE_SIZE]);
+                }
+                message(&row)
+            })
+            .collect();
+        let mut sum = QuadExt::ZERO;
+        let column = bus::terms(challenges, &messages)
+            .into_iter()
+            .enumerate()
+            .map(|(index, term)| {
+                if index % TABLE_SIZE == 0 {
+                    sum = QuadExt::ZERO;
+                }
+                sum += term;
+                sum
+            })
+            .collect();
+        vec![column]
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<Felt>> {
+        periodic_columns()
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+/// Values over one period of the periodic columns, in column order
+fn periodic_columns() -> Vec<Vec<Felt>> {
+    let column = |value: fn(u64, u64) -> u64| {
+        (0..TABLE_SIZE as u64)
+            .map(|entry| Felt::new(value(entry & 0xFF, entry >> 8)))
+            .collect()
+    };
+    vec![
+        column(|x, _| x),
+        column(|_, y| y),
+        column(|x, y| x & y),
+        column(|x, y| (x == 0 && y == 0) as u64),
+    ]
+}
+
+/// The lookup a row receives, as many times as it is made
+fn message<E: FieldElement>(row: &[E]) -> Message<E> {
+    let values: [E; MESSAGE_WIDTH] = [
+        E::from(Felt::new(bus::BYTE_AND)),
+        row[col::X],
+        row[col::Y],
+        row[col::Z],
+        E::ZERO,
+    ];
+    Message {
+        multiplicity: -row[col::MULT],
+        values,
+    }
+}
+
+/// Lay out the byte lookups of the ALU table of a run as a bitwise trace table of at least
+/// `min_length` rows, or return `None` if the run makes none
+pub fn build_trace_table(
+    trace: &ExecutionTrace,
+    min_length: usize,
+) -> Result<Option<TraceTable>, &'static str> {
+    let lookups = alu::byte_lookups(trace);
+    if lookups.is_empty() {
+        return Ok(None);
+    }
+    lay_out(&lookups, min_length).map(Some)
+}
+
+/// Lay out the byte lookups of the ALU table of a run as a bitwise trace table of at least
+/// `min_length` rows, even if the run makes none. Its height does not depend on the run.
+pub fn build_hiding_trace_table(
+    trace: &ExecutionTrace,
+    min_length: usize,
+) -> Result<TraceTable, &'static str> {
+    lay_out(&alu::byte_lookups(trace), min_length)
+}
+
+/// Count the lookups of each entry on its row in the last period
+fn lay_out(lookups: &[(u8, u8)], min_length: usize) -> Result<TraceTable, &'static str> {
+    let length = min_length.next_power_of_two().max(MIN_TRACE_LENGTH);
+    if length > super::MAX_TRACE_LENGTH {
+        return Err("Bitwise table too long");
+    }
+    let mut counts = vec![0u64; length];
+    let last_period = length - TABLE_SIZE;
+    for &(x, y) in lookups {
+        counts[last_period + x as usize + 256 * y as usize] += 1;
+    }
+    Ok(TraceTable {
+        columns: vec![counts.into_iter().map(Felt::new).collect()],
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::check_constraints;
+
+    /// Challenges with no relation to the table
+    fn challenges() -> Vec<QuadExt> {
+        (0..bus::SHARED_CHALLENGES as u64)
+            .map(|i| QuadExt(Felt::new(0x1234_5678_9ABC + 77 * i), Felt::new(i + 1)))
+            .collect()
+    }
+
+    /// `sum(multiplicity / fingerprint)` of the lookups the table should receive
+    fn expected_claim(lookups: &[(u8, u8)], challenges: &[QuadExt]) -> QuadExt {
+        let messages: Vec<Message<QuadExt>> = lookups
+            .iter()
+            .map(|&(x, y)| Message {
+                multiplicity: -QuadExt::ONE,
+                values: [bus::BYTE_AND, x as u64, y as u64, (x & y) as u64, 0]
+                    .map(|value| QuadExt::from(Felt::new(value))),
+            })
+            .collect();
+        bus::terms(challenges, &messages)
+            .into_iter()
+            .fold(QuadExt::ZERO, |acc, term| acc + term)
+    }
+
+    #[test]
+    fn test_table_receives_every_entry() {
+        // The first and last entries of a period, and a repeated lookup
+        let lookups = [(0, 0), (0xFF, 0xFF), (0x3C, 0xA5), (0x3C, 0xA5)];
+        let challenges = challenges();
+        for min_length in [0, 2 * TABLE_SIZE] {
+            let table = lay_out(&lookups, min_length).unwrap();
+            assert_eq!(table.length(), min_length.max(TABLE_SIZE));
+            let air = BitwiseAir::new(table.length());
+            assert_eq!(check_constraints(&air, &table), Ok(()));
+            let aux = air.build_aux_columns(&table, &challenges);
+            let claim = aux[0][table.length() - 1];
+            assert_eq!(claim, expected_claim(&lookups, &challenges));
+        }
+    }
+
+    #[test]
+    fn test_misplaced_counts_change_the_claim() {
+        let lookups = [(1, 3)];
+        let challenges = challenges();
+        let expected = expected_claim(&lookups, &challenges);
+        let table = lay_out(&lookups, 2 * TABLE_SIZE).unwrap();
+        let air = BitwiseAir::new(table.length());
+        let claim =
+            |table: &TraceTable| air.build_aux_columns(table, &challenges)[0][table.length() - 1];
+        assert_eq!(claim(&table), expected);
+
+        // Counting the lookup on the row of another entry: `1 & 3` is not `1 & 2`
+        let row = TABLE_SIZE + 1 + 256 * 3;
+        let mut forged = table.clone();
+        forged.columns[col::MULT].swap(row, row - 256);
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(claim(&forged), expected);
+
+        // Counting it in the first period, which the claim leaves out
+        let mut forged = table.clone();
+        forged.columns[col::MULT].swap(row, row - TABLE_SIZE);
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_eq!(claim(&forged), QuadExt::ZERO);
+    }
+}
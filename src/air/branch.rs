@@ -0,0 +1,421 @@
+//! Branch Chip
+//!
+//! This module proves where the LC3 control flow instructions, `BR`, `JMP` and `JSR`/`JSRR`, go
+//! next, in a table of its own connected to the [LC3 table](super::lc3) by the
+//! [bus](super::bus). The LC3 table sends every such instruction as a request
+//! `(condition, npc, target, next)`; this table answers every distinct request once, together
+//! with the number of times it was made.
+//!
+//! ## Design
+//! - `condition` packs the condition flags before the instruction, as in the COND register, the
+//!   `nzp` bits of a `BR` shifted left by 3, and a bit set by `JMP` and `JSR`, which always jump,
+//!   shifted left by 6. A row holds these seven bits, the address of the next instruction `npc`,
+//!   the target, the PC of the next row of the LC3 table and the multiplicity of the request.
+//! - The jump is taken if the instruction always jumps or if a flag is set together with its bit
+//!   of `nzp`, and `next` is then the target, else `npc`. The LC3 table proves that the target is
+//!   the PC-relative address of a `BR` or `JSR`, or the base register of a `JMP` or `JSRR`, and
+//!   sends at most one flag set.
+//! - Any row satisfying the constraints answers a correct request, so rows of zeros pad the table
+//!   with a multiplicity of zero.
+//! - The table grows with the number of distinct control flow requests of a run rather than with
+//!   its length. Zero-knowledge proofs pad it to the height of the LC3 table instead, and prove it
+//!   even for runs without control flow instructions, like the table of the [ALU chip](super::alu).
+
+use super::bus::{self, Message, MESSAGE_WIDTH};
+use super::{Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::opcode::Opcode;
+use crate::trace::{ExecutionTrace, Step};
+use alloc::collections::BTreeMap;
+use alloc::{vec, vec::Vec};
+
+/// Number of bits of the condition of a request
+pub const CONDITION_BITS: usize = 7;
+
+/// Smallest table the prover builds
+pub const MIN_TRACE_LENGTH: usize = 8;
+
+/// Highest degree of the constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Column indices of the branch trace table
+pub mod col {
+    /// 7 bits of the condition, least significant first: the P, Z and N flags, the `p`, `z` and
+    /// `n` bits of a `BR`, and the bit of the instructions that always jump
+    pub const CONDITION: usize = 0;
+    /// Address of the next instruction
+    pub const NPC: usize = CONDITION + super::CONDITION_BITS;
+    /// Address the instruction jumps to if taken
+    pub const TARGET: usize = NPC + 1;
+    /// PC of the next row of the LC3 table
+    pub const NEXT: usize = TARGET + 1;
+    /// Number of times the LC3 table makes the request of the row
+    pub const MULT: usize = NEXT + 1;
+    /// Number of main columns
+    pub const WIDTH: usize = MULT + 1;
+
+    /// Auxiliary: running sum of the bus
+    pub const BUS_SUM: usize = WIDTH;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = BUS_SUM + 1;
+}
+
+/// Bit of the condition set by the instructions that always jump
+const ALWAYS: usize = 6;
+
+/// The branch chip AIR
+pub struct BranchAir {
+    trace_length: usize,
+    num_constraints: usize,
+}
+
+impl BranchAir {
+    pub fn new(trace_length: usize) -> Self {
+        let mut num_constraints = 0;
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |_, _| num_constraints += 1);
+        BranchAir {
+            trace_length,
+            num_constraints,
+        }
+    }
+}
+
+impl Air for BranchAir {
+    fn trace_width(&self) -> usize {
+        col::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        col::FULL_WIDTH - col::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let mut index = 0;
+        evaluate(current, next, challenges, |_, value| {
+            result[index] = value;
+            index += 1;
+        });
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::with_capacity(self.num_constraints);
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |name, _| names.push(name));
+        names
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        vec![BoundaryConstraint {
+            column: col::BUS_SUM,
+            row: 0,
+            value: QuadExt::ZERO,
+        }]
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let mut row = vec![QuadExt::ZERO; col::WIDTH];
+        let messages: Vec<Message<QuadExt>> = (0..table.length())
+            .map(|index| {
+                table.read_row(index, &mut row);
+                message(&row)
+            })
+            .collect();
+        vec![bus::running_sum(
+            bus::terms(challenges, &messages).into_iter(),
+        )]
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+/// `sum(2^i * bits[i])`
+fn pack<E: FieldElement>(bits: &[E]) -> E {
+    bits.iter().rev().fold(E::ZERO, |acc, bit| acc + acc + *bit)
+}
+
+/// The values of the request of a control flow instruction
+pub(super) fn request_values<E: FieldElement>(
+    condition: E,
+    npc: E,
+    target: E,
+    next: E,
+) -> [E; MESSAGE_WIDTH] {
+    [
+        E::from(Felt::new(bus::BRANCH)),
+        condition,
+        npc,
+        target,
+        next,
+    ]
+}
+
+/// The request a row answers, received as many times as the LC3 table makes it
+fn message<E: FieldElement>(row: &[E]) -> Message<E> {
+    let condition = pack(&row[col::CONDITION..col::CONDITION + CONDITION_BITS]);
+    Message {
+        multiplicity: -row[col::MULT],
+        values: request_values(condition, row[col::NPC], row[col::TARGET], row[col::NEXT]),
+    }
+}
+
+/// Evaluate all transition constraints, passing each to `emit` with its name
+fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    mut emit: F,
+) {
+    let one = E::ONE;
+    let bits = &cur[col::CONDITION..col::CONDITION + CONDITION_BITS];
+    for bit in bits {
+        emit("condition bit is boolean", *bit * (*bit - one));
+    }
+    let taken = (0..3).fold(bits[ALWAYS], |acc, i| acc + bits[i] * bits[3 + i]);
+    let npc = cur[col::NPC];
+    emit(
+        "next PC",
+        cur[col::NEXT] - npc - taken * (cur[col::TARGET] - npc),
+    );
+
+    let message = message(cur);
+    emit(
+        "bus running sum",
+        (nxt[col::BUS_SUM] - cur[col::BUS_SUM]) * bus::fingerprint(challenges, &message.values)
+            - message.multiplicity,
+    );
+}
+
+/// The request of a control flow instruction: its condition, the address of the next
+/// instruction, its target and the PC it continues at
+fn request(step: &Step) -> Option<(u16, u16, u16, u16)> {
+    let instruction = step.instruction;
+    let npc = step.pc.wrapping_add(1);
+    let offset = |count: u16| {
+        let offset = instruction & ((1 << count) - 1);
+        if offset >> (count - 1) == 1 {
+            offset | (0xFFFF << count)
+        } else {
+            offset
+        }
+    };
+    let base = step.before.gprs[(instruction >> 6 & 0x7) as usize];
+    let (condition, target) = match step.opcode {
+        Opcode::OP_BR => (
+            (step.before.cond & 0x7) | (instruction >> 9 & 0x7) << 3,
+            npc.wrapping_add(offset(9)),
+        ),
+        Opcode::OP_JMP => (1 << ALWAYS, base),
+        Opcode::OP_JSR if instruction >> 11 & 1 == 1 => (1 << ALWAYS, npc.wrapping_add(offset(11))),
+        Opcode::OP_JSR => (1 << ALWAYS, base),
+        _ => return None,
+    };
+    Some((condition, npc, target, step.after.pc))
+}
+
+/// The distinct control flow requests of a run, with the number of times each is made
+fn requests(trace: &ExecutionTrace) -> BTreeMap<(u16, u16, u16, u16), u64> {
+    let mut requests = BTreeMap::new();
+    for request in trace.steps.iter().filter_map(request) {
+        *requests.entry(request).or_insert(0u64) += 1;
+    }
+    requests
+}
+
+/// Lay out the distinct control flow requests of a run as a branch trace table of at least
+/// `min_length` rows, or return `None` if the run makes none
+pub fn build_trace_table(
+    trace: &ExecutionTrace,
+    min_length: usize,
+) -> Result<Option<TraceTable>, &'static str> {
+    let requests = requests(trace);
+    if requests.is_empty() {
+        return Ok(None);
+    }
+    // The last row must answer nothing
+    let length = (requests.len() + 1)
+        .next_power_of_two()
+        .max(min_length.next_power_of_two())
+        .max(MIN_TRACE_LENGTH);
+    lay_out(&requests, length).map(Some)
+}
+
+/// Lay out the control flow requests of a run as a branch trace table of `length` rows, rounded
+/// up to a power of two, even if the run makes none. Its height does not depend on the requests.
+pub fn build_hiding_trace_table(
+    trace: &ExecutionTrace,
+    length: usize,
+) -> Result<TraceTable, &'static str> {
+    let requests = requests(trace);
+    let length = length.next_power_of_two().max(MIN_TRACE_LENGTH);
+    if requests.len() >= length {
+        return Err("Too many branch requests");
+    }
+    lay_out(&requests, length)
+}
+
+/// Write one row per request, followed by padding rows
+fn lay_out(
+    requests: &BTreeMap<(u16, u16, u16, u16), u64>,
+    length: usize,
+) -> Result<TraceTable, &'static str> {
+    if length > super::MAX_TRACE_LENGTH {
+        return Err("Too many branch requests");
+    }
+    let mut columns = vec![vec![Felt::ZERO; length]; col::WIDTH];
+    for (row, (&(condition, npc, target, next), &count)) in requests.iter().enumerate() {
+        let mut set = |column: usize, value: u64| columns[column][row] = Felt::new(value);
+        for i in 0..CONDITION_BITS {
+            set(col::CONDITION + i, (condition >> i & 1) as u64);
+        }
+        set(col::NPC, npc as u64);
+        set(col::TARGET, target as u64);
+        set(col::NEXT, next as u64);
+        set(col::MULT, count);
+    }
+    Ok(TraceTable { columns })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::lc3::{self, Lc3Air};
+    use crate::air::test_utils::{bus_balance, bus_sum, challenges, public_inputs};
+    use crate::air::{check_constraints, Chip, ConstraintViolation};
+    use crate::io::IoTape;
+    use crate::memory::Memory;
+    use crate::register::{Register, RegisterFile};
+    use crate::utils::execute_program_with_io;
+
+    /// Run a program using every control flow instruction, with branches taken and not taken and
+    /// a repeated request: the BR closing the loop, taken twice
+    fn branch_run() -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let program = [
+            0b0001_000_000_1_00011, // 3000 ADD R0, R0, #3
+            0b0001_000_000_1_11111, // 3001 ADD R0, R0, #-1
+            0b0000_001_111111110,   // 3002 BRp #-2, taken twice, then not
+            0b0000_010_000000001,   // 3003 BRz #1, taken
+            0xF025,                 // 3004 HALT, skipped
+            0b0100_1_00000000100,   // 3005 JSR #4
+            0b1110_010_000000011,   // 3006 LEA R2, #3
+            0b0100_0_00_010_000000, // 3007 JSRR R2
+            0xF025,                 // 3008 HALT
+            0x0000,                 // 3009
+            0b0001_001_001_1_00001, // 300A ADD R1, R1, #1
+            0b1100_000_111_000000,  // 300B RET
+        ];
+        let mut memory = Memory::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        let mut registers = RegisterFile::new();
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(Vec::new());
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        assert_eq!(registers.read(Register::R1), 2);
+        (image, trace)
+    }
+
+    #[test]
+    fn test_chip_satisfies_constraints() {
+        let (image, trace) = branch_run();
+        let table = build_trace_table(&trace, 0).unwrap().unwrap();
+        assert_eq!(table.width(), col::WIDTH);
+        assert_eq!(table.length(), MIN_TRACE_LENGTH);
+        // Three BR requests, then the JSR, the JSRR and both RETs, all answered
+        let mults = &table.columns[col::MULT][..7];
+        assert_eq!(mults.iter().filter(|&&m| m == Felt::new(2)).count(), 1);
+        assert!(mults.iter().all(|&m| m != Felt::ZERO));
+        let air = BranchAir::new(table.length());
+        assert_eq!(
+            air.transition_constraint_names().len(),
+            air.num_transition_constraints()
+        );
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        let lc3_table = lc3::build_trace_table(&trace).unwrap();
+        let lc3_air = Lc3Air::new(lc3_table.length(), public_inputs(&image, &trace));
+        assert_eq!(check_constraints(&lc3_air, &lc3_table), Ok(()));
+
+        // Taking the BR that should not be taken
+        let mut forged = table.clone();
+        let row = (0..7)
+            .find(|&row| table.columns[col::NEXT][row] == Felt::new(0x3003))
+            .unwrap();
+        forged.columns[col::NEXT][row] = forged.columns[col::TARGET][row];
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row: at, .. }) => {
+                assert_eq!(name, "next PC");
+                assert_eq!(at, row);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // A run without control flow instructions needs no chip
+        let mut other = trace.clone();
+        other.steps.retain(|step| request(step).is_none());
+        assert!(build_trace_table(&other, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_bus_links_the_tables() {
+        let (image, trace) = branch_run();
+        let table = build_trace_table(&trace, 0).unwrap().unwrap();
+        let air = BranchAir::new(table.length());
+        let challenges = challenges();
+        let others = bus_balance(
+            &public_inputs(&image, &trace),
+            &trace,
+            Some(Chip::Branch),
+            &challenges,
+        );
+        assert_ne!(others, QuadExt::ZERO);
+        let balance = |table: &TraceTable| others + bus_sum(&air, table, &challenges);
+        assert_eq!(balance(&table), QuadExt::ZERO);
+
+        // Answering a request one time too few
+        let mut forged = table.clone();
+        let row = (0..7)
+            .find(|&row| table.columns[col::MULT][row] == Felt::new(2))
+            .unwrap();
+        forged.columns[col::MULT][row] -= Felt::ONE;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+
+        // Answering the not taken BR as always taken satisfies the chip constraints, but the LC3
+        // table never makes that request
+        let mut forged = table.clone();
+        let row = (0..7)
+            .find(|&row| table.columns[col::NEXT][row] == Felt::new(0x3003))
+            .unwrap();
+        forged.columns[col::NEXT][row] = forged.columns[col::TARGET][row];
+        forged.columns[col::CONDITION + ALWAYS][row] = Felt::ONE;
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(balance(&forged), QuadExt::ZERO);
+    }
+}
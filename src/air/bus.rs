@@ -0,0 +1,131 @@
+//! Bus
+//!
+//! This module connects the tables of a proof: the [LC3 table](super::lc3) sends messages that
+//! chip tables, such as the [memory chip](super::memory), the [I/O chip](super::io), the
+//! [branch chip](super::branch), the [SHA-256 chip](super::sha256) and the
+//! [ALU chip](super::alu), receive. Chips may send messages
+//! too, as the ALU chip does to the [bitwise chip](super::bitwise). It is a LogUp argument that
+//! spans several tables.
+//!
+//! ## Design
+//! - A message is a tuple of [`MESSAGE_WIDTH`] values, the first of which is a tag naming its
+//!   kind. It is fingerprinted as `alpha - (m_0 + gamma * m_1 + ... + gamma^4 * m_4)` for random
+//!   challenges `alpha` and `gamma`, drawn once the main columns of every table of the proof are
+//!   committed. Messages with fewer values are padded with zeros.
+//! - Every table on the bus has an auxiliary column holding the running sum of
+//!   `multiplicity / fingerprint` over its messages. The multiplicity is positive for messages
+//!   sent and negative for messages received. The sum starts at zero and, like the sum of the
+//!   [lookup argument](super::lookup), leaves out the last row.
+//! - The verifier takes part in the bus as well: it sends the messages the public inputs give,
+//!   such as the words of the initial and final memory images and the bytes of the public input
+//!   and journal. Their sum is computed by the verifier, see [`public_sum`], so a proof leaving
+//!   out the table receiving them cannot balance.
+//! - The value of each running sum on the last row is claimed in the proof and pinned by a
+//!   boundary constraint. The verifier checks that the claims of all tables and the public sum add
+//!   up to zero, which shows, except with negligible probability, that every message sent is
+//!   received as many times.
+
+use crate::field::{batch_inverse, FieldElement, QuadExt};
+use crate::proof::PublicInputs;
+use alloc::vec::Vec;
+
+/// Number of values of a message
+pub const MESSAGE_WIDTH: usize = 5;
+
+/// Number of random challenges of the bus: `alpha` and `gamma`
+pub const NUM_CHALLENGES: usize = 2;
+
+/// Index of `alpha` among the challenges, after those of the program ROM and of the lookup
+/// argument
+const ALPHA: usize = super::rom::NUM_CHALLENGES + super::lookup::NUM_CHALLENGES;
+const GAMMA: usize = ALPHA + 1;
+
+/// Number of auxiliary challenges of every table on the bus: the challenges are shared by all
+/// tables of a proof, and those of the bus come last
+pub const SHARED_CHALLENGES: usize = GAMMA + 1;
+
+/// Tag of the message words of a `TRAP x27` hash: `(tag, call, index, word)`
+pub const SHA256_INPUT: u64 = 0;
+
+/// Tag of the digest words of a `TRAP x27` hash: `(tag, call, index, word)`
+pub const SHA256_DIGEST: u64 = 1;
+
+/// Tag of the request of an `ADD`: `(tag, a, b, result)`
+pub const ALU_ADD: u64 = 2;
+
+/// Tag of the request of an `AND`: `(tag, a, b, result)`
+pub const ALU_AND: u64 = 3;
+
+/// Tag of the request of a `NOT`: `(tag, a, b, result)`, with `b` the ignored second operand
+pub const ALU_NOT: u64 = 4;
+
+/// Tag of a lookup into the byte AND table: `(tag, x, y, x & y)`
+pub const BYTE_AND: u64 = 5;
+
+/// Tag of a memory access: `(tag, address, time, old, new)`
+pub const MEMORY: u64 = 6;
+
+/// Tag of the request of a row of an I/O trap: `(tag, sequence, kind + 8 * done, lo, hi)`
+pub const IO: u64 = 7;
+
+/// Tag of a byte of the input or the journal: `(tag, channel, position, byte)`
+pub const TAPE: u64 = 8;
+
+/// Tag of the request of a control flow instruction: `(tag, condition, npc, target, next)`
+pub const BRANCH: u64 = 9;
+
+/// A message sent or received by a row
+pub(super) struct Message<E> {
+    pub multiplicity: E,
+    pub values: [E; MESSAGE_WIDTH],
+}
+
+/// `alpha - (m_0 + gamma * m_1 + ... + gamma^4 * m_4)`
+pub(super) fn fingerprint<E: FieldElement>(challenges: &[E], values: &[E; MESSAGE_WIDTH]) -> E {
+    let (alpha, gamma) = (challenges[ALPHA], challenges[GAMMA]);
+    alpha
+        - values
+            .iter()
+            .rev()
+            .fold(E::ZERO, |acc, value| acc * gamma + *value)
+}
+
+/// `multiplicity / fingerprint` for every message, with the fingerprints inverted in one batch
+pub(super) fn terms<E: FieldElement>(challenges: &[E], messages: &[Message<E>]) -> Vec<E> {
+    let fingerprints: Vec<E> = messages
+        .iter()
+        .map(|message| fingerprint(challenges, &message.values))
+        .collect();
+    batch_inverse(&fingerprints)
+        .into_iter()
+        .zip(messages)
+        .map(|(inverse, message)| inverse * message.multiplicity)
+        .collect()
+}
+
+/// Running sum of `row_sums`: zero on the first row, and the sum of the previous rows after
+pub(super) fn running_sum<E: FieldElement>(row_sums: impl Iterator<Item = E>) -> Vec<E> {
+    let mut sum = E::ZERO;
+    row_sums
+        .map(|row_sum| {
+            let value = sum;
+            sum += row_sum;
+            value
+        })
+        .collect()
+}
+
+/// Sum of `multiplicity / fingerprint` over the messages the verifier sends from the public inputs
+pub fn public_sum(public_inputs: &PublicInputs, challenges: &[QuadExt]) -> QuadExt {
+    let mut messages = super::memory::public_messages(
+        &public_inputs.memory_image,
+        &public_inputs.final_memory_image,
+    );
+    messages.extend(super::io::public_messages(
+        &public_inputs.input,
+        &public_inputs.output,
+    ));
+    terms(challenges, &messages)
+        .into_iter()
+        .fold(QuadExt::ZERO, |acc, term| acc + term)
+}
@@ -0,0 +1,629 @@
+//! I/O Chip
+//!
+//! This module binds the bytes a run reads and writes to the public inputs, in a table of its own
+//! connected to the [LC3 table](super::lc3) by the [bus](super::bus): the input bytes consumed by
+//! `GETC`/`IN` and the output journal written by `OUT`/`PUTS`/`PUTSP`.
+//!
+//! ## Design
+//! - The LC3 table sends every row of these traps as a request
+//!   `(sequence, kind + 8 * done, lo, hi)`: the number of such rows before it, the trap, whether
+//!   the row completes its instruction, and the bytes of the word the row works on, which is the
+//!   character read or written, or the word read by a string walk. The LC3 table range-checks
+//!   both bytes.
+//! - Row `i` of this table receives the request numbered `i`, so the rows hold the requests in the
+//!   order of the run. Rows selecting no trap pad the table to a power of two, and receive
+//!   nothing.
+//! - A row reads at most one input byte and writes at most two output bytes (the two characters
+//!   of a `PUTSP` word), which it decides from zero tests of both bytes. The same tests show that
+//!   string walks end at their terminator.
+//! - Two columns count the input and output bytes before each row. Every byte is received from
+//!   the bus as `(channel, position, byte)`, and the verifier sends the bytes of the public input
+//!   and journal, see [`public_messages`]. Since the counters give every byte of a channel a
+//!   distinct position and end at the lengths of the public tapes, the bytes of the run are the
+//!   public bytes, in order.
+//! - The table grows with the number of I/O rows of a run rather than with its length, and the
+//!   LC3 table keeps a single counter for them. That height would reveal how much I/O a run
+//!   does, so zero-knowledge proofs pad the table to the height of the LC3 table instead, and
+//!   prove it even for runs without I/O.
+
+use super::bus::{self, Message, MESSAGE_WIDTH};
+use super::{lc3, Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use alloc::{vec, vec::Vec};
+
+/// Number of trap routines doing I/O, with vectors 0x20 to 0x24
+pub const KINDS: usize = 5;
+const KIND_GETC: usize = 0;
+const KIND_OUT: usize = 1;
+const KIND_PUTS: usize = 2;
+const KIND_IN: usize = 3;
+const KIND_PUTSP: usize = 4;
+
+const INPUT_CHANNEL: u64 = 0;
+const OUTPUT_CHANNEL: u64 = 1;
+
+/// Smallest table the prover builds
+pub const MIN_TRACE_LENGTH: usize = 8;
+
+/// Highest degree of the constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Column indices of the I/O trace table
+pub mod col {
+    /// One-hot selectors of the trap, in the order of their vectors, all zero on padding rows
+    pub const KIND: usize = 0;
+    /// Set on rows completing their instruction
+    pub const DONE: usize = KIND + super::KINDS;
+    /// Low and high byte of the word of the row
+    pub const LO: usize = DONE + 1;
+    pub const HI: usize = LO + 1;
+    /// Zero test of the low byte
+    pub const LO_INV: usize = HI + 1;
+    pub const LO_ZERO: usize = LO_INV + 1;
+    /// Zero test of the high byte
+    pub const HI_INV: usize = LO_ZERO + 1;
+    pub const HI_ZERO: usize = HI_INV + 1;
+    /// Set on rows writing an output byte: `OUT`, and `PUTS`/`PUTSP` before the terminator
+    pub const OUT_FIRST: usize = HI_ZERO + 1;
+    /// Set on `PUTSP` rows writing the high byte of their word as well
+    pub const OUT_SECOND: usize = OUT_FIRST + 1;
+    /// Row counter, the sequence number of the request of the row
+    pub const SEQ: usize = OUT_SECOND + 1;
+    /// Number of input bytes read before the row
+    pub const IN_POS: usize = SEQ + 1;
+    /// Number of output bytes written before the row
+    pub const OUT_POS: usize = IN_POS + 1;
+    /// Number of main columns
+    pub const WIDTH: usize = OUT_POS + 1;
+
+    /// Auxiliary: running sum of the bus
+    pub const BUS_SUM: usize = WIDTH;
+    /// Auxiliary: inverse fingerprints of the first and the second byte of the row
+    pub const BYTE_INVERSES: usize = BUS_SUM + 1;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = BYTE_INVERSES + 2;
+}
+
+/// The I/O chip AIR for a run reading `input_length` bytes and writing `output_length` bytes
+pub struct IoAir {
+    trace_length: usize,
+    input_length: usize,
+    output_length: usize,
+    num_constraints: usize,
+}
+
+impl IoAir {
+    pub fn new(trace_length: usize, input_length: usize, output_length: usize) -> Self {
+        let mut num_constraints = 0;
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |_, _| num_constraints += 1);
+        IoAir {
+            trace_length,
+            input_length,
+            output_length,
+            num_constraints,
+        }
+    }
+}
+
+impl Air for IoAir {
+    fn trace_width(&self) -> usize {
+        col::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        col::FULL_WIDTH - col::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let mut index = 0;
+        evaluate(current, next, challenges, |_, value| {
+            result[index] = value;
+            index += 1;
+        });
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::with_capacity(self.num_constraints);
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |name, _| names.push(name));
+        names
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        let last = self.trace_length - 1;
+        let pin = |column: usize, row: usize, value: u64| BoundaryConstraint {
+            column,
+            row,
+            value: Felt::new(value).into(),
+        };
+        vec![
+            pin(col::SEQ, 0, 0),
+            pin(col::IN_POS, 0, 0),
+            pin(col::OUT_POS, 0, 0),
+            pin(col::IN_POS, last, self.input_length as u64),
+            pin(col::OUT_POS, last, self.output_length as u64),
+            pin(col::BUS_SUM, 0, 0),
+        ]
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let mut row = vec![QuadExt::ZERO; col::WIDTH];
+        let mut messages = Vec::with_capacity(table.length() * 3);
+        for index in 0..table.length() {
+            table.read_row(index, &mut row);
+            messages.push(request(&row));
+            messages.extend(bytes(&row));
+        }
+        let terms = bus::terms(challenges, &messages);
+        let rows = terms.chunks(3);
+        let (mut first_inverses, mut second_inverses) = (Vec::new(), Vec::new());
+        for terms in rows.clone() {
+            first_inverses.push(terms[1]);
+            second_inverses.push(terms[2]);
+        }
+        let row_sums = rows.map(|terms| terms[0] + terms[1] + terms[2]);
+        vec![bus::running_sum(row_sums), first_inverses, second_inverses]
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+fn constant<E: FieldElement>(value: u64) -> E {
+    E::from(Felt::new(value))
+}
+
+/// `kind + 8 * done`, the code of a request
+fn code<E: FieldElement>(kinds: &[E], done: E) -> E {
+    let kind = (0..KINDS).fold(E::ZERO, |acc, k| acc + kinds[k] * constant(k as u64));
+    kind + done * constant(8)
+}
+
+/// The request the LC3 table sends for a row of `GETC`, `OUT`, `PUTS`, `IN` or `PUTSP`, given the
+/// trap selectors of the row. Every value is zero on other rows.
+pub(super) fn request_values<E: FieldElement>(
+    kinds: &[E],
+    sequence: E,
+    done: E,
+    lo: E,
+    hi: E,
+) -> [E; MESSAGE_WIDTH] {
+    let kinds = &kinds[..KINDS];
+    let active = kinds.iter().fold(E::ZERO, |acc, kind| acc + *kind);
+    [
+        active * constant(bus::IO),
+        active * sequence,
+        code(kinds, active * done),
+        active * lo,
+        active * hi,
+    ]
+}
+
+/// The request a row receives: that of the LC3 row with the sequence number of the row
+fn request<E: FieldElement>(row: &[E]) -> Message<E> {
+    let kinds = &row[col::KIND..col::KIND + KINDS];
+    let active = kinds.iter().fold(E::ZERO, |acc, kind| acc + *kind);
+    Message {
+        multiplicity: -active,
+        values: [
+            constant(bus::IO),
+            row[col::SEQ],
+            code(kinds, row[col::DONE]),
+            row[col::LO],
+            row[col::HI],
+        ],
+    }
+}
+
+/// The bytes a row reads or writes: the input byte or the first output byte, then the second
+/// output byte
+fn bytes<E: FieldElement>(row: &[E]) -> [Message<E>; 2] {
+    let input = row[col::KIND + KIND_GETC] + row[col::KIND + KIND_IN];
+    let output = row[col::OUT_FIRST];
+    [
+        Message {
+            multiplicity: -(input + output),
+            values: [
+                constant(bus::TAPE),
+                input * constant(INPUT_CHANNEL) + output * constant(OUTPUT_CHANNEL),
+                input * row[col::IN_POS] + output * row[col::OUT_POS],
+                row[col::LO],
+                E::ZERO,
+            ],
+        },
+        Message {
+            multiplicity: -row[col::OUT_SECOND],
+            values: [
+                constant(bus::TAPE),
+                constant(OUTPUT_CHANNEL),
+                row[col::OUT_POS] + E::ONE,
+                row[col::HI],
+                E::ZERO,
+            ],
+        },
+    ]
+}
+
+/// The bytes of the public input and journal, which the verifier sends on the bus
+pub(super) fn public_messages<E: FieldElement>(input: &[u8], output: &[u8]) -> Vec<Message<E>> {
+    let channels = [(INPUT_CHANNEL, input), (OUTPUT_CHANNEL, output)];
+    channels
+        .iter()
+        .flat_map(|&(channel, bytes)| {
+            bytes
+                .iter()
+                .enumerate()
+                .map(move |(position, &byte)| Message {
+                    multiplicity: E::ONE,
+                    values: [
+                        constant(bus::TAPE),
+                        constant(channel),
+                        constant(position as u64),
+                        constant(byte as u64),
+                        E::ZERO,
+                    ],
+                })
+        })
+        .collect()
+}
+
+/// Evaluate all transition constraints, passing each to `emit` with its name
+fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    mut emit: F,
+) {
+    let one = E::ONE;
+    let kind = |k: usize| cur[col::KIND + k];
+    let mut active = E::ZERO;
+    for k in 0..KINDS {
+        emit("trap selector is boolean", kind(k) * (kind(k) - one));
+        active += kind(k);
+    }
+    emit("at most one trap", active * (active - one));
+    let done = cur[col::DONE];
+    emit("done is boolean", done * (done - one));
+
+    let (lo, hi) = (cur[col::LO], cur[col::HI]);
+    let (lo_zero, hi_zero) = (cur[col::LO_ZERO], cur[col::HI_ZERO]);
+    emit(
+        "low byte zero detection",
+        lo * cur[col::LO_INV] - (one - lo_zero),
+    );
+    emit("low byte zero flag", lo * lo_zero);
+    emit(
+        "high byte zero detection",
+        hi * cur[col::HI_INV] - (one - hi_zero),
+    );
+    emit("high byte zero flag", hi * hi_zero);
+
+    let input = kind(KIND_GETC) + kind(KIND_IN);
+    let string = kind(KIND_PUTS) + kind(KIND_PUTSP);
+    emit("input is a byte", input * hi);
+    emit("PUTS termination", kind(KIND_PUTS) * (done - lo_zero));
+    emit(
+        "PUTSP termination",
+        kind(KIND_PUTSP) * (done - (lo_zero + hi_zero - lo_zero * hi_zero)),
+    );
+    emit(
+        "output flag",
+        cur[col::OUT_FIRST] - kind(KIND_OUT) - string * (one - lo_zero),
+    );
+    emit(
+        "second output flag",
+        cur[col::OUT_SECOND] - kind(KIND_PUTSP) * (one - lo_zero) * (one - hi_zero),
+    );
+
+    emit("sequence number", nxt[col::SEQ] - cur[col::SEQ] - one);
+    emit(
+        "input position",
+        nxt[col::IN_POS] - cur[col::IN_POS] - input,
+    );
+    emit(
+        "output position",
+        nxt[col::OUT_POS] - cur[col::OUT_POS] - cur[col::OUT_FIRST] - cur[col::OUT_SECOND],
+    );
+
+    let [first, second] = bytes(cur);
+    let first_inverse = cur[col::BYTE_INVERSES];
+    let second_inverse = cur[col::BYTE_INVERSES + 1];
+    emit(
+        "first byte inverse",
+        first_inverse * bus::fingerprint(challenges, &first.values) - first.multiplicity,
+    );
+    emit(
+        "second byte inverse",
+        second_inverse * bus::fingerprint(challenges, &second.values) - second.multiplicity,
+    );
+    let request = request(cur);
+    emit(
+        "bus running sum",
+        (nxt[col::BUS_SUM] - cur[col::BUS_SUM] - first_inverse - second_inverse)
+            * bus::fingerprint(challenges, &request.values)
+            - request.multiplicity,
+    );
+}
+
+/// The I/O request of a row of the LC3 table: the trap, whether the row completes its
+/// instruction, and its word
+fn row_request(lc3_table: &TraceTable, index: usize) -> Option<(usize, bool, u16)> {
+    let cell = |column: usize| lc3_table.columns[column][index];
+    let kind = (0..KINDS).find(|&k| cell(lc3::col::TRAP + k) == Felt::ONE)?;
+    let word = cell(lc3::col::RES).as_u64() as u16;
+    Some((kind, cell(lc3::col::DONE) == Felt::ONE, word))
+}
+
+/// Lay out the I/O requests of an LC3 table as an I/O trace table of at least `min_length` rows,
+/// or return `None` if the run makes none
+pub fn build_trace_table(
+    lc3_table: &TraceTable,
+    min_length: usize,
+) -> Result<Option<TraceTable>, &'static str> {
+    let requests = requests(lc3_table);
+    if requests.is_empty() {
+        return Ok(None);
+    }
+    // The last row must receive nothing
+    let length = (requests.len() + 1)
+        .next_power_of_two()
+        .max(min_length.next_power_of_two())
+        .max(MIN_TRACE_LENGTH);
+    lay_out(&requests, length).map(Some)
+}
+
+/// Lay out the I/O requests of an LC3 table as an I/O trace table of `length` rows, rounded up to
+/// a power of two, even if the run makes none.
+///
+/// As for the [ALU chip](super::alu), `length` is that of the LC3 table of a zero-knowledge
+/// proof, and the height of the table does not depend on the requests.
+pub fn build_hiding_trace_table(
+    lc3_table: &TraceTable,
+    length: usize,
+) -> Result<TraceTable, &'static str> {
+    let requests = requests(lc3_table);
+    let length = length.next_power_of_two().max(MIN_TRACE_LENGTH);
+    if requests.len() >= length {
+        return Err("Too many I/O requests");
+    }
+    lay_out(&requests, length)
+}
+
+fn requests(lc3_table: &TraceTable) -> Vec<(usize, bool, u16)> {
+    (0..lc3_table.length())
+        .filter_map(|index| row_request(lc3_table, index))
+        .collect()
+}
+
+/// Write one row per request, in order, followed by padding rows
+fn lay_out(requests: &[(usize, bool, u16)], length: usize) -> Result<TraceTable, &'static str> {
+    if length > super::MAX_TRACE_LENGTH {
+        return Err("Too many I/O requests");
+    }
+    let mut columns = vec![vec![Felt::ZERO; length]; col::WIDTH];
+    let (mut input, mut output) = (0u64, 0u64);
+    let padding = (KINDS, false, 0);
+    let rows = requests.iter().chain(core::iter::repeat(&padding));
+    for (row, &(kind, done, word)) in rows.take(length).enumerate() {
+        let (lo, hi) = (word & 0xFF, word >> 8);
+        let out_first = kind == KIND_OUT || ((kind == KIND_PUTS || kind == KIND_PUTSP) && lo != 0);
+        let out_second = kind == KIND_PUTSP && lo != 0 && hi != 0;
+        let mut set = |column: usize, value: Felt| columns[column][row] = value;
+        if kind < KINDS {
+            set(col::KIND + kind, Felt::ONE);
+        }
+        set(col::DONE, done.into());
+        set(col::LO, lo.into());
+        set(col::HI, hi.into());
+        set(col::LO_INV, Felt::from(lo).inverse());
+        set(col::LO_ZERO, (lo == 0).into());
+        set(col::HI_INV, Felt::from(hi).inverse());
+        set(col::HI_ZERO, (hi == 0).into());
+        set(col::OUT_FIRST, out_first.into());
+        set(col::OUT_SECOND, out_second.into());
+        set(col::SEQ, Felt::new(row as u64));
+        set(col::IN_POS, Felt::new(input));
+        set(col::OUT_POS, Felt::new(output));
+        input += (kind == KIND_GETC || kind == KIND_IN) as u64;
+        output += out_first as u64 + out_second as u64;
+    }
+    Ok(TraceTable { columns })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::lc3::Lc3Air;
+    use crate::air::test_utils::{bus_balance, bus_sum, challenges, public_inputs};
+    use crate::air::{check_constraints, Chip, ConstraintViolation};
+    use crate::io::IoTape;
+    use crate::memory::Memory;
+    use crate::proof::PublicInputs;
+    use crate::register::{Register, RegisterFile};
+    use crate::trace::ExecutionTrace;
+    use crate::utils::execute_program_with_io;
+
+    /// Run a program reading `input` from a tape
+    fn run(
+        program: &[u16],
+        data: &[(u16, u16)],
+        input: &[u8],
+    ) -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        for (address, value) in data {
+            memory.write(*address, *value);
+        }
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(input.to_vec());
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        assert_eq!(trace.output, tape.journal());
+        (image, trace)
+    }
+
+    /// A program reading two characters and writing five
+    fn io_run() -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let (image, trace) = run(
+            &[
+                0b1111_0000_00100000,   // 3000 GETC
+                0b1111_0000_00100001,   // 3001 OUT
+                0b1111_0000_00100011,   // 3002 IN
+                0b0001_000_000_1_00001, // 3003 ADD R0, R0, #1
+                0b1111_0000_00100001,   // 3004 OUT
+                0b1110_000_000000010,   // 3005 LEA R0, #2 -> 0x3008
+                0b1111_0000_00100100,   // 3006 PUTSP
+                0xF025,                 // 3007 HALT
+            ],
+            &[(0x3008, 0x6968), (0x3009, 0x0021)],
+            b"xyz",
+        );
+        assert_eq!(trace.input, b"xy");
+        assert_eq!(trace.output, b"xzhi!");
+        (image, trace)
+    }
+
+    /// The I/O table of a run, and its AIR
+    fn io_table(trace: &ExecutionTrace) -> (TraceTable, IoAir) {
+        let lc3_table = lc3::build_trace_table(trace).unwrap();
+        let table = build_trace_table(&lc3_table, 0).unwrap().unwrap();
+        let air = IoAir::new(table.length(), trace.input.len(), trace.output.len());
+        (table, air)
+    }
+
+    #[test]
+    fn test_chip_satisfies_constraints() {
+        let (image, trace) = io_run();
+        let (table, air) = io_table(&trace);
+        assert_eq!(table.width(), col::WIDTH);
+        // GETC, OUT, IN, OUT and the two words of PUTSP, then padding
+        assert_eq!(table.length(), MIN_TRACE_LENGTH);
+        assert_eq!(table.columns[col::KIND + KIND_PUTSP][5], Felt::ONE);
+        assert_eq!(table.columns[col::DONE][5], Felt::ONE);
+        assert_eq!(
+            air.transition_constraint_names().len(),
+            air.num_transition_constraints()
+        );
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        let public_inputs = public_inputs(&image, &trace);
+        let lc3_table = lc3::build_trace_table(&trace).unwrap();
+        let lc3_air = Lc3Air::new(lc3_table.length(), public_inputs.clone());
+        assert_eq!(check_constraints(&lc3_air, &lc3_table), Ok(()));
+        let challenges = challenges();
+        assert_eq!(
+            bus_balance(&public_inputs, &trace, None, &challenges),
+            QuadExt::ZERO
+        );
+        assert_ne!(
+            bus_balance(&public_inputs, &trace, Some(Chip::Io), &challenges),
+            QuadExt::ZERO
+        );
+
+        // A string walk cannot end before its terminator
+        let mut forged = table.clone();
+        assert_eq!(forged.columns[col::LO][4], Felt::new(0x68));
+        forged.columns[col::DONE][4] = Felt::ONE;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "PUTSP termination");
+                assert_eq!(row, 4);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Nor can an input character be wider than a byte
+        let mut forged = table.clone();
+        forged.columns[col::HI][0] = Felt::ONE;
+        forged.columns[col::HI_INV][0] = Felt::ONE;
+        forged.columns[col::HI_ZERO][0] = Felt::ZERO;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "input is a byte");
+                assert_eq!(row, 0);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // A run without I/O needs no chip
+        let (_, trace) = run(&[0xF025], &[], b"");
+        let lc3_table = lc3::build_trace_table(&trace).unwrap();
+        assert!(build_trace_table(&lc3_table, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_public_io() {
+        let (image, trace) = io_run();
+        let (table, air) = io_table(&trace);
+        let public_inputs = public_inputs(&image, &trace);
+        let challenges = challenges();
+
+        let forged_balance = |forge: &dyn Fn(&mut PublicInputs)| {
+            let mut public_inputs = public_inputs.clone();
+            forge(&mut public_inputs);
+            let air = IoAir::new(
+                table.length(),
+                public_inputs.input.len(),
+                public_inputs.output.len(),
+            );
+            let others = bus_balance(&public_inputs, &trace, Some(Chip::Io), &challenges);
+            (
+                check_constraints(&air, &table),
+                others + bus_sum(&air, &table, &challenges),
+            )
+        };
+        assert_eq!(forged_balance(&|_| {}), (Ok(()), QuadExt::ZERO));
+
+        // A journal other than the one written, or an input tape other than the one read
+        let (constraints, balance) = forged_balance(&|p| p.output[1] = b'Z');
+        assert_eq!(constraints, Ok(()));
+        assert_ne!(balance, QuadExt::ZERO);
+        let (constraints, balance) = forged_balance(&|p| p.input[0] = b'w');
+        assert_eq!(constraints, Ok(()));
+        assert_ne!(balance, QuadExt::ZERO);
+
+        // A shorter journal, or a longer input tape
+        let forged_column = |forge: &dyn Fn(&mut PublicInputs)| match forged_balance(forge).0 {
+            Err(ConstraintViolation::Boundary { column, .. }) => column,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(forged_column(&|p| p.output.truncate(4)), col::OUT_POS);
+        assert_eq!(forged_column(&|p| p.input.push(b'z')), col::IN_POS);
+
+        // Requests taken out of order: both OUT rows write their character where the other should
+        let mut forged = table.clone();
+        for column in col::KIND..col::SEQ {
+            forged.columns[column].swap(1, 3);
+        }
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        let others = bus_balance(&public_inputs, &trace, Some(Chip::Io), &challenges);
+        assert_ne!(others + bus_sum(&air, &forged, &challenges), QuadExt::ZERO);
+    }
+}
@@ -0,0 +1,1513 @@
+//! LC3 AIR
+//!
+//! This module encodes the semantics of the LC3 instruction set, as implemented in
+//! [`instruction`](crate::instruction), as polynomial constraints over the trace table.
+//!
+//! ## Trace Layout
+//! - Row `i` holds the register state before the instruction it executes, the raw instruction and
+//!   a set of helper columns (decoded fields, operands, result, effective address, memory values).
+//! - Most instructions take one row. `PUTS` and `PUTSP` take one row per word they read, and
+//!   `TRAP x27` one row per word it reads or writes; the rows of such a walk repeat the
+//!   instruction and the register state, and the instruction is `DONE` on its last row.
+//! - After `HALT`, the final register state is repeated with `HALTED` set until the table reaches
+//!   a power-of-two length. A segment of a longer run instead pauses after any instruction but
+//!   `HALT`, and the state it pauses in is repeated the same way.
+//!
+//! ## Encoding
+//! - The instruction is decomposed into bits. One-hot selectors pick the opcode, the trap routine
+//!   and the registers named by the DR, SR1/BaseR and SR2 fields.
+//! - Computed 16-bit values (result, effective address) are split into two bytes, which are
+//!   range-checked by a lookup argument into a byte table. The sign bit of the result, which
+//!   sets the N flag, is extracted by the same lookups. See [`lookup`](super::lookup).
+//! - Immediates and offsets are sign-extended from the instruction bits. Address additions modulo
+//!   `2^16` use a boolean carry column.
+//! - Condition codes are three boolean flags. The Z flag of a result uses an inverse column.
+//! - Every constraint is gated by the selectors of the instructions it applies to, so that the
+//!   maximum constraint degree is 3.
+//!
+//! ## Memory
+//! - Every row makes up to three memory accesses: the instruction fetch at `PC`, a first data
+//!   access at `EA` and, for LDI and STI, a second data access at the pointer read by the first.
+//! - A run fetching its instructions from a program ROM makes no fetch access. Its rows look up
+//!   the instruction in the ROM instead, and the memory chip keeps its accesses inside the ROM
+//!   from changing the words there, see [`rom`](super::rom).
+//! - Every access is sent on the [bus](super::bus) with its address, time, and old and new value.
+//!   The [memory chip](super::memory) receives them sorted by address and time and proves them
+//!   consistent; the LC3 table only proves that the accesses are the ones its instructions make.
+//!   Two auxiliary columns add the inverse fingerprints of the accesses of a row, which keeps the
+//!   degree of the bus constraint at 3.
+//!
+//! ## I/O
+//! - Every row of `GETC`, `IN`, `OUT`, `PUTS` and `PUTSP` is sent on the [bus](super::bus) with a
+//!   counter of such rows, whether it completes its instruction and the bytes of its word. The
+//!   [I/O chip](super::io) receives them in order, decides which bytes each row reads or writes
+//!   and binds them to the public input and journal. It also checks that string walks end at
+//!   their terminator; the LC3 table only proves that the words are the characters in R0 or the
+//!   memory contents.
+//! - `TRAP x26` reads a word of private input into R0. Like a register load, it leaves the value
+//!   free in the trace and binds it to no public input.
+//!
+//! ## Precompiles
+//! - `TRAP x27` reads the `32 * R1` words of a padded message from R0 on, then writes the 16
+//!   words of its SHA-256 digest from R2 on. A countdown column tracks the words left in each
+//!   phase.
+//! - Every word read or written is sent on the [bus](super::bus), tagged with the number of hashes
+//!   before it and its index in the message or digest. The [SHA-256 chip](super::sha256) receives
+//!   them and proves the hash; the LC3 table only proves that the words are the memory contents.
+//! - `ADD`, `AND` and `NOT` send their operation, operands and result on the bus as well. The
+//!   [ALU chip](super::alu) receives them and proves the results; the LC3 table only proves that
+//!   the operands are the registers or immediate the instruction names.
+//!
+//! ## Control Flow
+//! - `BR`, `JMP` and `JSR`/`JSRR` send their condition, the address of the next instruction, their
+//!   target and the PC of the next row on the bus. The [branch chip](super::branch) receives them
+//!   and proves that the run continues at the target if the jump is taken, and at the next
+//!   instruction otherwise. The LC3 table only proves the target, which `EA` holds: the
+//!   PC-relative address of `BR` and `JSR`, or the base register of `JMP` and `JSRR`.
+
+use super::branch;
+use super::bus::{self, Message};
+use super::io;
+use super::lookup::{self, RowLookups};
+use super::memory::{self, SlotAccess, SLOTS};
+use super::rom::{self, RomBounds};
+use super::sha256;
+use super::{Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::opcode::Opcode;
+use crate::proof::PublicInputs;
+use crate::register::R_COUNT;
+use crate::trace::{ExecutionTrace, RegisterState, Step};
+use alloc::{vec, vec::Vec};
+
+/// Column indices of the LC3 trace table
+pub mod col {
+    use crate::register::R_COUNT;
+
+    /// Program counter
+    pub const PC: usize = 0;
+    /// R0-R7 occupy consecutive columns
+    pub const R0: usize = PC + 1;
+    /// Negative condition flag
+    pub const FLAG_N: usize = R0 + R_COUNT;
+    /// Zero condition flag
+    pub const FLAG_Z: usize = FLAG_N + 1;
+    /// Positive condition flag
+    pub const FLAG_P: usize = FLAG_Z + 1;
+    /// Set on the rows after `HALT`
+    pub const HALTED: usize = FLAG_P + 1;
+    /// Set on rows continuing the instruction of the previous row
+    pub const CONT: usize = HALTED + 1;
+    /// Set on rows completing their instruction
+    pub const DONE: usize = CONT + 1;
+    /// Raw instruction
+    pub const INSTRUCTION: usize = DONE + 1;
+    /// 16 instruction bits, least significant first
+    pub const INSTRUCTION_BITS: usize = INSTRUCTION + 1;
+    /// 16 one-hot opcode selectors, indexed by opcode value
+    pub const OPCODE: usize = INSTRUCTION_BITS + 16;
+    /// 7 one-hot trap selectors, indexed by `trapvect8 - 0x20`
+    pub const TRAP: usize = OPCODE + 16;
+    /// 8 one-hot selectors for bits [11:9]
+    pub const DR: usize = TRAP + super::NUM_TRAPS;
+    /// 8 one-hot selectors for bits [8:6]
+    pub const SR1: usize = DR + 8;
+    /// 8 one-hot selectors for bits [2:0]
+    pub const SR2: usize = SR1 + 8;
+    /// First ALU operand, the register selected by SR1
+    pub const OP_A: usize = SR2 + 8;
+    /// Second ALU operand, SR2 or the sign-extended imm5
+    pub const OP_B: usize = OP_A + 1;
+    /// Value written to the destination register, or the word a trap routine works on
+    pub const RES: usize = OP_B + 1;
+    /// Low and high byte of the result, and its sign bit
+    pub const RES_LO: usize = RES + 1;
+    pub const RES_HI: usize = RES_LO + 1;
+    pub const RES_SIGN: usize = RES_HI + 1;
+    pub const RES_INV: usize = RES_SIGN + 1;
+    pub const RES_ZERO: usize = RES_INV + 1;
+    /// Effective address, jump target, or string walk address
+    pub const EA: usize = RES_ZERO + 1;
+    /// Low and high byte of the effective address
+    pub const EA_LO: usize = EA + 1;
+    pub const EA_HI: usize = EA_LO + 1;
+    pub const EA_CARRY: usize = EA_HI + 1;
+    /// First data access: old and new value at `EA`
+    pub const M1_OLD: usize = EA_CARRY + 1;
+    pub const M1_NEW: usize = M1_OLD + 1;
+    /// Second data access of LDI/STI: old and new value at `M1_OLD`
+    pub const M2_OLD: usize = M1_NEW + 1;
+    pub const M2_NEW: usize = M2_OLD + 1;
+    /// Whether PC is 0xFFFF, so that PC + 1 wraps around
+    pub const PC_WRAP: usize = M2_NEW + 1;
+    pub const PC_INV: usize = PC_WRAP + 1;
+    /// Set on rows fetching their instruction from memory
+    pub const FETCH: usize = PC_INV + 1;
+    /// Set on rows fetching their instruction from the program ROM
+    pub const ROM_FETCH: usize = FETCH + 1;
+    /// Row counter, the time base of the memory accesses of the row
+    pub const CLK: usize = ROM_FETCH + 1;
+    /// Number of rows of `GETC`, `OUT`, `PUTS`, `IN` and `PUTSP` before the row
+    pub const IO_SEQ: usize = CLK + 1;
+    /// Number of `TRAP x27` hashes completed before the row
+    pub const SHA_CALL: usize = IO_SEQ + 1;
+    /// `TRAP x27`: words left to read, or to write, after the row's, with an inverse column and a
+    /// flag set when none are left
+    pub const SHA_REM: usize = SHA_CALL + 1;
+    pub const SHA_INV: usize = SHA_REM + 1;
+    pub const SHA_END: usize = SHA_INV + 1;
+    /// `TRAP x27`: set on the rows writing the digest
+    pub const SHA_DIGEST: usize = SHA_END + 1;
+    /// Byte table of the lookup argument
+    pub const LOOKUP_TABLE: usize = SHA_DIGEST + 1;
+    /// Number of lookups of each byte, on its first row in the table
+    pub const LOOKUP_MULT: usize = LOOKUP_TABLE + 1;
+    /// Number of fetches of each program ROM entry, on its row in the first period
+    pub const ROM_MULT: usize = LOOKUP_MULT + 1;
+    /// Number of main columns
+    pub const WIDTH: usize = ROM_MULT + 1;
+
+    /// Auxiliary: sum of the inverse fingerprints of the fetch and the first data access, then
+    /// inverse fingerprint of the second data access
+    pub const MEM_INVERSES: usize = WIDTH;
+    /// Auxiliary: sum of the inverses of each pair of lookups of the row
+    pub const LOOKUP_INVERSES: usize = MEM_INVERSES + 2;
+    /// Auxiliary: multiplicities over the differences to the table entries of the row
+    pub const LOOKUP_TERM: usize =
+        LOOKUP_INVERSES + crate::air::lookup::inverse_columns(super::LOOKUPS);
+    /// Auxiliary: running sum of the lookup argument
+    pub const LOOKUP_SUM: usize = LOOKUP_TERM + 1;
+    /// Auxiliary: running sum of the program ROM lookup
+    pub const ROM_SUM: usize = LOOKUP_SUM + 1;
+    /// Auxiliary: running sum of the bus messages sent
+    pub const BUS_SUM: usize = ROM_SUM + 1;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = BUS_SUM + 1;
+
+    /// Periodic: address of a program ROM entry
+    pub const ROM_ADDRESS: usize = FULL_WIDTH;
+    /// Periodic: word of a program ROM entry
+    pub const ROM_WORD: usize = ROM_ADDRESS + 1;
+    /// Number of columns of the rows given to the constraints
+    pub const ROW_WIDTH: usize = ROM_WORD + 1;
+}
+
+/// Number of trap routines, with vectors 0x20 to 0x27
+pub const NUM_TRAPS: usize = 8;
+const TRAP_GETC: usize = 0;
+const TRAP_OUT: usize = 1;
+const TRAP_PUTS: usize = 2;
+const TRAP_IN: usize = 3;
+const TRAP_PUTSP: usize = 4;
+const TRAP_HALT: usize = 5;
+const TRAP_PRIVATE: usize = 6;
+const TRAP_SHA256: usize = 7;
+
+/// Number of bytes each row looks up
+pub const LOOKUPS: usize = 5;
+
+const LOOKUP_COLUMNS: lookup::Columns = lookup::Columns {
+    table: col::LOOKUP_TABLE,
+    multiplicity: col::LOOKUP_MULT,
+    inverses: col::LOOKUP_INVERSES,
+    term: col::LOOKUP_TERM,
+    sum: col::LOOKUP_SUM,
+};
+
+/// Number of main columns of the LC3 trace table
+pub const TRACE_WIDTH: usize = col::WIDTH;
+
+/// Number of auxiliary columns of the LC3 trace table
+pub const AUX_WIDTH: usize = col::FULL_WIDTH - col::WIDTH;
+
+/// Highest degree of the LC3 transition constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Smallest trace table the prover accepts, large enough for the byte table
+pub const MIN_TRACE_LENGTH: usize = lookup::MIN_TRACE_LENGTH;
+
+/// Largest trace table the prover accepts: the times of its memory accesses stay below
+/// [`memory::END_TIME`]
+pub const MAX_TRACE_LENGTH: usize = 1 << 30;
+
+const TWO_16: u64 = 1 << 16;
+
+/// The LC3 AIR for a run with the given public inputs
+pub struct Lc3Air {
+    trace_length: usize,
+    public_inputs: PublicInputs,
+    num_constraints: usize,
+}
+
+impl Lc3Air {
+    pub fn new(trace_length: usize, public_inputs: PublicInputs) -> Self {
+        let mut num_constraints = 0;
+        let zeros = vec![Felt::ZERO; col::ROW_WIDTH];
+        let rom = RomBounds::new(None);
+        evaluate(&zeros, &zeros, &zeros, Felt::ZERO, &rom, |_, _| {
+            num_constraints += 1
+        });
+        Lc3Air {
+            trace_length,
+            public_inputs,
+            num_constraints,
+        }
+    }
+}
+
+impl Air for Lc3Air {
+    fn trace_width(&self) -> usize {
+        TRACE_WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        AUX_WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let mut index = 0;
+        let halts = E::from(Felt::from(self.public_inputs.halted));
+        let rom = RomBounds::new(self.public_inputs.rom);
+        evaluate(current, next, challenges, halts, &rom, |_, value| {
+            result[index] = value;
+            index += 1;
+        });
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::with_capacity(self.num_constraints);
+        let zeros = vec![Felt::ZERO; col::ROW_WIDTH];
+        let rom = RomBounds::new(None);
+        evaluate(&zeros, &zeros, &zeros, Felt::ZERO, &rom, |name, _| {
+            names.push(name)
+        });
+        names
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        let last = self.trace_length - 1;
+        let mut constraints = Vec::new();
+        let mut pin = |column: usize, row: usize, value: Felt| {
+            constraints.push(BoundaryConstraint {
+                column,
+                row,
+                value: value.into(),
+            });
+        };
+
+        for (row, state) in [
+            (0, &self.public_inputs.initial_state),
+            (last, &self.public_inputs.final_state),
+        ] {
+            for (column, value) in state_values(state) {
+                pin(column, row, value);
+            }
+        }
+        pin(col::HALTED, 0, Felt::ZERO);
+        pin(col::CONT, 0, Felt::ZERO);
+        pin(col::HALTED, last, Felt::ONE);
+        pin(col::CLK, 0, Felt::ZERO);
+        pin(col::IO_SEQ, 0, Felt::ZERO);
+        pin(col::ROM_SUM, 0, Felt::ZERO);
+        pin(col::ROM_SUM, last, Felt::ZERO);
+        pin(col::SHA_CALL, 0, Felt::ZERO);
+        pin(col::BUS_SUM, 0, Felt::ZERO);
+        constraints.extend(lookup::boundary_constraints(
+            &LOOKUP_COLUMNS,
+            self.trace_length,
+        ));
+        constraints
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let length = table.length();
+        let mut row = vec![QuadExt::ZERO; TRACE_WIDTH];
+        let mut next = vec![QuadExt::ZERO; TRACE_WIDTH];
+        table.read_row(0, &mut next);
+        let mut messages = Vec::with_capacity(length * (SLOTS + 1));
+        for index in 0..length {
+            core::mem::swap(&mut row, &mut next);
+            table.read_row((index + 1) % length, &mut next);
+            messages.extend(memory::slot_messages(row[col::CLK], accesses(&row)));
+            messages.push(bus_message(&row, &next));
+        }
+        let terms = bus::terms(challenges, &messages);
+        let rows = terms.chunks(SLOTS + 1);
+        let (mut first_inverses, mut second_inverses) = (Vec::new(), Vec::new());
+        for terms in rows.clone() {
+            first_inverses.push(terms[0] + terms[1]);
+            second_inverses.push(terms[2]);
+        }
+        let row_sums = rows.map(|terms| terms.iter().fold(QuadExt::ZERO, |acc, term| acc + *term));
+        let bus_sum = bus::running_sum(row_sums);
+
+        let mut columns = vec![first_inverses, second_inverses];
+        columns.extend(lookup::build_sum_columns(
+            table,
+            &LOOKUP_COLUMNS,
+            challenges,
+            lookups,
+        ));
+        columns.push(rom::build_sum_column(
+            table,
+            challenges,
+            &self.periodic_columns(),
+        ));
+        columns.push(bus_sum);
+        columns
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<Felt>> {
+        let public_inputs = &self.public_inputs;
+        rom::periodic_columns(public_inputs.rom, &public_inputs.memory_image)
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+/// The memory accesses made by a row: instruction fetch, first and second data access
+pub(super) fn accesses<E: FieldElement>(row: &[E]) -> [SlotAccess<E>; SLOTS] {
+    let op = |opcode: Opcode| row[col::OPCODE + opcode as usize];
+    let walk =
+        row[col::TRAP + TRAP_PUTS] + row[col::TRAP + TRAP_PUTSP] + row[col::TRAP + TRAP_SHA256];
+    let indirect = op(Opcode::OP_LDI) + op(Opcode::OP_STI);
+    let direct = op(Opcode::OP_LD) + op(Opcode::OP_LDR) + op(Opcode::OP_ST) + op(Opcode::OP_STR);
+    [
+        SlotAccess {
+            flag: row[col::FETCH],
+            address: row[col::PC],
+            old: row[col::INSTRUCTION],
+            new: row[col::INSTRUCTION],
+        },
+        SlotAccess {
+            flag: direct + indirect + walk,
+            address: row[col::EA],
+            old: row[col::M1_OLD],
+            new: row[col::M1_NEW],
+        },
+        SlotAccess {
+            flag: indirect,
+            address: row[col::M1_OLD],
+            old: row[col::M2_OLD],
+            new: row[col::M2_NEW],
+        },
+    ]
+}
+
+/// The message a row sends on the bus: a message word read or a digest word written by
+/// `TRAP x27`, as `(tag, hash, index, word)`, the request of an ALU instruction, as
+/// `(tag, a, b, result)`, the request of a row of an I/O trap, as
+/// `(tag, sequence, kind + 8 * done, lo, hi)`, or the request of a control flow instruction, as
+/// `(tag, condition, npc, target, next)` with `next` the PC of the next row
+fn bus_message<E: FieldElement>(row: &[E], next: &[E]) -> Message<E> {
+    let op = |opcode: Opcode| row[col::OPCODE + opcode as usize];
+    let (s_add, s_and, s_not) = (op(Opcode::OP_ADD), op(Opcode::OP_AND), op(Opcode::OP_NOT));
+    let s_alu = s_add + s_and + s_not;
+    let s_br = op(Opcode::OP_BR);
+    let s_jump = op(Opcode::OP_JMP) + op(Opcode::OP_JSR);
+    let s_ctl = s_br + s_jump;
+    let bits = &row[col::INSTRUCTION_BITS..];
+    let flags = row[col::FLAG_P] + row[col::FLAG_Z] * constant(2) + row[col::FLAG_N] * constant(4);
+    let condition = s_br * (flags + pack(&bits[9..12]) * constant(8)) + s_jump * constant(64);
+    let npc = row[col::PC] + E::ONE - row[col::PC_WRAP] * constant(TWO_16);
+    let branch = branch::request_values(
+        condition,
+        s_ctl * npc,
+        s_ctl * row[col::EA],
+        s_ctl * next[col::PC],
+    );
+    let t_sha = row[col::TRAP + TRAP_SHA256];
+    let digest = row[col::SHA_DIGEST];
+    let rem = row[col::SHA_REM];
+    let read_words = row[col::R0 + 1] * constant(sha256::WORDS_PER_BLOCK as u64);
+    let last_digest_word = constant::<E>(sha256::DIGEST_WORDS as u64 - 1);
+    let io_kinds = &row[col::TRAP..col::TRAP + io::KINDS];
+    let t_io = io_kinds.iter().fold(E::ZERO, |acc, kind| acc + *kind);
+    let io = io::request_values(
+        io_kinds,
+        row[col::IO_SEQ],
+        row[col::DONE],
+        row[col::RES_LO],
+        row[col::RES_HI],
+    );
+    Message {
+        multiplicity: t_sha + s_alu + t_io + s_ctl,
+        values: [
+            (t_sha - digest) * constant(bus::SHA256_INPUT)
+                + digest * constant(bus::SHA256_DIGEST)
+                + s_add * constant(bus::ALU_ADD)
+                + s_and * constant(bus::ALU_AND)
+                + s_not * constant(bus::ALU_NOT)
+                + io[0]
+                + s_ctl * branch[0],
+            t_sha * row[col::SHA_CALL] + s_alu * row[col::OP_A] + io[1] + branch[1],
+            (t_sha - digest) * (read_words - E::ONE - rem)
+                + digest * (last_digest_word - rem)
+                + s_alu * row[col::OP_B]
+                + io[2]
+                + branch[2],
+            (t_sha + s_alu) * row[col::RES] + io[3] + branch[3],
+            io[4] + branch[4],
+        ],
+    }
+}
+
+/// The values a row looks up: the bytes of the result without its sign bit, and the bytes of
+/// the effective address
+fn lookups<E: FieldElement>(row: &[E]) -> RowLookups<E, LOOKUPS> {
+    let unsigned_hi = row[col::RES_HI] - row[col::RES_SIGN] * constant(0x80);
+    RowLookups {
+        bytes: [
+            row[col::RES_LO],
+            unsigned_hi,
+            unsigned_hi + constant(0x80),
+            row[col::EA_LO],
+            row[col::EA_HI],
+        ],
+    }
+}
+
+/// Values of the register state columns, with COND split into its three flags
+fn state_values(state: &RegisterState) -> Vec<(usize, Felt)> {
+    let mut values = vec![(col::PC, Felt::from(state.pc))];
+    for (i, gpr) in state.gprs.iter().enumerate() {
+        values.push((col::R0 + i, Felt::from(*gpr)));
+    }
+    values.push((col::FLAG_N, Felt::from(state.cond >> 2 & 1)));
+    values.push((col::FLAG_Z, Felt::from(state.cond >> 1 & 1)));
+    values.push((col::FLAG_P, Felt::from(state.cond & 1)));
+    values
+}
+
+fn constant<E: FieldElement>(value: u64) -> E {
+    E::from(Felt::new(value))
+}
+
+/// `sum(2^i * bits[i])`
+fn pack<E: FieldElement>(bits: &[E]) -> E {
+    bits.iter().rev().fold(E::ZERO, |acc, bit| acc + acc + *bit)
+}
+
+/// Sign-extend the low `count` bits to 16 bits: the sign bit contributes `2^16 - 2^count` more
+fn sign_extend<E: FieldElement>(bits: &[E], count: usize) -> E {
+    pack(&bits[..count]) + bits[count - 1] * constant(TWO_16 - (1 << count))
+}
+
+/// Evaluate all transition constraints, passing each to `emit` with its name.
+///
+/// `halts` is one if the run ends with `HALT` and zero if it pauses, and `rom` holds the bounds
+/// of the program ROM, if the run fetches its instructions from one.
+fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    halts: E,
+    rom: &RomBounds<E>,
+    mut emit: F,
+) {
+    let one = E::ONE;
+    let bits = &cur[col::INSTRUCTION_BITS..col::INSTRUCTION_BITS + 16];
+    let op = |opcode: Opcode| cur[col::OPCODE + opcode as usize];
+    let trap = |index: usize| cur[col::TRAP + index];
+    let reg = |i: usize| cur[col::R0 + i];
+    let select =
+        |selectors: usize| (0..R_COUNT).fold(E::ZERO, |acc, i| acc + cur[selectors + i] * reg(i));
+
+    let halted = cur[col::HALTED];
+    let done = cur[col::DONE];
+    let cont = cur[col::CONT];
+    let pc = cur[col::PC];
+    let pc_wrap = cur[col::PC_WRAP];
+    let npc = pc + one - pc_wrap * constant(TWO_16);
+    let (n, z, p) = (cur[col::FLAG_N], cur[col::FLAG_Z], cur[col::FLAG_P]);
+    let op_a = cur[col::OP_A];
+    let op_b = cur[col::OP_B];
+    let res = cur[col::RES];
+    let (lo, hi) = (cur[col::RES_LO], cur[col::RES_HI]);
+    let ea = cur[col::EA];
+    let ea_carry = cur[col::EA_CARRY];
+    let (m1_old, m1_new) = (cur[col::M1_OLD], cur[col::M1_NEW]);
+    let (m2_old, m2_new) = (cur[col::M2_OLD], cur[col::M2_NEW]);
+    let (sha_end, sha_digest) = (cur[col::SHA_END], cur[col::SHA_DIGEST]);
+
+    let s_add = op(Opcode::OP_ADD);
+    let s_and = op(Opcode::OP_AND);
+    let s_not = op(Opcode::OP_NOT);
+    let s_br = op(Opcode::OP_BR);
+    let s_jmp = op(Opcode::OP_JMP);
+    let s_jsr = op(Opcode::OP_JSR);
+    let s_ld = op(Opcode::OP_LD);
+    let s_ldi = op(Opcode::OP_LDI);
+    let s_ldr = op(Opcode::OP_LDR);
+    let s_lea = op(Opcode::OP_LEA);
+    let s_st = op(Opcode::OP_ST);
+    let s_sti = op(Opcode::OP_STI);
+    let s_str = op(Opcode::OP_STR);
+    let s_trap = op(Opcode::OP_TRAP);
+    let t_sha = trap(TRAP_SHA256);
+    let t_string = trap(TRAP_PUTS) + trap(TRAP_PUTSP);
+    // Instructions spanning several rows, one per word they access
+    let t_walk = t_string + t_sha;
+    let t_input = trap(TRAP_GETC) + trap(TRAP_IN);
+    // Instructions writing DR and setting the condition codes
+    let writes_dr = s_add + s_and + s_not + s_ld + s_ldi + s_ldr + s_lea;
+    // Instructions continuing at PC + 1
+    let sequential = writes_dr + s_st + s_sti + s_str + s_trap;
+
+    // Instruction decoding
+    for bit in bits {
+        emit("instruction bit is boolean", *bit * (*bit - one));
+    }
+    emit(
+        "instruction matches its bits",
+        cur[col::INSTRUCTION] - pack(bits),
+    );
+    let mut selector_sum = E::ZERO;
+    let mut selector_opcode = E::ZERO;
+    for opcode in 0..16 {
+        let s = cur[col::OPCODE + opcode];
+        emit("opcode selector is boolean", s * (s - one));
+        selector_sum += s;
+        selector_opcode += s * constant(opcode as u64);
+    }
+    emit(
+        "one opcode selector per active row",
+        selector_sum - (one - halted),
+    );
+    emit(
+        "opcode selector matches opcode bits",
+        selector_opcode - pack(&bits[12..16]),
+    );
+    emit("RTI is not supported", op(Opcode::OP_RTI));
+    emit("reserved opcode is not supported", op(Opcode::OP_RES));
+    let mut trap_sum = E::ZERO;
+    let mut trap_vector = E::ZERO;
+    for index in 0..NUM_TRAPS {
+        let t = trap(index);
+        emit("trap selector is boolean", t * (t - one));
+        trap_sum += t;
+        trap_vector += t * constant(0x20 + index as u64);
+    }
+    emit("one trap selector per TRAP", trap_sum - s_trap);
+    emit(
+        "trap selector matches trap vector",
+        trap_vector - s_trap * pack(&bits[..8]),
+    );
+    for (selectors, field) in [(col::DR, 9), (col::SR1, 6), (col::SR2, 0)] {
+        let mut sum = E::ZERO;
+        let mut index = E::ZERO;
+        for i in 0..R_COUNT {
+            let s = cur[selectors + i];
+            emit("register selector is boolean", s * (s - one));
+            sum += s;
+            index += s * constant(i as u64);
+        }
+        emit("one register selector per field", sum - one);
+        emit(
+            "register selector matches its field",
+            index - pack(&bits[field..field + 3]),
+        );
+    }
+
+    // Control flags
+    emit("halted is boolean", halted * (halted - one));
+    emit("done is boolean", done * (done - one));
+    for flag in [n, z, p] {
+        emit("condition flag is boolean", flag * (flag - one));
+    }
+    let flag_sum = n + z + p;
+    emit("at most one condition flag", flag_sum * (flag_sum - one));
+    let pc_from_max = pc - constant(TWO_16 - 1);
+    emit(
+        "PC wrap detection",
+        pc_from_max * cur[col::PC_INV] - (one - pc_wrap),
+    );
+    emit("PC wrap flag", pc_from_max * pc_wrap);
+
+    // Operands, sent to the ALU chip with the result
+    emit("operand A is SR1", op_a - select(col::SR1));
+    emit(
+        "immediate operand B",
+        bits[5] * (op_b - sign_extend(bits, 5)),
+    );
+    emit(
+        "register operand B",
+        (one - bits[5]) * (op_b - select(col::SR2)),
+    );
+
+    // Result, range-checked by the lookups of its bytes
+    emit("result matches its bytes", res - lo - hi * constant(0x100));
+    let res_sign = cur[col::RES_SIGN];
+    emit("result sign is boolean", res_sign * (res_sign - one));
+    let res_zero = cur[col::RES_ZERO];
+    emit(
+        "result zero detection",
+        res * cur[col::RES_INV] - (one - res_zero),
+    );
+    emit("result zero flag", res * res_zero);
+
+    emit("LD/LDR result", (s_ld + s_ldr) * (res - m1_old));
+    emit("LDI result", s_ldi * (res - m2_old));
+    emit("LEA result", s_lea * (res - ea));
+    emit("JSR link", s_jsr * (res - npc));
+    emit("OUT character", trap(TRAP_OUT) * (res - reg(0)));
+    emit("PUTS/PUTSP word", t_string * (res - m1_old));
+
+    // Effective address, range-checked by the lookups of its bytes
+    emit(
+        "address matches its bytes",
+        ea - cur[col::EA_LO] - cur[col::EA_HI] * constant(0x100),
+    );
+    emit("address carry is boolean", ea_carry * (ea_carry - one));
+    let wrapped_ea = ea + ea_carry * constant(TWO_16);
+    emit(
+        "PC-relative address",
+        (s_ld + s_st + s_ldi + s_sti + s_lea + s_br) * (npc + sign_extend(bits, 9) - wrapped_ea),
+    );
+    emit(
+        "JSR target",
+        s_jsr * bits[11] * (npc + sign_extend(bits, 11) - wrapped_ea),
+    );
+    emit(
+        "register target",
+        (s_jmp + s_jsr * (one - bits[11])) * (ea - op_a),
+    );
+    emit(
+        "base-relative address",
+        (s_ldr + s_str) * (op_a + sign_extend(bits, 6) - wrapped_ea),
+    );
+    emit(
+        "string walk starts at R0",
+        t_walk * (one - cont) * (ea - reg(0)),
+    );
+
+    // Memory accesses
+    let stored = select(col::DR);
+    emit(
+        "first access is a read unless storing",
+        (one - s_st - s_str - sha_digest) * (m1_new - m1_old),
+    );
+    emit(
+        "second access is a read unless STI",
+        (one - s_sti) * (m2_new - m2_old),
+    );
+    emit("ST/STR value", (s_st + s_str) * (m1_new - stored));
+    emit("STI value", s_sti * (m2_new - stored));
+
+    // Control flow within the instruction
+    emit(
+        "only string walks span several rows",
+        (one - done) * (one - t_walk),
+    );
+
+    // Transition to the next row
+    emit("continuation flag", nxt[col::CONT] - (one - done));
+    emit(
+        "continued instruction",
+        (one - done) * (nxt[col::INSTRUCTION] - cur[col::INSTRUCTION]),
+    );
+    emit(
+        "string walk advances",
+        nxt[col::CONT]
+            * (one - sha_end)
+            * (ea + one - nxt[col::EA] - nxt[col::EA_CARRY] * constant(TWO_16)),
+    );
+    emit(
+        "SHA-256 digest starts at R2",
+        nxt[col::CONT] * sha_end * (nxt[col::EA] - reg(2)),
+    );
+    emit("halted stays set", halted * (one - nxt[col::HALTED]));
+    emit(
+        "HALT halts",
+        halts * (one - halted) * (nxt[col::HALTED] - trap(TRAP_HALT)),
+    );
+    emit("paused run does not HALT", (one - halts) * trap(TRAP_HALT));
+    emit(
+        "pause ends an instruction",
+        (one - halts) * nxt[col::HALTED] * (one - done),
+    );
+
+    for i in 0..R_COUNT {
+        let mut written = cur[col::DR + i] * writes_dr;
+        if i == 7 {
+            written += s_jsr;
+        }
+        if i == 0 {
+            written += t_input + trap(TRAP_PRIVATE);
+        }
+        emit(
+            "register update",
+            nxt[col::R0 + i] - reg(i) - written * (res - reg(i)),
+        );
+    }
+
+    let res_n = res_sign;
+    let res_p = one - res_n - res_zero;
+    for (flag, column, value) in [
+        ("N flag update", col::FLAG_N, res_n),
+        ("Z flag update", col::FLAG_Z, res_zero),
+        ("P flag update", col::FLAG_P, res_p),
+    ] {
+        emit(
+            flag,
+            nxt[column] - cur[column] - writes_dr * (value - cur[column]),
+        );
+    }
+
+    let next_pc = nxt[col::PC];
+    emit(
+        "sequential PC",
+        sequential * (next_pc - done * npc - (one - done) * pc),
+    );
+    emit("halted PC", halted * (next_pc - pc));
+
+    // Memory, and the program ROM
+    let fetches = (one - halted) * (one - cont);
+    emit(
+        "fetch flag",
+        cur[col::FETCH] - fetches * (one - rom.present),
+    );
+    emit(
+        "ROM fetch flag",
+        cur[col::ROM_FETCH] - fetches * rom.present,
+    );
+    emit("clock", nxt[col::CLK] - cur[col::CLK] - one);
+    let [fetch, first, second] =
+        memory::slot_messages(cur[col::CLK], accesses(cur)).map(|access| {
+            (
+                bus::fingerprint(challenges, &access.values),
+                access.multiplicity,
+            )
+        });
+    let first_inverses = cur[col::MEM_INVERSES];
+    let second_inverse = cur[col::MEM_INVERSES + 1];
+    emit(
+        "memory access inverses",
+        first_inverses * fetch.0 * first.0 - fetch.1 * first.0 - first.1 * fetch.0,
+    );
+    emit(
+        "second memory access inverse",
+        second_inverse * second.0 - second.1,
+    );
+    rom::evaluate(cur, nxt, challenges, rom, &mut emit);
+
+    // I/O, sent on the bus for the I/O chip
+    let t_io = t_input + trap(TRAP_OUT) + t_string;
+    emit("I/O sequence", nxt[col::IO_SEQ] - cur[col::IO_SEQ] - t_io);
+
+    // Lookups
+    lookup::evaluate(
+        cur,
+        nxt,
+        &LOOKUP_COLUMNS,
+        &lookups(cur),
+        challenges,
+        &mut emit,
+    );
+
+    // SHA-256: the words read and written are sent on the bus, like the ALU requests
+    let sha_rem = cur[col::SHA_REM];
+    emit(
+        "SHA-256 message word",
+        (t_sha - sha_digest) * (res - m1_old),
+    );
+    emit("SHA-256 digest word", sha_digest * (m1_new - res));
+    emit(
+        "SHA-256 end detection",
+        t_sha * (sha_rem * cur[col::SHA_INV] - one + sha_end),
+    );
+    emit("SHA-256 end flag", sha_rem * sha_end);
+    for column in [col::SHA_REM, col::SHA_END, col::SHA_DIGEST] {
+        emit(
+            "SHA-256 columns outside TRAP x27",
+            (one - t_sha) * cur[column],
+        );
+    }
+    emit(
+        "SHA-256 reads R1 blocks",
+        t_sha * (one - cont) * (sha_rem + one - reg(1) * constant(sha256::WORDS_PER_BLOCK as u64)),
+    );
+    emit("SHA-256 reads first", t_sha * (one - cont) * sha_digest);
+    let next_cont = nxt[col::CONT];
+    emit(
+        "SHA-256 word countdown",
+        next_cont * (one - sha_end) * (nxt[col::SHA_REM] - sha_rem + t_sha),
+    );
+    emit(
+        "SHA-256 digest countdown",
+        next_cont * sha_end * (nxt[col::SHA_REM] - constant(sha256::DIGEST_WORDS as u64 - 1)),
+    );
+    emit(
+        "SHA-256 writes after reading",
+        next_cont * (nxt[col::SHA_DIGEST] - sha_digest - sha_end),
+    );
+    emit("SHA-256 termination", t_sha * (done - sha_digest * sha_end));
+    emit(
+        "SHA-256 hash counter",
+        nxt[col::SHA_CALL] - cur[col::SHA_CALL] - t_sha * done,
+    );
+    let message = bus_message(cur, nxt);
+    emit(
+        "bus running sum",
+        (nxt[col::BUS_SUM] - cur[col::BUS_SUM] - first_inverses - second_inverse)
+            * bus::fingerprint(challenges, &message.values)
+            - message.multiplicity,
+    );
+}
+
+/// One row of the trace table under construction
+struct Row(Vec<Felt>);
+
+impl Row {
+    fn set(&mut self, column: usize, value: impl Into<Felt>) {
+        self.0[column] = value.into();
+    }
+
+    fn set_bits(&mut self, column: usize, value: u16) {
+        for i in 0..16 {
+            self.0[column + i] = Felt::from(value >> i & 1);
+        }
+    }
+
+    /// Set `value` and its bit decomposition
+    fn set_decomposed(&mut self, column: usize, bits: usize, value: u16) {
+        self.set(column, value);
+        self.set_bits(bits, value);
+    }
+
+    /// Set `value` and its low and high byte
+    fn set_bytes(&mut self, column: usize, lo: usize, hi: usize, value: u16) {
+        self.set(column, value);
+        self.set(lo, value & 0xFF);
+        self.set(hi, value >> 8);
+    }
+
+    /// Set an inverse column and zero flag for `value`
+    fn set_zero_test(&mut self, inv: usize, zero: usize, value: Felt) {
+        self.set(inv, value.inverse());
+        self.set(zero, value == Felt::ZERO);
+    }
+}
+
+fn sign_extend_u16(value: u16, bit_count: u16) -> u16 {
+    if (value >> (bit_count - 1)) & 1 != 0 {
+        value | (0xFFFF << bit_count)
+    } else {
+        value
+    }
+}
+
+/// Per-row values that depend on the instruction semantics
+#[derive(Default)]
+struct Effects {
+    res: u16,
+    ea: u16,
+    ea_carry: bool,
+    m1: (u16, u16),
+    m2: (u16, u16),
+}
+
+/// `a + b` modulo `2^16`, with the carry out
+fn add_with_carry(a: u16, b: u16) -> (u16, bool) {
+    a.overflowing_add(b)
+}
+
+/// Build a row for `state` executing `instruction`, with the instruction-specific values in
+/// `effects`
+fn build_row(
+    state: &RegisterState,
+    instruction: u16,
+    halted: bool,
+    cont: bool,
+    done: bool,
+    effects: &Effects,
+) -> Row {
+    let mut row = Row(vec![Felt::ZERO; TRACE_WIDTH]);
+    for (column, value) in state_values(state) {
+        row.set(column, value);
+    }
+    row.set(col::HALTED, halted);
+    row.set(col::CONT, cont);
+    row.set(col::DONE, done);
+    row.set(col::FETCH, !halted && !cont);
+    row.set_decomposed(col::INSTRUCTION, col::INSTRUCTION_BITS, instruction);
+
+    if !halted {
+        let opcode = instruction >> 12;
+        row.set(col::OPCODE + opcode as usize, Felt::ONE);
+        if opcode == Opcode::OP_TRAP as u16 {
+            let index = (instruction & 0xFF) as usize - 0x20;
+            row.set(col::TRAP + index, Felt::ONE);
+        }
+    }
+
+    let dr = (instruction >> 9 & 0x7) as usize;
+    let sr1 = (instruction >> 6 & 0x7) as usize;
+    let sr2 = (instruction & 0x7) as usize;
+    row.set(col::DR + dr, Felt::ONE);
+    row.set(col::SR1 + sr1, Felt::ONE);
+    row.set(col::SR2 + sr2, Felt::ONE);
+
+    let op_a = state.gprs[sr1];
+    let op_b = if instruction >> 5 & 1 == 1 {
+        sign_extend_u16(instruction & 0x1F, 5)
+    } else {
+        state.gprs[sr2]
+    };
+    row.set(col::OP_A, op_a);
+    row.set(col::OP_B, op_b);
+
+    row.set_bytes(col::RES, col::RES_LO, col::RES_HI, effects.res);
+    row.set(col::RES_SIGN, effects.res >> 15);
+    row.set_zero_test(col::RES_INV, col::RES_ZERO, Felt::from(effects.res));
+
+    row.set_bytes(col::EA, col::EA_LO, col::EA_HI, effects.ea);
+    row.set(col::EA_CARRY, effects.ea_carry);
+    row.set(col::M1_OLD, effects.m1.0);
+    row.set(col::M1_NEW, effects.m1.1);
+    row.set(col::M2_OLD, effects.m2.0);
+    row.set(col::M2_NEW, effects.m2.1);
+
+    row.set(col::PC_WRAP, state.pc == 0xFFFF);
+    row.set(
+        col::PC_INV,
+        (Felt::from(state.pc) - Felt::new(TWO_16 - 1)).inverse(),
+    );
+    row
+}
+
+/// Rows for one executed instruction, fetched from the program ROM if `from_rom` is set
+fn step_rows(step: &Step, from_rom: bool) -> Result<Vec<Row>, &'static str> {
+    let instruction = step.instruction;
+    let state = &step.before;
+    let npc = step.pc.wrapping_add(1);
+    // The data accesses follow the fetch, unless the instruction comes from the ROM
+    let data = step
+        .memory_accesses
+        .get(usize::from(!from_rom)..)
+        .unwrap_or_default();
+    let access = |i: usize| -> Result<(u16, u16), &'static str> {
+        data.get(i)
+            .map(|a| (a.old_value, a.new_value))
+            .ok_or("Missing memory access in execution trace")
+    };
+    let op_a = state.gprs[(instruction >> 6 & 0x7) as usize];
+    let op_b = if instruction >> 5 & 1 == 1 {
+        sign_extend_u16(instruction & 0x1F, 5)
+    } else {
+        state.gprs[(instruction & 0x7) as usize]
+    };
+    let pc_relative = || add_with_carry(npc, sign_extend_u16(instruction & 0x1FF, 9));
+    let base_relative = || add_with_carry(op_a, sign_extend_u16(instruction & 0x3F, 6));
+
+    let mut effects = Effects::default();
+    match step.opcode {
+        Opcode::OP_ADD => effects.res = op_a.wrapping_add(op_b),
+        Opcode::OP_AND => effects.res = op_a & op_b,
+        Opcode::OP_NOT => effects.res = !op_a,
+        Opcode::OP_BR => (effects.ea, effects.ea_carry) = pc_relative(),
+        Opcode::OP_JMP => effects.ea = op_a,
+        Opcode::OP_JSR => {
+            effects.res = npc;
+            (effects.ea, effects.ea_carry) = if instruction >> 11 & 1 == 1 {
+                add_with_carry(npc, sign_extend_u16(instruction & 0x7FF, 11))
+            } else {
+                (op_a, false)
+            };
+        }
+        Opcode::OP_LD | Opcode::OP_LDR => {
+            (effects.ea, effects.ea_carry) = if step.opcode == Opcode::OP_LD {
+                pc_relative()
+            } else {
+                base_relative()
+            };
+            effects.m1 = access(0)?;
+            effects.res = effects.m1.0;
+        }
+        Opcode::OP_ST | Opcode::OP_STR => {
+            (effects.ea, effects.ea_carry) = if step.opcode == Opcode::OP_ST {
+                pc_relative()
+            } else {
+                base_relative()
+            };
+            effects.m1 = access(0)?;
+        }
+        Opcode::OP_LDI | Opcode::OP_STI => {
+            (effects.ea, effects.ea_carry) = pc_relative();
+            effects.m1 = access(0)?;
+            effects.m2 = access(1)?;
+            effects.res = if step.opcode == Opcode::OP_LDI {
+                effects.m2.0
+            } else {
+                0
+            };
+        }
+        Opcode::OP_LEA => {
+            (effects.ea, effects.ea_carry) = pc_relative();
+            effects.res = effects.ea;
+        }
+        Opcode::OP_TRAP => match instruction & 0xFF {
+            0x20 | 0x23 | 0x26 => effects.res = step.after.gprs[0],
+            0x21 => effects.res = state.gprs[0],
+            0x22 | 0x24 => {
+                // One row per word read by the string walk
+                let words = data;
+                if words.is_empty() {
+                    return Err("Missing memory access in execution trace");
+                }
+                let mut rows = Vec::with_capacity(words.len());
+                let mut previous: Option<u16> = None;
+                for (i, word) in words.iter().enumerate() {
+                    let mut effects = Effects {
+                        res: word.old_value,
+                        ea: word.address,
+                        m1: (word.old_value, word.new_value),
+                        ..Effects::default()
+                    };
+                    if let Some(previous) = previous {
+                        effects.ea_carry = add_with_carry(previous, 1).1;
+                    }
+                    previous = Some(word.address);
+                    let last = i == words.len() - 1;
+                    rows.push(build_row(state, instruction, false, i > 0, last, &effects));
+                }
+                return Ok(rows);
+            }
+            0x25 => {}
+            0x27 => {
+                // One row per word read, then one per digest word written
+                let words = data;
+                let reads = sha256::WORDS_PER_BLOCK * state.gprs[1] as usize;
+                if reads == 0 || words.len() != reads + sha256::DIGEST_WORDS {
+                    return Err("Missing memory access in execution trace");
+                }
+                let mut rows = Vec::with_capacity(words.len());
+                for (i, word) in words.iter().enumerate() {
+                    let digest = i >= reads;
+                    let mut effects = Effects {
+                        res: if digest {
+                            word.new_value
+                        } else {
+                            word.old_value
+                        },
+                        ea: word.address,
+                        m1: (word.old_value, word.new_value),
+                        ..Effects::default()
+                    };
+                    if i > 0 && i != reads {
+                        effects.ea_carry = add_with_carry(words[i - 1].address, 1).1;
+                    }
+                    let rem = if digest {
+                        reads + sha256::DIGEST_WORDS - 1 - i
+                    } else {
+                        reads - 1 - i
+                    };
+                    let last = i == words.len() - 1;
+                    let mut row = build_row(state, instruction, false, i > 0, last, &effects);
+                    row.set(col::SHA_REM, Felt::new(rem as u64));
+                    row.set_zero_test(col::SHA_INV, col::SHA_END, Felt::new(rem as u64));
+                    row.set(col::SHA_DIGEST, digest);
+                    rows.push(row);
+                }
+                return Ok(rows);
+            }
+            _ => return Err("Unknown TRAP vector"),
+        },
+        Opcode::OP_RTI | Opcode::OP_RES => return Err("Unsupported opcode in execution trace"),
+    }
+
+    Ok(vec![build_row(
+        state,
+        instruction,
+        false,
+        false,
+        true,
+        &effects,
+    )])
+}
+
+/// Lay out an execution trace as an LC3 trace table
+pub fn build_trace_table(trace: &ExecutionTrace) -> Result<TraceTable, &'static str> {
+    build_padded_trace_table(trace, MIN_TRACE_LENGTH)
+}
+
+/// [`build_trace_table`], padded with halted rows to at least `min_length` rows
+pub fn build_padded_trace_table(
+    trace: &ExecutionTrace,
+    min_length: usize,
+) -> Result<TraceTable, &'static str> {
+    let final_state = trace.final_state().ok_or("Empty execution trace")?;
+    trace.initial_state().unwrap().check_cond()?;
+    final_state.check_cond()?;
+
+    let from_rom = trace.rom.is_some();
+    let mut rows = Vec::with_capacity(trace.len() + 1);
+    for step in &trace.steps {
+        rows.extend(step_rows(step, from_rom)?);
+    }
+    if from_rom {
+        for row in &mut rows {
+            let fetch = row.0[col::FETCH];
+            row.set(col::ROM_FETCH, fetch);
+            row.set(col::FETCH, Felt::ZERO);
+        }
+    }
+
+    // The last row must make no access
+    let length = (rows.len() + 1)
+        .next_power_of_two()
+        .max(min_length.next_power_of_two())
+        .max(rom::min_trace_length(trace.rom))
+        .max(MIN_TRACE_LENGTH);
+    if length > MAX_TRACE_LENGTH {
+        return Err("Execution trace too long");
+    }
+    while rows.len() < length {
+        rows.push(build_row(
+            &final_state,
+            0,
+            true,
+            false,
+            true,
+            &Effects::default(),
+        ));
+    }
+
+    let (mut io_rows, mut hashes) = (0, 0);
+    for (index, row) in rows.iter_mut().enumerate() {
+        row.set(col::CLK, Felt::new(index as u64));
+        row.set(col::IO_SEQ, Felt::new(io_rows));
+        row.set(col::SHA_CALL, Felt::new(hashes));
+        if row.0[col::TRAP..col::TRAP + io::KINDS].contains(&Felt::ONE) {
+            io_rows += 1;
+        }
+        if row.0[col::TRAP + TRAP_SHA256] == Felt::ONE && row.0[col::DONE] == Felt::ONE {
+            hashes += 1;
+        }
+    }
+
+    let mut columns: Vec<Vec<Felt>> = (0..TRACE_WIDTH)
+        .map(|column| rows.iter().map(|row| row.0[column]).collect())
+        .collect();
+    let mut row = vec![Felt::ZERO; TRACE_WIDTH];
+    let row_lookups: Vec<RowLookups<Felt, LOOKUPS>> = (0..length)
+        .map(|index| {
+            for (value, column) in row.iter_mut().zip(&columns) {
+                *value = column[index];
+            }
+            lookups(&row)
+        })
+        .collect();
+    lookup::fill_table_columns(&mut columns, &LOOKUP_COLUMNS, row_lookups.into_iter())?;
+    rom::fill_multiplicity_column(&mut columns, trace.rom)?;
+    Ok(TraceTable { columns })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::test_utils::{bus_balance, challenges, public_inputs};
+    use crate::air::{check_constraints, ConstraintViolation};
+    use crate::io::IoTape;
+    use crate::memory::{Memory, ProgramRom};
+    use crate::register::{Register, RegisterFile};
+    use crate::utils::{execute_program, execute_program_with_io, execute_segment};
+
+    /// Run a program and return the initial memory image and the execution trace
+    fn run(program: &[u16], data: &[(u16, u16)]) -> (Vec<(u16, u16)>, ExecutionTrace) {
+        run_with_input(program, data, &[])
+    }
+
+    /// Run a program reading `input` from a tape
+    fn run_with_input(
+        program: &[u16],
+        data: &[(u16, u16)],
+        input: &[u8],
+    ) -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        for (address, value) in data {
+            memory.write(*address, *value);
+        }
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(input.to_vec());
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        assert_eq!(trace.output, tape.journal());
+        (image, trace)
+    }
+
+    fn air_for(image: &[(u16, u16)], trace: &ExecutionTrace, table: &TraceTable) -> Lc3Air {
+        Lc3Air::new(table.length(), public_inputs(image, trace))
+    }
+
+    /// A program exercising every supported opcode and both operand modes
+    fn all_opcodes() -> (Vec<(u16, u16)>, ExecutionTrace) {
+        run(
+            &[
+                0b0101_000_000_1_00000,  // 3000 AND R0, R0, #0
+                0b0001_000_000_1_11111,  // 3001 ADD R0, R0, #-1
+                0b0001_001_000_0_00_000, // 3002 ADD R1, R0, R0
+                0b0101_010_001_0_00_000, // 3003 AND R2, R1, R0
+                0b0000_100_000000001,    // 3004 BRn #1 (taken)
+                0b0000_111_111111111,    // 3005 skipped
+                0b1001_011_010_111111,   // 3006 NOT R3, R2
+                0b0000_110_000000001,    // 3007 BRnz #1 (not taken)
+                0b1110_100_000010111,    // 3008 LEA R4, #23 -> 0x3020
+                0b0010_101_000010111,    // 3009 LD R5, #23 -> [0x3021]
+                0b1010_110_000010111,    // 300A LDI R6, #23 -> [[0x3022]]
+                0b0110_111_100_000011,   // 300B LDR R7, R4, #3 -> [0x3023]
+                0b0011_001_000010111,    // 300C ST R1, #23 -> 0x3024
+                0b1011_011_000010111,    // 300D STI R3, #23 -> [0x3025]
+                0b0111_000_100_000110,   // 300E STR R0, R4, #6 -> 0x3026
+                0b0100_1_00000000001,    // 300F JSR #1 -> 0x3011
+                0b1111_0000_00100101,    // 3010 HALT
+                0b1110_000_000011111,    // 3011 LEA R0, #31 -> 0x3031
+                0b1111_0000_00100010,    // 3012 PUTS
+                0b1111_0000_00100100,    // 3013 PUTSP
+                0b0001_000_000_1_00001,  // 3014 ADD R0, R0, #1
+                0b1111_0000_00100001,    // 3015 OUT
+                0b1100_000_111_000000,   // 3016 RET
+            ],
+            &[
+                (0x3021, 0x1234),
+                (0x3022, 0x4000),
+                (0x4000, 0x8001),
+                (0x3023, 0x0007),
+                (0x3025, 0x4001),
+                (0x3031, 0x0041),
+                (0x3032, 0x0042),
+                (0x3033, 0x0000),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_all_opcodes_satisfy_constraints() {
+        let (image, trace) = all_opcodes();
+        let table = build_trace_table(&trace).unwrap();
+        assert_eq!(table.width(), TRACE_WIDTH);
+        let air = air_for(&image, &trace, &table);
+        assert_eq!(
+            air.transition_constraint_names().len(),
+            air.num_transition_constraints()
+        );
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        // The chips receive every message the table sends
+        assert_eq!(
+            bus_balance(&air.public_inputs, &trace, None, &challenges()),
+            QuadExt::ZERO
+        );
+    }
+
+    #[test]
+    fn test_checker_pinpoints_violation() {
+        let (image, trace) = all_opcodes();
+        let mut table = build_trace_table(&trace).unwrap();
+        let air = air_for(&image, &trace, &table);
+
+        // Pretend ADD R1, R0, R0 produced a different value in R1
+        table.columns[col::R0 + 1][3] += Felt::ONE;
+        match check_constraints(&air, &table) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "register update");
+                assert_eq!(row, 2);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Returning somewhere other than R7, with an address still matching its bytes
+        let mut table = build_trace_table(&trace).unwrap();
+        let ret = (0..table.length())
+            .find(|&row| table.columns[col::INSTRUCTION][row] == Felt::new(0xC1C0))
+            .unwrap();
+        table.columns[col::EA][ret] += Felt::ONE;
+        table.columns[col::EA_LO][ret] += Felt::ONE;
+        match check_constraints(&air, &table) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "register target");
+                assert_eq!(row, ret);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lookups() {
+        let (image, trace) = all_opcodes();
+        let table = build_trace_table(&trace).unwrap();
+        assert_eq!(table.length(), MIN_TRACE_LENGTH);
+        let air = air_for(&image, &trace, &table);
+        let forged_column = |row: usize, changes: &[(usize, Felt)]| {
+            let mut table = table.clone();
+            for &(column, delta) in changes {
+                table.columns[column][row] += delta;
+            }
+            match check_constraints(&air, &table) {
+                Err(ConstraintViolation::Boundary { column, .. }) => column,
+                other => panic!("unexpected result: {:?}", other),
+            }
+        };
+
+        // ST R1 leaves the result at zero, which cannot claim to be negative
+        assert_eq!(
+            table.columns[col::INSTRUCTION][11],
+            Felt::new(0b0011_001_000010111)
+        );
+        assert_eq!(
+            forged_column(11, &[(col::RES_SIGN, Felt::ONE)]),
+            col::LOOKUP_SUM
+        );
+        // The table cannot claim a lookup of byte 0 that no row makes
+        assert_eq!(
+            forged_column(0, &[(col::LOOKUP_MULT, Felt::ONE)]),
+            col::LOOKUP_SUM
+        );
+        // LEA R4 computes 0x3020, whose bytes cannot be 0x120 and 0x2F
+        assert_eq!(table.columns[col::EA][7], Felt::new(0x3020));
+        assert_eq!(
+            forged_column(
+                7,
+                &[(col::EA_LO, Felt::new(0x100)), (col::EA_HI, -Felt::ONE)]
+            ),
+            col::LOOKUP_SUM
+        );
+    }
+
+    #[test]
+    fn test_paused_segment() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        memory.write(0x3000, 0b0001_000_000_1_00011); // ADD R0, R0, #3
+        memory.write(0x3001, 0b0011_000_000000001); // ST R0, #1 -> 0x3003
+        memory.write(0x3002, 0xF025); // HALT
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(Vec::new());
+        let trace = execute_segment(&mut memory, &mut registers, &mut tape, 2).unwrap();
+        assert!(!trace.halted);
+        let table = build_trace_table(&trace).unwrap();
+        let air = air_for(&image, &trace, &table);
+        assert!(air.public_inputs.final_memory_image.contains(&(0x3003, 3)));
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        // A paused run cannot claim to halt
+        let mut public_inputs = air.public_inputs.clone();
+        public_inputs.halted = true;
+        match check_constraints(&Lc3Air::new(table.length(), public_inputs), &table) {
+            Err(ConstraintViolation::Transition { name, .. }) => assert_eq!(name, "HALT halts"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Nor leave memory other than it did
+        let mut public_inputs = air.public_inputs.clone();
+        public_inputs
+            .final_memory_image
+            .retain(|&(address, _)| address != 0x3003);
+        assert_ne!(
+            bus_balance(&public_inputs, &trace, None, &challenges()),
+            QuadExt::ZERO
+        );
+    }
+
+    #[test]
+    fn test_private_input() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        memory.write(0x3000, 0xF026); // TRAP x26
+        memory.write(0x3001, 0b0001_000_000_1_00001); // ADD R0, R0, #1
+        memory.write(0x3002, 0xF025); // HALT
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(Vec::new()).with_private_input(vec![0x1234]);
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        assert_eq!(trace.final_state().unwrap().gprs[0], 0x1235);
+        assert!(trace.input.is_empty());
+
+        let table = build_padded_trace_table(&trace, 2 * MIN_TRACE_LENGTH).unwrap();
+        assert_eq!(table.length(), 2 * MIN_TRACE_LENGTH);
+        let air = air_for(&image, &trace, &table);
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+    }
+
+    #[test]
+    fn test_program_rom() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        let program = [
+            0b0010_000_000000011,   // 3000 LD R0, #3 -> [0x3004]
+            0b0001_000_000_1_11111, // 3001 ADD R0, R0, #-1
+            0b0000_001_111111110,   // 3002 BRp #-2
+            0xF025,                 // 3003 HALT
+        ];
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        memory.write(0x3004, 0x0003);
+        memory
+            .set_rom(Some(ProgramRom {
+                start: 0x3000,
+                length: 4,
+            }))
+            .unwrap();
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let trace = execute_program(&mut memory, &mut registers).unwrap();
+        let table = build_trace_table(&trace).unwrap();
+        assert_eq!(table.columns[col::ROM_FETCH][0], Felt::ONE);
+        assert_eq!(table.columns[col::FETCH][0], Felt::ZERO);
+        assert_eq!(table.columns[col::ROM_MULT][1], Felt::new(3));
+        let air = air_for(&image, &trace, &table);
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        // Fetches cannot be counted against another ROM entry
+        let mut forged = table.clone();
+        forged.columns[col::ROM_MULT][1] -= Felt::ONE;
+        forged.columns[col::ROM_MULT][2] += Felt::ONE;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Boundary { column, .. }) => assert_eq!(column, col::ROM_SUM),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Nor against a ROM holding other words
+        let mut public_inputs = air.public_inputs.clone();
+        public_inputs.memory_image[1].1 = 0b0001_000_000_1_11110;
+        assert!(check_constraints(&Lc3Air::new(table.length(), public_inputs), &table).is_err());
+
+        // A run fetching from the ROM cannot claim to fetch from memory
+        let mut public_inputs = air.public_inputs.clone();
+        public_inputs.rom = None;
+        match check_constraints(&Lc3Air::new(table.length(), public_inputs), &table) {
+            Err(ConstraintViolation::Transition { name, .. }) => assert_eq!(name, "fetch flag"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
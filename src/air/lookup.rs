@@ -0,0 +1,192 @@
+//! Lookups
+//!
+//! This module implements a LogUp lookup argument for the [LC3 AIR](super::lc3) and the
+//! [memory chip](super::memory). It proves that values of a trace table are bytes, which is how
+//! the LC3 AIR range-checks 16-bit words and sign bits, and how the memory chip range-checks time
+//! differences and distances to the program ROM. The results of AND, like those of the other ALU
+//! instructions, are proven by the [ALU chip](super::alu), which looks up pairs of bytes in the
+//! byte AND table of the [bitwise chip](super::bitwise) over the bus.
+//!
+//! ## Design
+//! - The byte table is a main column that starts at 0, climbs in steps of 0 or 1 and ends at
+//!   255, so it holds every byte and nothing else. A multiplicity column counts how often each
+//!   byte is looked up, on the first row holding it. The table must reach 255 on a row before the
+//!   last one, so trace tables have at least [`MIN_TRACE_LENGTH`] rows.
+//! - Every row of a table looks up the same number of bytes, given by its AIR as linear
+//!   combinations of its columns. Each table has its own byte table and running sum, in the
+//!   [`Columns`] its AIR names.
+//! - For a random challenge `beta`, the argument checks that
+//!   `sum of 1 / (beta - v)` over the looked-up values `v` equals `sum of m / (beta - t)` over the
+//!   table entries `t` with multiplicities `m`. Auxiliary columns hold the sum of the inverses of
+//!   each pair of lookups (pairing keeps the constraint degree at 3), the table term of the row,
+//!   and a running sum that starts and ends at zero. The sums leave out the last row, whose
+//!   lookups no constraint relies on.
+//! - A 16-bit word is range-checked as two looked-up bytes `lo + 256 * hi`. The sign bit `s` of a
+//!   word is extracted by looking up `hi - 128 * s` and `hi - 128 * s + 128`: both are bytes only
+//!   if `hi - 128 * s` is below 128, which also makes `hi` a byte.
+
+use super::{BoundaryConstraint, TraceTable};
+use crate::field::{batch_inverse, Felt, FieldElement, QuadExt};
+use alloc::{vec, vec::Vec};
+
+/// Number of entries of the byte table
+pub const TABLE_SIZE: usize = 256;
+
+/// Number of random challenges of the lookup argument: `beta`
+pub const NUM_CHALLENGES: usize = 1;
+
+/// Smallest trace table holding the table on rows before the last one
+pub const MIN_TRACE_LENGTH: usize = 2 * TABLE_SIZE;
+
+/// Index of `beta` among the challenges of the AIR, after those of the program ROM lookup
+const BETA: usize = super::rom::NUM_CHALLENGES;
+
+/// Number of auxiliary columns holding inverses for `lookups` bytes per row, one per pair
+pub const fn inverse_columns(lookups: usize) -> usize {
+    lookups.div_ceil(2)
+}
+
+/// The columns of a table taking part in the argument
+pub(super) struct Columns {
+    /// Main: the byte table
+    pub table: usize,
+    /// Main: the number of lookups of each byte
+    pub multiplicity: usize,
+    /// Auxiliary: the first of the inverse columns
+    pub inverses: usize,
+    /// Auxiliary: the table term of the row
+    pub term: usize,
+    /// Auxiliary: the running sum
+    pub sum: usize,
+}
+
+/// The bytes a row looks up
+pub(super) struct RowLookups<E, const N: usize> {
+    pub bytes: [E; N],
+}
+
+/// The constraints pinning the byte table and the running sum of a table of `length` rows
+pub(super) fn boundary_constraints(columns: &Columns, length: usize) -> Vec<BoundaryConstraint> {
+    let last = length - 1;
+    [
+        (columns.table, 0, 0),
+        (columns.table, last, TABLE_SIZE as u64 - 1),
+        (columns.sum, 0, 0),
+        (columns.sum, last, 0),
+    ]
+    .into_iter()
+    .map(|(column, row, value)| BoundaryConstraint {
+        column,
+        row,
+        value: QuadExt::from(Felt::new(value)),
+    })
+    .collect()
+}
+
+/// Write the byte table and its multiplicities into a column-major table.
+///
+/// `lookups` yields the values looked up by each row of the main columns.
+pub(super) fn fill_table_columns<const N: usize>(
+    columns: &mut [Vec<Felt>],
+    layout: &Columns,
+    lookups: impl Iterator<Item = RowLookups<Felt, N>>,
+) -> Result<(), &'static str> {
+    let length = columns[layout.table].len();
+    let bytes = (0..length as u64).map(|row| row.min(TABLE_SIZE as u64 - 1));
+    columns[layout.table] = bytes.map(Felt::new).collect();
+
+    let mut byte_counts = [0u64; TABLE_SIZE];
+    for row in lookups.take(length - 1) {
+        for value in row.bytes {
+            let byte = value.as_u64() as usize;
+            if byte >= TABLE_SIZE {
+                return Err("Looked-up value is not a byte");
+            }
+            byte_counts[byte] += 1;
+        }
+    }
+    for (entry, count) in byte_counts.into_iter().enumerate() {
+        columns[layout.multiplicity][entry] = Felt::new(count);
+    }
+    Ok(())
+}
+
+/// Evaluate the constraints of the table and of the running sum
+pub(super) fn evaluate<E: FieldElement, F: FnMut(&'static str, E), const N: usize>(
+    cur: &[E],
+    nxt: &[E],
+    layout: &Columns,
+    lookups: &RowLookups<E, N>,
+    challenges: &[E],
+    emit: &mut F,
+) {
+    let one = E::ONE;
+    let beta = challenges[BETA];
+    let table = cur[layout.table];
+    let step = nxt[layout.table] - table;
+    emit("byte table step", step * (step - one));
+
+    let mut sum = E::ZERO;
+    for (i, pair) in lookups.bytes.chunks(2).enumerate() {
+        let inverses = cur[layout.inverses + i];
+        let constraint = match pair {
+            [a, b] => inverses * (beta - *a) * (beta - *b) - (beta - *a + beta - *b),
+            _ => inverses * (beta - pair[0]) - one,
+        };
+        emit("lookup inverses", constraint);
+        sum += inverses;
+    }
+    let term = cur[layout.term];
+    emit(
+        "lookup table term",
+        term * (beta - table) - cur[layout.multiplicity],
+    );
+    emit(
+        "lookup running sum",
+        nxt[layout.sum] - cur[layout.sum] - sum + term,
+    );
+}
+
+/// Build the auxiliary columns of the lookup argument: the inverse columns, followed by the table
+/// term and the running sum.
+///
+/// `lookups` returns the values looked up by one row of the main columns.
+pub(super) fn build_sum_columns<E: FieldElement, const N: usize>(
+    table: &TraceTable,
+    layout: &Columns,
+    challenges: &[E],
+    lookups: impl Fn(&[E]) -> RowLookups<E, N>,
+) -> Vec<Vec<E>> {
+    let length = table.length();
+    let beta = challenges[BETA];
+    let mut row = vec![E::ZERO; table.width()];
+    let mut differences = Vec::with_capacity(length * (N + 1));
+    let mut multiplicities = Vec::with_capacity(length);
+    for index in 0..length {
+        table.read_row(index, &mut row);
+        differences.extend(lookups(&row).bytes.iter().map(|value| beta - *value));
+        differences.push(beta - row[layout.table]);
+        multiplicities.push(row[layout.multiplicity]);
+    }
+    let inverses = batch_inverse(&differences);
+
+    let inverse_columns = inverse_columns(N);
+    let mut columns: Vec<Vec<E>> = (0..inverse_columns + 2)
+        .map(|_| Vec::with_capacity(length))
+        .collect();
+    let mut sum = E::ZERO;
+    let rows = inverses.chunks(N + 1).zip(multiplicities);
+    for (row_inverses, multiplicity) in rows {
+        columns[inverse_columns + 1].push(sum);
+        let (lookup_inverses, table_inverses) = row_inverses.split_at(N);
+        for (i, pair) in lookup_inverses.chunks(2).enumerate() {
+            let value = pair.iter().fold(E::ZERO, |acc, inverse| acc + *inverse);
+            columns[i].push(value);
+            sum += value;
+        }
+        let term = table_inverses[0] * multiplicity;
+        columns[inverse_columns].push(term);
+        sum -= term;
+    }
+    columns
+}
@@ -0,0 +1,675 @@
+//! Memory Chip
+//!
+//! This module implements offline memory checking for the [LC3 AIR](super::lc3) in a table of its
+//! own, connected to the LC3 table by the [bus](super::bus). It proves that every read of the
+//! execution returns the value of the last write to the same address, or the value of the initial
+//! memory image if the address was never written.
+//!
+//! ## Design
+//! - Every row of the LC3 table makes up to [`SLOTS`] memory accesses: the instruction fetch, a
+//!   first data access (LD, LDR, ST, STR, the pointer of LDI/STI, the words of a PUTS/PUTSP walk)
+//!   and a second data access (the indirect access of LDI/STI). The access in slot `k` of row `i`
+//!   happens at time `SLOTS * i + k + 1`, and the LC3 table sends it on the bus as
+//!   `(tag, address, time, old, new)`.
+//! - Each non-zero word of the initial memory image is an extra access at time 0 that writes the
+//!   word over a zero.
+//! - Each word of the final memory image (every word the initial image holds or the run accesses)
+//!   is an extra access at time [`END_TIME`] that reads the word. The last access to every address
+//!   must be such a final read, so the final image holds the value the run left in every word it
+//!   touched.
+//! - The verifier sends the image writes and the final reads on the bus itself, from the public
+//!   inputs. A proof leaving out this table leaves them unreceived.
+//! - This table lists all accesses sorted by `(address, time)` and receives each of them from the
+//!   bus. On the sorted list, the constraints check that:
+//!   - the list is strictly increasing in `(address, time)`, by range-checking the difference
+//!     between consecutive entries with [`DELTA_BYTES`] bytes, which the
+//!     [lookup argument](super::lookup) proves are bytes;
+//!   - every access to an address starts from the value the previous access to that address left;
+//!   - the first access to every address starts from zero;
+//!   - the last access to every address is a final read;
+//!   - accesses inside the program ROM leave its words unchanged, see [`rom`](super::rom).
+//! - The table grows with the number of accesses rather than with the length of the run, and
+//!   runs making few accesses per instruction keep the LC3 table free of the sorted columns. Its
+//!   height would reveal that number, so zero-knowledge proofs make it at least as high as the LC3
+//!   table, which the sorted columns used to be part of.
+
+use super::bus::{self, Message};
+use super::lookup::{self, RowLookups};
+use super::rom::{self, RomBounds};
+use super::{lc3, Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::memory::ProgramRom;
+use alloc::{vec, vec::Vec};
+
+/// Number of memory accesses a row of the LC3 table can make
+pub const SLOTS: usize = 3;
+
+/// Number of bytes of the difference between consecutive sorted accesses
+pub const DELTA_BYTES: usize = 4;
+
+/// Number of bytes each row looks up: the difference to the next access and the distance to the
+/// program ROM
+pub const LOOKUPS: usize = DELTA_BYTES + 2;
+
+/// Time of the final reads, after the accesses of any trace table of fewer than `2^30` rows
+pub const END_TIME: u64 = (1 << (8 * DELTA_BYTES)) - 1;
+
+/// Smallest table the prover builds, large enough for the byte table
+pub const MIN_TRACE_LENGTH: usize = lookup::MIN_TRACE_LENGTH;
+
+/// Highest degree of the constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Column indices of the memory trace table
+pub mod col {
+    /// Sorted memory accesses: address, time, old and new value
+    pub const ADDRESS: usize = 0;
+    pub const TIME: usize = ADDRESS + 1;
+    pub const OLD: usize = TIME + 1;
+    pub const NEW: usize = OLD + 1;
+    /// Set on rows holding an access
+    pub const ACTIVE: usize = NEW + 1;
+    /// Set if the access has the same address as the one on the previous row
+    pub const SAME: usize = ACTIVE + 1;
+    /// Difference to the next access, least significant byte first
+    pub const DELTA_BYTES: usize = SAME + 1;
+    /// Set if the access is inside the program ROM
+    pub const IN_ROM: usize = DELTA_BYTES + super::DELTA_BYTES;
+    /// For an access outside the program ROM, set if it is above the ROM
+    pub const ABOVE_ROM: usize = IN_ROM + 1;
+    /// Distance from an access outside the program ROM to the ROM, minus one if below it, least
+    /// significant byte first
+    pub const ROM_GAP: usize = ABOVE_ROM + 1;
+    /// Byte table of the lookup argument
+    pub const LOOKUP_TABLE: usize = ROM_GAP + 2;
+    /// Number of lookups of each byte, on its first row in the table
+    pub const LOOKUP_MULT: usize = LOOKUP_TABLE + 1;
+    /// Number of main columns
+    pub const WIDTH: usize = LOOKUP_MULT + 1;
+
+    /// Auxiliary: running sum of the bus
+    pub const BUS_SUM: usize = WIDTH;
+    /// Auxiliary: sum of the inverses of each pair of lookups of the row
+    pub const LOOKUP_INVERSES: usize = BUS_SUM + 1;
+    /// Auxiliary: multiplicities over the differences to the table entries of the row
+    pub const LOOKUP_TERM: usize =
+        LOOKUP_INVERSES + crate::air::lookup::inverse_columns(super::LOOKUPS);
+    /// Auxiliary: running sum of the lookup argument
+    pub const LOOKUP_SUM: usize = LOOKUP_TERM + 1;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = LOOKUP_SUM + 1;
+}
+
+const LOOKUP_COLUMNS: lookup::Columns = lookup::Columns {
+    table: col::LOOKUP_TABLE,
+    multiplicity: col::LOOKUP_MULT,
+    inverses: col::LOOKUP_INVERSES,
+    term: col::LOOKUP_TERM,
+    sum: col::LOOKUP_SUM,
+};
+
+/// The memory chip AIR for a run fetching from `rom`, if any
+pub struct MemoryAir {
+    trace_length: usize,
+    rom: Option<ProgramRom>,
+    num_constraints: usize,
+}
+
+impl MemoryAir {
+    pub fn new(trace_length: usize, rom: Option<ProgramRom>) -> Self {
+        let mut num_constraints = 0;
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        let bounds = RomBounds::new(None);
+        evaluate(&zeros, &zeros, &zeros, &bounds, |_, _| num_constraints += 1);
+        MemoryAir {
+            trace_length,
+            rom,
+            num_constraints,
+        }
+    }
+}
+
+impl Air for MemoryAir {
+    fn trace_width(&self) -> usize {
+        col::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        col::FULL_WIDTH - col::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let mut index = 0;
+        let rom = RomBounds::new(self.rom);
+        evaluate(current, next, challenges, &rom, |_, value| {
+            result[index] = value;
+            index += 1;
+        });
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::with_capacity(self.num_constraints);
+        let zeros = vec![Felt::ZERO; col::FULL_WIDTH];
+        let rom = RomBounds::new(None);
+        evaluate(&zeros, &zeros, &zeros, &rom, |name, _| names.push(name));
+        names
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        let last = self.trace_length - 1;
+        let mut constraints = lookup::boundary_constraints(&LOOKUP_COLUMNS, self.trace_length);
+        for (column, row) in [(col::SAME, 0), (col::ACTIVE, last), (col::BUS_SUM, 0)] {
+            constraints.push(BoundaryConstraint {
+                column,
+                row,
+                value: QuadExt::ZERO,
+            });
+        }
+        constraints
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let mut row = vec![QuadExt::ZERO; col::WIDTH];
+        let messages: Vec<Message<QuadExt>> = (0..table.length())
+            .map(|index| {
+                table.read_row(index, &mut row);
+                received(&row)
+            })
+            .collect();
+        let mut columns = vec![bus::running_sum(
+            bus::terms(challenges, &messages).into_iter(),
+        )];
+        columns.extend(lookup::build_sum_columns(
+            table,
+            &LOOKUP_COLUMNS,
+            challenges,
+            lookups,
+        ));
+        columns
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+/// A memory access with the time it happened at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimedAccess {
+    pub address: u16,
+    pub time: u64,
+    pub old_value: u16,
+    pub new_value: u16,
+}
+
+/// Time of the access in `slot` of row `row`; time 0 belongs to the initial memory image
+pub fn timestamp(row: usize, slot: usize) -> u64 {
+    (row * SLOTS + slot + 1) as u64
+}
+
+/// Sort the accesses made by the rows, together with the image writes and the final reads, by
+/// `(address, time)`
+pub fn sort_accesses(image: &[(u16, u16)], mut accesses: Vec<TimedAccess>) -> Vec<TimedAccess> {
+    accesses.extend(image.iter().map(|&(address, value)| TimedAccess {
+        address,
+        time: 0,
+        old_value: 0,
+        new_value: value,
+    }));
+    accesses.sort_unstable();
+    let mut sorted: Vec<TimedAccess> = Vec::with_capacity(accesses.len());
+    for (index, access) in accesses.iter().enumerate() {
+        sorted.push(*access);
+        if accesses.get(index + 1).map(|next| next.address) != Some(access.address) {
+            sorted.push(TimedAccess {
+                address: access.address,
+                time: END_TIME,
+                old_value: access.new_value,
+                new_value: access.new_value,
+            });
+        }
+    }
+    sorted
+}
+
+/// An access as a bus message `(tag, address, time, old, new)`, sent `multiplicity` times
+fn message<E: FieldElement>(multiplicity: E, address: E, time: E, old: E, new: E) -> Message<E> {
+    Message {
+        multiplicity,
+        values: [E::from(Felt::new(bus::MEMORY)), address, time, old, new],
+    }
+}
+
+/// The image writes and the final reads, which the verifier sends on the bus
+pub(super) fn public_messages<E: FieldElement>(
+    image: &[(u16, u16)],
+    final_image: &[(u16, u16)],
+) -> Vec<Message<E>> {
+    let word = |value: u16| E::from(Felt::from(value));
+    let writes = image
+        .iter()
+        .map(|&(address, value)| message(E::ONE, word(address), E::ZERO, E::ZERO, word(value)));
+    let end = E::from(Felt::new(END_TIME));
+    let reads = final_image
+        .iter()
+        .map(|&(address, value)| message(E::ONE, word(address), end, word(value), word(value)));
+    writes.chain(reads).collect()
+}
+
+/// An access of a row of the LC3 table: `flag` is one if it happens
+pub(super) struct SlotAccess<E> {
+    pub flag: E,
+    pub address: E,
+    pub old: E,
+    pub new: E,
+}
+
+/// The accesses of the LC3 row with clock `clk` as the messages it sends, once for every access
+/// that happens
+pub(super) fn slot_messages<E: FieldElement>(
+    clk: E,
+    accesses: [SlotAccess<E>; SLOTS],
+) -> [Message<E>; SLOTS] {
+    let base_time = clk * E::from(Felt::new(SLOTS as u64));
+    let mut slot = 0;
+    accesses.map(|access| {
+        slot += 1;
+        let time = base_time + E::from(Felt::new(slot));
+        message(access.flag, access.address, time, access.old, access.new)
+    })
+}
+
+/// The access a row receives, once if the row holds one
+fn received<E: FieldElement>(row: &[E]) -> Message<E> {
+    message(
+        -row[col::ACTIVE],
+        row[col::ADDRESS],
+        row[col::TIME],
+        row[col::OLD],
+        row[col::NEW],
+    )
+}
+
+/// The values a row looks up: the bytes of the difference to the next access and of the distance
+/// to the program ROM
+fn lookups<E: FieldElement>(row: &[E]) -> RowLookups<E, LOOKUPS> {
+    let mut bytes = [E::ZERO; LOOKUPS];
+    bytes[..DELTA_BYTES].copy_from_slice(&row[col::DELTA_BYTES..col::DELTA_BYTES + DELTA_BYTES]);
+    bytes[DELTA_BYTES..].copy_from_slice(&row[col::ROM_GAP..col::ROM_GAP + 2]);
+    RowLookups { bytes }
+}
+
+/// Evaluate all transition constraints, passing each to `emit` with its name
+fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    rom: &RomBounds<E>,
+    mut emit: F,
+) {
+    let one = E::ONE;
+    let active = cur[col::ACTIVE];
+    let same = cur[col::SAME];
+    let next_active = nxt[col::ACTIVE];
+    let next_same = nxt[col::SAME];
+
+    emit("sorted active is boolean", active * (active - one));
+    emit("sorted same address is boolean", same * (same - one));
+    emit(
+        "sorted accesses are contiguous",
+        (one - active) * next_active,
+    );
+    emit(
+        "inactive sorted rows are not the same address",
+        (one - active) * same,
+    );
+    emit(
+        "first access to an address starts from zero",
+        active * (one - same) * cur[col::OLD],
+    );
+    emit(
+        "last access to an address is a final read",
+        active * (one - next_same) * (cur[col::TIME] - E::from(Felt::new(END_TIME))),
+    );
+
+    let delta_bytes = &cur[col::DELTA_BYTES..col::DELTA_BYTES + DELTA_BYTES];
+    let delta = delta_bytes
+        .iter()
+        .rev()
+        .fold(E::ZERO, |acc, byte| acc * E::from(Felt::new(0x100)) + *byte);
+    let address_delta = nxt[col::ADDRESS] - cur[col::ADDRESS];
+    let time_delta = nxt[col::TIME] - cur[col::TIME];
+    emit(
+        "sorted same address",
+        next_active * next_same * address_delta,
+    );
+    emit(
+        "sorted accesses are increasing",
+        next_active
+            * (next_same * (time_delta - one) + (one - next_same) * (address_delta - one) - delta),
+    );
+    emit(
+        "read returns the last written value",
+        next_active * next_same * (nxt[col::OLD] - cur[col::NEW]),
+    );
+    rom::evaluate_sorted(cur, rom, &mut emit);
+
+    lookup::evaluate(
+        cur,
+        nxt,
+        &LOOKUP_COLUMNS,
+        &lookups(cur),
+        challenges,
+        &mut emit,
+    );
+
+    let message = received(cur);
+    emit(
+        "bus running sum",
+        (nxt[col::BUS_SUM] - cur[col::BUS_SUM]) * bus::fingerprint(challenges, &message.values)
+            - message.multiplicity,
+    );
+}
+
+/// The accesses made by the rows of an LC3 table, with their times
+fn row_accesses(lc3_table: &TraceTable) -> Vec<TimedAccess> {
+    let mut row = vec![Felt::ZERO; lc3::TRACE_WIDTH];
+    let mut accesses = Vec::new();
+    for index in 0..lc3_table.length() {
+        lc3_table.read_row(index, &mut row);
+        for (slot, access) in lc3::accesses(&row).iter().enumerate() {
+            if access.flag == Felt::ONE {
+                accesses.push(TimedAccess {
+                    address: access.address.as_u64() as u16,
+                    time: timestamp(index, slot),
+                    old_value: access.old.as_u64() as u16,
+                    new_value: access.new.as_u64() as u16,
+                });
+            }
+        }
+    }
+    accesses
+}
+
+/// Lay out the accesses of an LC3 table, of a run that started from the non-zero words in
+/// `memory_image` and fetched from `rom`, as a memory trace table of at least `min_length` rows
+pub fn build_trace_table(
+    lc3_table: &TraceTable,
+    memory_image: &[(u16, u16)],
+    rom: Option<ProgramRom>,
+    min_length: usize,
+) -> Result<TraceTable, &'static str> {
+    let sorted = sort_accesses(memory_image, row_accesses(lc3_table));
+    // The last row must hold no access
+    let length = (sorted.len() + 1)
+        .next_power_of_two()
+        .max(min_length.next_power_of_two())
+        .max(MIN_TRACE_LENGTH);
+    if length > super::MAX_TRACE_LENGTH {
+        return Err("Too many memory accesses");
+    }
+    let mut columns = vec![vec![Felt::ZERO; length]; col::WIDTH];
+    fill_sorted_columns(&mut columns, &sorted);
+    rom::fill_sorted_columns(&mut columns, rom);
+    let mut row = vec![Felt::ZERO; col::WIDTH];
+    let row_lookups: Vec<RowLookups<Felt, LOOKUPS>> = (0..length)
+        .map(|index| {
+            for (value, column) in row.iter_mut().zip(&columns) {
+                *value = column[index];
+            }
+            lookups(&row)
+        })
+        .collect();
+    lookup::fill_table_columns(&mut columns, &LOOKUP_COLUMNS, row_lookups.into_iter())?;
+    Ok(TraceTable { columns })
+}
+
+/// Write `sorted` into the sorted access columns of a column-major table
+fn fill_sorted_columns(columns: &mut [Vec<Felt>], sorted: &[TimedAccess]) {
+    let mut previous: Option<&TimedAccess> = None;
+    for (row, access) in sorted.iter().enumerate() {
+        columns[col::ADDRESS][row] = Felt::from(access.address);
+        columns[col::TIME][row] = Felt::new(access.time);
+        columns[col::OLD][row] = Felt::from(access.old_value);
+        columns[col::NEW][row] = Felt::from(access.new_value);
+        columns[col::ACTIVE][row] = Felt::ONE;
+        if let Some(previous) = previous {
+            let same = previous.address == access.address;
+            let delta = if same {
+                access.time - previous.time - 1
+            } else {
+                (access.address - previous.address - 1) as u64
+            };
+            columns[col::SAME][row] = Felt::from(same);
+            for i in 0..DELTA_BYTES {
+                columns[col::DELTA_BYTES + i][row - 1] = Felt::new(delta >> (8 * i) & 0xFF);
+            }
+        }
+        previous = Some(access);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::test_utils::{bus_balance, bus_sum, challenges, public_inputs};
+    use crate::air::{check_constraints, Chip, ConstraintViolation};
+    use crate::memory::Memory;
+    use crate::proof::PublicInputs;
+    use crate::register::{Register, RegisterFile};
+    use crate::trace::ExecutionTrace;
+    use crate::utils::execute_program;
+
+    /// Run a program loaded at 0x3000 with extra data words, fetching it from a ROM if `rom` is
+    /// set, and return the initial memory image and the execution trace
+    fn run(
+        program: &[u16],
+        data: &[(u16, u16)],
+        rom: Option<ProgramRom>,
+    ) -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        for (address, value) in data {
+            memory.write(*address, *value);
+        }
+        memory.set_rom(rom).unwrap();
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let trace = execute_program(&mut memory, &mut registers).unwrap();
+        (image, trace)
+    }
+
+    /// A program making direct and indirect loads and stores
+    fn loads_and_stores() -> (Vec<(u16, u16)>, ExecutionTrace) {
+        run(
+            &[
+                0b0010_001_000000100, // 3000 LD R1, #4 -> [0x3005]
+                0b1010_010_000000100, // 3001 LDI R2, #4 -> [[0x3006]]
+                0b0011_001_000000101, // 3002 ST R1, #5 -> 0x3008
+                0b1011_010_000000011, // 3003 STI R2, #3 -> [0x3007]
+                0xF025,               // 3004 HALT
+                0x1234,
+                0x4000,
+                0x4001,
+            ],
+            &[(0x4000, 0x8001)],
+            None,
+        )
+    }
+
+    /// The memory table of a run, and its AIR
+    fn memory_table(
+        public_inputs: &PublicInputs,
+        trace: &ExecutionTrace,
+        image: &[(u16, u16)],
+    ) -> (TraceTable, MemoryAir) {
+        let lc3_table = lc3::build_trace_table(trace).unwrap();
+        let table = build_trace_table(&lc3_table, image, public_inputs.rom, 0).unwrap();
+        let air = MemoryAir::new(table.length(), public_inputs.rom);
+        (table, air)
+    }
+
+    #[test]
+    fn test_chip_satisfies_constraints() {
+        let (image, trace) = loads_and_stores();
+        let public_inputs = public_inputs(&image, &trace);
+        assert!(public_inputs.final_memory_image.contains(&(0x4001, 0x8001)));
+        let (table, air) = memory_table(&public_inputs, &trace, &image);
+        assert_eq!(table.width(), col::WIDTH);
+        assert_eq!(table.length(), MIN_TRACE_LENGTH);
+        assert_eq!(
+            air.transition_constraint_names().len(),
+            air.num_transition_constraints()
+        );
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        // The accesses the LC3 table sends and the image words the verifier sends are received
+        // by the memory table, and by nothing else
+        let challenges = challenges();
+        assert_eq!(
+            bus_balance(&public_inputs, &trace, None, &challenges),
+            QuadExt::ZERO
+        );
+        assert_ne!(
+            bus_balance(&public_inputs, &trace, Some(Chip::Memory), &challenges),
+            QuadExt::ZERO
+        );
+    }
+
+    #[test]
+    fn test_memory_consistency() {
+        let (image, trace) = loads_and_stores();
+        let public_inputs = public_inputs(&image, &trace);
+
+        // LD R1 read 0x1234 from 0x3005, which the image says holds a different word
+        let forged: Vec<(u16, u16)> = image
+            .iter()
+            .map(|&(address, value)| (address, if address == 0x3005 { 0x9999 } else { value }))
+            .collect();
+        let (table, air) = memory_table(&public_inputs, &trace, &forged);
+        match check_constraints(&air, &table) {
+            Err(ConstraintViolation::Transition { name, .. }) => {
+                assert_eq!(name, "read returns the last written value");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // The accesses are consistent, but with an image other than the one in the public inputs
+        let (table, air) = memory_table(&public_inputs, &trace, &image);
+        let challenges = challenges();
+        let mut forged_inputs = public_inputs.clone();
+        forged_inputs.memory_image = forged;
+        let others = bus_balance(&forged_inputs, &trace, Some(Chip::Memory), &challenges);
+        assert_ne!(others + bus_sum(&air, &table, &challenges), QuadExt::ZERO);
+
+        // Moving the fetch of 0x3000 to the time of the image write breaks the ordering
+        let mut forged = table.clone();
+        assert_eq!(forged.columns[col::ADDRESS][1], Felt::new(0x3000));
+        forged.columns[col::TIME][1] = Felt::ZERO;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "sorted accesses are increasing");
+                assert_eq!(row, 0);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_program_rom() {
+        let rom = ProgramRom {
+            start: 0x3000,
+            length: 4,
+        };
+        let (image, trace) = run(
+            &[
+                0b0010_000_000000011,   // 3000 LD R0, #3 -> [0x3004]
+                0b0001_000_000_1_11111, // 3001 ADD R0, R0, #-1
+                0b0000_001_111111110,   // 3002 BRp #-2
+                0xF025,                 // 3003 HALT
+            ],
+            &[(0x3004, 0x0003)],
+            Some(rom),
+        );
+        let public_inputs = public_inputs(&image, &trace);
+        let (table, air) = memory_table(&public_inputs, &trace, &image);
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+        let challenges = challenges();
+        assert_eq!(
+            bus_balance(&public_inputs, &trace, None, &challenges),
+            QuadExt::ZERO
+        );
+
+        // A sorted access inside the ROM cannot hide there
+        let mut forged = table.clone();
+        let row = (0..table.length())
+            .find(|&row| table.columns[col::IN_ROM][row] == Felt::ONE)
+            .unwrap();
+        forged.columns[col::IN_ROM][row] = Felt::ZERO;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, .. }) => {
+                assert_eq!(name, "sorted access outside the ROM")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_program_rom_is_read_only() {
+        // Overwrite HALT and restore it, which the executor refuses with a ROM
+        let (image, mut trace) = run(
+            &[
+                0b0010_001_000000010, // 3000 LD R1, #2 -> [0x3003]
+                0b0011_000_000000001, // 3001 ST R0, #1 -> 0x3003
+                0b0011_001_000000000, // 3002 ST R1, #0 -> 0x3003
+                0xF025,               // 3003 HALT
+            ],
+            &[],
+            None,
+        );
+        assert_eq!(trace.final_memory_image(&image), image);
+
+        // The same run, as if it had fetched from a ROM
+        trace.rom = Some(ProgramRom {
+            start: 0x3000,
+            length: 4,
+        });
+        for step in &mut trace.steps {
+            step.memory_accesses.remove(0);
+        }
+        let public_inputs = public_inputs(&image, &trace);
+        assert_eq!(public_inputs.check_rom(), Ok(()));
+        let (table, air) = memory_table(&public_inputs, &trace, &image);
+        match check_constraints(&air, &table) {
+            Err(ConstraintViolation::Transition { name, .. }) => {
+                assert_eq!(name, "ROM words are read-only")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Algebraic Intermediate Representation Module
+//!
+//! An AIR describes a computation as a table of field elements (the trace table) together with
+//! polynomial constraints over pairs of consecutive rows and over single cells. The STARK prover
+//! shows that it knows a table satisfying these constraints.
+//!
+//! An AIR may also have auxiliary columns. They are built by the prover only after the main
+//! columns are committed, from random challenges drawn at that point, which is what permutation
+//! and lookup arguments need. The challenges are drawn from the quadratic extension [`QuadExt`],
+//! since the soundness error of these arguments grows with the trace length over the size of the
+//! field they are drawn from, so the auxiliary columns hold extension elements.
+//!
+//! - [`lc3`]: The AIR of an LC3 run, encoding the semantics of every instruction.
+//! - [`rom`]: Instruction fetches of the LC3 AIR from a read-only program ROM.
+//! - [`lookup`]: Range checks by lookups into a byte table.
+//! - [`io`]: The I/O chip, which binds the input bytes and output journal of an LC3 run to the
+//!   public inputs.
+//! - [`bus`]: Messages between the tables of a proof, such as the LC3 table and its chips.
+//! - [`sha256`]: The SHA-256 chip, which proves the compressions requested by `TRAP x27`.
+//! - [`alu`]: The ALU chip, which proves the results of `ADD`, `AND` and `NOT`.
+//! - [`bitwise`]: The bitwise chip, which holds the byte AND table the ALU chip looks up.
+//! - [`memory`]: The memory chip, which proves the memory accesses consistent by offline memory
+//!   checking.
+//! - [`branch`]: The branch chip, which proves where `BR`, `JMP` and `JSR`/`JSRR` go next.
+//! - [`check_constraints`]: Evaluates an AIR against a concrete trace table and reports the first
+//!   violated constraint, which is the first thing to reach for when a proof fails to generate.
+//!
+//! Memory accesses, I/O, ALU instructions with their byte table, control flow and SHA-256 hashes
+//! are proven by [`Chip`]s.
+
+pub mod alu;
+pub mod bitwise;
+pub mod branch;
+pub mod bus;
+pub mod io;
+pub mod lc3;
+pub mod lookup;
+pub mod memory;
+pub mod rom;
+pub mod sha256;
+
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::proof::PublicInputs;
+use crate::trace::ExecutionTrace;
+use crate::transcript::{Sha256Sponge, Transcript};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+pub use lc3::{
+    build_padded_trace_table, build_trace_table, Lc3Air, MAX_CONSTRAINT_DEGREE, MAX_TRACE_LENGTH,
+    MIN_TRACE_LENGTH,
+};
+
+/// A constraint pinning `column` at `row` to `value`. Values of auxiliary columns may depend on
+/// the challenges, so they are extension elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundaryConstraint {
+    pub column: usize,
+    pub row: usize,
+    pub value: QuadExt,
+}
+
+/// An algebraic intermediate representation the STARK prover can prove.
+///
+/// AIRs are `Sync` so that the prover can evaluate constraints on several threads.
+pub trait Air: Sync {
+    /// Number of main columns of the trace table
+    fn trace_width(&self) -> usize;
+
+    /// Number of auxiliary columns, which follow the main columns in every row
+    fn aux_width(&self) -> usize {
+        0
+    }
+
+    /// Number of random challenges the auxiliary columns are built from
+    fn num_aux_challenges(&self) -> usize {
+        0
+    }
+
+    /// Number of rows of the trace table, a power of two
+    fn trace_length(&self) -> usize;
+
+    /// Number of transition constraints
+    fn num_transition_constraints(&self) -> usize;
+
+    /// Highest degree of any transition constraint, counted in trace columns
+    fn max_constraint_degree(&self) -> usize;
+
+    /// Evaluate the transition constraints on two consecutive rows.
+    ///
+    /// Rows hold the main columns followed by the auxiliary columns, and `challenges` are the
+    /// auxiliary challenges. All constraints evaluate to zero on every pair of rows of a valid
+    /// trace, except the pair that wraps around from the last row to the first.
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    );
+
+    /// Human-readable names of the transition constraints, in evaluation order
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        vec!["transition"; self.num_transition_constraints()]
+    }
+
+    /// Constraints on single cells of the trace table, given the auxiliary challenges
+    fn boundary_constraints(&self, challenges: &[QuadExt]) -> Vec<BoundaryConstraint>;
+
+    /// Build the auxiliary columns of `table` from the auxiliary challenges
+    fn build_aux_columns(&self, _table: &TraceTable, _challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        Vec::new()
+    }
+
+    /// Columns known to the verifier that repeat with a period dividing the trace length, each
+    /// given by its values over one period, whose length is a power of two.
+    ///
+    /// Their values follow the auxiliary columns in the rows passed to `evaluate_transition`.
+    fn periodic_columns(&self) -> Vec<Vec<Felt>> {
+        Vec::new()
+    }
+
+    /// Auxiliary columns holding running sums of the [`bus`] between tables. Their values on the
+    /// last row are claimed in the proof, and the claims of all tables of a proof add up to zero.
+    fn bus_columns(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Number of trace-length chunks the composition polynomial is split into
+    fn num_composition_chunks(&self) -> usize {
+        self.max_constraint_degree().saturating_sub(1).max(1)
+    }
+}
+
+/// A table proven alongside the LC3 table and connected to it by the [`bus`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Chip {
+    /// The [`sha256`] chip
+    Sha256,
+    /// The [`alu`] chip
+    Alu,
+    /// The [`bitwise`] chip
+    Bitwise,
+    /// The [`memory`] chip
+    Memory,
+    /// The [`io`] chip
+    Io,
+    /// The [`branch`] chip
+    Branch,
+}
+
+impl Chip {
+    /// Every chip, in the order their tables are proven
+    pub const ALL: [Chip; 6] = [
+        Chip::Sha256,
+        Chip::Alu,
+        Chip::Bitwise,
+        Chip::Memory,
+        Chip::Io,
+        Chip::Branch,
+    ];
+
+    /// Identifier of the chip in receipts and transcripts
+    pub fn id(self) -> u8 {
+        match self {
+            Chip::Sha256 => 0,
+            Chip::Alu => 1,
+            Chip::Bitwise => 2,
+            Chip::Memory => 3,
+            Chip::Io => 4,
+            Chip::Branch => 5,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Chip::Sha256),
+            1 => Some(Chip::Alu),
+            2 => Some(Chip::Bitwise),
+            3 => Some(Chip::Memory),
+            4 => Some(Chip::Io),
+            5 => Some(Chip::Branch),
+            _ => None,
+        }
+    }
+
+    /// Highest degree of the constraints of the chip
+    pub fn max_constraint_degree(self) -> usize {
+        match self {
+            Chip::Sha256 => sha256::MAX_CONSTRAINT_DEGREE,
+            Chip::Alu => alu::MAX_CONSTRAINT_DEGREE,
+            Chip::Bitwise => bitwise::MAX_CONSTRAINT_DEGREE,
+            Chip::Memory => memory::MAX_CONSTRAINT_DEGREE,
+            Chip::Io => io::MAX_CONSTRAINT_DEGREE,
+            Chip::Branch => branch::MAX_CONSTRAINT_DEGREE,
+        }
+    }
+
+    /// Smallest table of the chip
+    pub fn min_trace_length(self) -> usize {
+        match self {
+            Chip::Sha256 => sha256::MIN_TRACE_LENGTH,
+            Chip::Alu => alu::MIN_TRACE_LENGTH,
+            Chip::Bitwise => bitwise::MIN_TRACE_LENGTH,
+            Chip::Memory => memory::MIN_TRACE_LENGTH,
+            Chip::Io => io::MIN_TRACE_LENGTH,
+            Chip::Branch => branch::MIN_TRACE_LENGTH,
+        }
+    }
+
+    /// The AIR of a table of the chip with `trace_length` rows, in a proof of a run with the given
+    /// public inputs
+    pub fn air(self, trace_length: usize, public_inputs: &PublicInputs) -> ChipAir {
+        match self {
+            Chip::Sha256 => ChipAir::Sha256(sha256::Sha256Air::new(trace_length)),
+            Chip::Alu => ChipAir::Alu(alu::AluAir::new(trace_length)),
+            Chip::Bitwise => ChipAir::Bitwise(bitwise::BitwiseAir::new(trace_length)),
+            Chip::Memory => {
+                ChipAir::Memory(memory::MemoryAir::new(trace_length, public_inputs.rom))
+            }
+            Chip::Io => ChipAir::Io(io::IoAir::new(
+                trace_length,
+                public_inputs.input.len(),
+                public_inputs.output.len(),
+            )),
+            Chip::Branch => ChipAir::Branch(branch::BranchAir::new(trace_length)),
+        }
+    }
+
+    /// Lay out the part of a run the chip proves as a table of at least `min_length` rows, or
+    /// return `None` if the run does not use the chip.
+    ///
+    /// `lc3_table` is the LC3 table of the run, whose rows make the memory accesses and I/O.
+    /// `hiding` is the length of the LC3 table of a zero-knowledge proof. The ALU, I/O and branch
+    /// tables then have as many rows, whatever the run, so that their height reveals nothing
+    /// about the private data, the memory table at least as many, and the bitwise table is
+    /// included even if the run does not use it.
+    pub fn build_trace_table(
+        self,
+        trace: &ExecutionTrace,
+        lc3_table: &TraceTable,
+        public_inputs: &PublicInputs,
+        min_length: usize,
+        hiding: Option<usize>,
+    ) -> Result<Option<TraceTable>, &'static str> {
+        match self {
+            Chip::Sha256 => sha256::build_trace_table(trace, min_length),
+            Chip::Alu => match hiding {
+                Some(length) => {
+                    alu::build_hiding_trace_table(trace, length.max(min_length)).map(Some)
+                }
+                None => alu::build_trace_table(trace, min_length),
+            },
+            Chip::Bitwise => match hiding {
+                Some(_) => bitwise::build_hiding_trace_table(trace, min_length).map(Some),
+                None => bitwise::build_trace_table(trace, min_length),
+            },
+            Chip::Memory => memory::build_trace_table(
+                lc3_table,
+                &public_inputs.memory_image,
+                public_inputs.rom,
+                hiding.map_or(min_length, |length| length.max(min_length)),
+            )
+            .map(Some),
+            Chip::Io => match hiding {
+                Some(length) => {
+                    io::build_hiding_trace_table(lc3_table, length.max(min_length)).map(Some)
+                }
+                None => io::build_trace_table(lc3_table, min_length),
+            },
+            Chip::Branch => match hiding {
+                Some(length) => {
+                    branch::build_hiding_trace_table(trace, length.max(min_length)).map(Some)
+                }
+                None => branch::build_trace_table(trace, min_length),
+            },
+        }
+    }
+}
+
+/// The AIR of a [`Chip`], which delegates to the AIR of the chip
+pub enum ChipAir {
+    Sha256(sha256::Sha256Air),
+    Alu(alu::AluAir),
+    Bitwise(bitwise::BitwiseAir),
+    Memory(memory::MemoryAir),
+    Io(io::IoAir),
+    Branch(branch::BranchAir),
+}
+
+macro_rules! delegate {
+    ($self:ident, $air:ident => $call:expr) => {
+        match $self {
+            ChipAir::Sha256($air) => $call,
+            ChipAir::Alu($air) => $call,
+            ChipAir::Bitwise($air) => $call,
+            ChipAir::Memory($air) => $call,
+            ChipAir::Io($air) => $call,
+            ChipAir::Branch($air) => $call,
+        }
+    };
+}
+
+impl Air for ChipAir {
+    fn trace_width(&self) -> usize {
+        delegate!(self, air => air.trace_width())
+    }
+
+    fn aux_width(&self) -> usize {
+        delegate!(self, air => air.aux_width())
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        delegate!(self, air => air.num_aux_challenges())
+    }
+
+    fn trace_length(&self) -> usize {
+        delegate!(self, air => air.trace_length())
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        delegate!(self, air => air.num_transition_constraints())
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        delegate!(self, air => air.max_constraint_degree())
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        delegate!(self, air => air.evaluate_transition(current, next, challenges, result))
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        delegate!(self, air => air.transition_constraint_names())
+    }
+
+    fn boundary_constraints(&self, challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        delegate!(self, air => air.boundary_constraints(challenges))
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        delegate!(self, air => air.build_aux_columns(table, challenges))
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<Felt>> {
+        delegate!(self, air => air.periodic_columns())
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        delegate!(self, air => air.bus_columns())
+    }
+
+    fn num_composition_chunks(&self) -> usize {
+        delegate!(self, air => air.num_composition_chunks())
+    }
+}
+
+/// Column-major table of field elements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceTable {
+    pub columns: Vec<Vec<Felt>>,
+}
+
+impl TraceTable {
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn length(&self) -> usize {
+        self.columns.first().map_or(0, |column| column.len())
+    }
+
+    /// Copy row `index` into `row`, embedding it into the field of `row`
+    pub fn read_row<E: FieldElement>(&self, index: usize, row: &mut [E]) {
+        for (value, column) in row.iter_mut().zip(&self.columns) {
+            *value = E::from(column[index]);
+        }
+    }
+}
+
+/// A constraint that does not hold on a concrete trace table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    /// The transition constraint `name` (at `index`) fails between `row` and `row + 1`
+    Transition {
+        name: &'static str,
+        index: usize,
+        row: usize,
+        value: QuadExt,
+    },
+    /// The boundary constraint on `column` fails at `row`
+    Boundary {
+        column: usize,
+        row: usize,
+        expected: QuadExt,
+        actual: QuadExt,
+    },
+    /// The table does not have the shape the AIR expects
+    Shape,
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::Transition {
+                name,
+                index,
+                row,
+                value,
+            } => write!(
+                f,
+                "transition constraint #{} `{}` violated between rows {} and {} (evaluates to {})",
+                index,
+                name,
+                row,
+                row + 1,
+                value
+            ),
+            ConstraintViolation::Boundary {
+                column,
+                row,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "boundary constraint on column {} violated at row {}: expected {}, found {}",
+                column, row, expected, actual
+            ),
+            ConstraintViolation::Shape => write!(f, "trace table does not match the AIR shape"),
+        }
+    }
+}
+
+/// Evaluate every constraint of `air` on the main columns in `table` and report the first one that
+/// does not hold.
+///
+/// The auxiliary columns are built from fixed pseudo-random challenges. Boundary constraints on
+/// auxiliary columns are checked last: a running sum ending at the wrong value is usually a
+/// symptom of a violation reported by the other constraints. Bus sums are not checked, since they
+/// only balance across all tables of a proof.
+pub fn check_constraints<A: Air>(air: &A, table: &TraceTable) -> Result<(), ConstraintViolation> {
+    let n = air.trace_length();
+    if table.width() != air.trace_width() || table.length() != n {
+        return Err(ConstraintViolation::Shape);
+    }
+
+    let mut transcript = Transcript::<Sha256Sponge>::new(b"lc3-zkvm-check-constraints");
+    let challenges: Vec<QuadExt> = (0..air.num_aux_challenges())
+        .map(|_| transcript.challenge("aux challenge"))
+        .collect();
+    let aux_columns = air.build_aux_columns(table, &challenges);
+    if aux_columns.len() != air.aux_width() || aux_columns.iter().any(|c| c.len() != n) {
+        return Err(ConstraintViolation::Shape);
+    }
+    let main_width = table.width();
+    let width = main_width + aux_columns.len();
+    let cell = |column: usize, row: usize| match column.checked_sub(main_width) {
+        Some(aux) => aux_columns[aux][row],
+        None => QuadExt::from(table.columns[column][row]),
+    };
+
+    let (main_boundary, aux_boundary): (Vec<_>, Vec<_>) = air
+        .boundary_constraints(&challenges)
+        .into_iter()
+        .partition(|constraint| constraint.column < air.trace_width());
+    let check_boundary = |constraints: Vec<BoundaryConstraint>| {
+        for constraint in constraints {
+            let actual = cell(constraint.column, constraint.row);
+            if actual != constraint.value {
+                return Err(ConstraintViolation::Boundary {
+                    column: constraint.column,
+                    row: constraint.row,
+                    expected: constraint.value,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    };
+    check_boundary(main_boundary)?;
+
+    let names = air.transition_constraint_names();
+    let periodic = air.periodic_columns();
+    let read_row = |index: usize, row: &mut [QuadExt]| {
+        table.read_row(index, row);
+        for (value, column) in row[main_width..].iter_mut().zip(&aux_columns) {
+            *value = column[index];
+        }
+        for (value, column) in row[width..].iter_mut().zip(&periodic) {
+            *value = QuadExt::from(column[index % column.len()]);
+        }
+    };
+    let mut current = vec![QuadExt::ZERO; width + periodic.len()];
+    let mut next = current.clone();
+    let mut result = vec![QuadExt::ZERO; air.num_transition_constraints()];
+    for row in 0..n - 1 {
+        read_row(row, &mut current);
+        read_row(row + 1, &mut next);
+        air.evaluate_transition(&current, &next, &challenges, &mut result);
+        if let Some(index) = result.iter().position(|value| *value != QuadExt::ZERO) {
+            return Err(ConstraintViolation::Transition {
+                name: names[index],
+                index,
+                row,
+                value: result[index],
+            });
+        }
+    }
+
+    check_boundary(aux_boundary)
+}
+
+/// Helpers for the tests of the tables of a run
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod test_utils {
+    use super::*;
+
+    /// The public inputs of a run that started from the non-zero words in `image`
+    pub fn public_inputs(image: &[(u16, u16)], trace: &ExecutionTrace) -> PublicInputs {
+        PublicInputs {
+            memory_root: [0; 32],
+            memory_image: image.to_vec(),
+            input: trace.input.clone(),
+            output: trace.output.clone(),
+            initial_state: trace.initial_state().unwrap(),
+            final_state: trace.final_state().unwrap(),
+            final_memory_image: trace.final_memory_image(image),
+            halted: trace.halted,
+            rom: trace.rom,
+        }
+    }
+
+    /// Challenges with no relation to the tables
+    pub fn challenges() -> Vec<QuadExt> {
+        (0..bus::SHARED_CHALLENGES as u64)
+            .map(|i| QuadExt(Felt::new(0x1234_5678_9ABC + 77 * i), Felt::new(i + 1)))
+            .collect()
+    }
+
+    /// Value of the bus column of `air` on the last row
+    pub fn bus_sum<A: Air>(air: &A, table: &TraceTable, challenges: &[QuadExt]) -> QuadExt {
+        let aux = air.build_aux_columns(table, challenges);
+        let column = air.bus_columns()[0] - air.trace_width();
+        aux[column][table.length() - 1]
+    }
+
+    /// Sum of the bus claims of the LC3 table and the chip tables of a run, but the table of
+    /// `excluded`, and of the messages the verifier sends
+    pub fn bus_balance(
+        public_inputs: &PublicInputs,
+        trace: &ExecutionTrace,
+        excluded: Option<Chip>,
+        challenges: &[QuadExt],
+    ) -> QuadExt {
+        let table = build_trace_table(trace).unwrap();
+        let air = Lc3Air::new(table.length(), public_inputs.clone());
+        let mut balance =
+            bus_sum(&air, &table, challenges) + bus::public_sum(public_inputs, challenges);
+        for chip in Chip::ALL.into_iter().filter(|&chip| Some(chip) != excluded) {
+            let chip_table = chip.build_trace_table(trace, &table, public_inputs, 0, None);
+            if let Some(chip_table) = chip_table.unwrap() {
+                let chip_air = chip.air(chip_table.length(), public_inputs);
+                balance += bus_sum(&chip_air, &chip_table, challenges);
+            }
+        }
+        balance
+    }
+}
@@ -0,0 +1,223 @@
+//! Program ROM
+//!
+//! This module proves the instruction fetches of the [LC3 AIR](super::lc3) for runs that fetch
+//! their instructions from a read-only [`ProgramRom`] instead of memory. Such a fetch is a lookup
+//! into the ROM rather than an access sent to the [memory chip](super::memory), which saves the
+//! memory table one access per instruction.
+//!
+//! ## Design
+//! - The ROM is a preprocessed table: two periodic columns hold the addresses and words of the
+//!   ROM, padded to a power-of-two period by repeating its first entry. They depend only on the
+//!   memory image, which the program ID commits to, so the verifier builds them itself and the
+//!   proof commits to nothing for them. A trace table has at least two periods, so that every
+//!   entry appears on a row before the last one. Runs without a ROM have a table of one zero
+//!   entry, which nothing looks up.
+//! - A row fetching from the ROM looks up `(PC, instruction)`. A multiplicity column counts the
+//!   fetches of each entry, on its row in the first period.
+//! - Fetches and entries are fingerprinted as `alpha - (address + gamma * word)` for random
+//!   challenges `alpha` and `gamma`. An auxiliary running sum adds the inverse fingerprint of
+//!   every fetch and subtracts the multiplicity over the fingerprint of every entry. It starts and
+//!   ends at zero and, like the sum of the [lookup argument](super::lookup), leaves out the last
+//!   row.
+//! - The ROM words are also in the memory image, so that the program can read its code and
+//!   constants as data. The executor stops a run that writes into the ROM, and the memory chip
+//!   rules such writes out on its sorted accesses: every access inside the ROM leaves its word
+//!   unchanged, but for the image writes at time 0. A flag column marks the sorted accesses inside
+//!   the ROM. An access without the flag is proven outside the ROM by its distance to it, which
+//!   the [lookup argument](super::lookup) range-checks as two bytes: `start - 1 - address` below
+//!   the ROM and `address - end` above it. Runs without a ROM have an empty range at 0, above
+//!   which every address lies.
+
+use super::lc3::col;
+use super::{bus, memory, TraceTable};
+use crate::field::{batch_inverse, Felt, FieldElement};
+use crate::memory::{Memory, ProgramRom};
+use alloc::{vec, vec::Vec};
+use core::iter;
+
+/// Number of random challenges of the ROM lookup: `alpha` and `gamma`
+pub const NUM_CHALLENGES: usize = 2;
+
+/// The program ROM as the constraints see it: `present` is one if the run fetches from a ROM,
+/// which spans the addresses from `start` up to `end`, exclusive
+pub(super) struct RomBounds<E> {
+    pub present: E,
+    pub start: E,
+    pub end: E,
+}
+
+impl<E: FieldElement> RomBounds<E> {
+    pub fn new(rom: Option<ProgramRom>) -> Self {
+        let (start, end) = bounds(rom);
+        RomBounds {
+            present: E::from(Felt::from(rom.is_some())),
+            start: E::from(Felt::new(start)),
+            end: E::from(Felt::new(end)),
+        }
+    }
+}
+
+/// First address of the ROM and the address after its last one, or an empty range at 0
+fn bounds(rom: Option<ProgramRom>) -> (u64, u64) {
+    rom.map_or((0, 0), |rom| {
+        (rom.start as u64, rom.start as u64 + rom.length as u64)
+    })
+}
+
+/// Number of rows over which the ROM columns repeat
+pub fn period(rom: Option<ProgramRom>) -> usize {
+    rom.map_or(1, |rom| (rom.length as usize).next_power_of_two())
+}
+
+/// Smallest trace table holding every entry of the ROM on a row before the last one
+pub fn min_trace_length(rom: Option<ProgramRom>) -> usize {
+    2 * period(rom)
+}
+
+/// Values over one period of the ROM address and word columns, with the words read from the
+/// memory image
+pub(super) fn periodic_columns(
+    rom: Option<ProgramRom>,
+    memory_image: &[(u16, u16)],
+) -> Vec<Vec<Felt>> {
+    let Some(rom) = rom else {
+        return vec![vec![Felt::ZERO]; 2];
+    };
+    let memory = Memory::from_image(memory_image);
+    let entries = rom.addresses().chain(iter::repeat(rom.start));
+    let (addresses, words) = entries
+        .take(period(Some(rom)))
+        .map(|address| (Felt::from(address), Felt::from(memory[address])))
+        .unzip();
+    vec![addresses, words]
+}
+
+/// `alpha - (address + gamma * word)`
+fn fingerprint<E: FieldElement>(challenges: &[E], address: E, word: E) -> E {
+    let (alpha, gamma) = (challenges[0], challenges[1]);
+    alpha - (address + gamma * word)
+}
+
+/// Write the number of fetches of every ROM entry into the multiplicity column of a column-major
+/// table
+pub(super) fn fill_multiplicity_column(
+    columns: &mut [Vec<Felt>],
+    rom: Option<ProgramRom>,
+) -> Result<(), &'static str> {
+    let Some(rom) = rom else {
+        return Ok(());
+    };
+    let length = columns[col::ROM_MULT].len();
+    let mut counts = vec![0u64; rom.length as usize];
+    let fetches = columns[col::ROM_FETCH].iter().zip(&columns[col::PC]);
+    for (&fetch, pc) in fetches.take(length - 1) {
+        if fetch == Felt::ONE {
+            let pc = pc.as_u64() as u16;
+            if !rom.contains(pc) {
+                return Err("Instruction fetch outside the program ROM");
+            }
+            counts[(pc - rom.start) as usize] += 1;
+        }
+    }
+    for (entry, count) in counts.into_iter().enumerate() {
+        columns[col::ROM_MULT][entry] = Felt::new(count);
+    }
+    Ok(())
+}
+
+/// Write the ROM flag, side and distance of every sorted access into a column-major memory table
+pub(super) fn fill_sorted_columns(columns: &mut [Vec<Felt>], rom: Option<ProgramRom>) {
+    use memory::col;
+    let (start, end) = bounds(rom);
+    for row in 0..columns[col::ADDRESS].len() {
+        let address = columns[col::ADDRESS][row].as_u64();
+        let (inside, above, gap) = if address < start {
+            (false, false, start - 1 - address)
+        } else if address >= end {
+            (false, true, address - end)
+        } else {
+            (true, false, 0)
+        };
+        columns[col::IN_ROM][row] = Felt::from(inside);
+        columns[col::ABOVE_ROM][row] = Felt::from(above);
+        columns[col::ROM_GAP][row] = Felt::new(gap & 0xFF);
+        columns[col::ROM_GAP + 1][row] = Felt::new(gap >> 8);
+    }
+}
+
+/// Evaluate the constraints on the sorted accesses of a memory table inside the ROM
+pub(super) fn evaluate_sorted<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    rom: &RomBounds<E>,
+    emit: &mut F,
+) {
+    use memory::col;
+    let one = E::ONE;
+    let inside = cur[col::IN_ROM];
+    let above = cur[col::ABOVE_ROM];
+    let address = cur[col::ADDRESS];
+    let gap = cur[col::ROM_GAP] + cur[col::ROM_GAP + 1] * E::from(Felt::new(0x100));
+    emit("sorted ROM flag is boolean", inside * (inside - one));
+    emit("sorted ROM side is boolean", above * (above - one));
+    emit(
+        "sorted access outside the ROM",
+        (one - inside)
+            * (gap - above * (address - rom.end) - (one - above) * (rom.start - one - address)),
+    );
+    emit(
+        "ROM words are read-only",
+        inside * cur[col::TIME] * (cur[col::NEW] - cur[col::OLD]),
+    );
+}
+
+/// Evaluate the constraints of the running sum of the fetches of an LC3 table
+pub(super) fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    rom: &RomBounds<E>,
+    emit: &mut F,
+) {
+    let one = E::ONE;
+    let fetch = fingerprint(challenges, cur[col::PC], cur[col::INSTRUCTION]);
+    let entry = fingerprint(challenges, cur[col::ROM_ADDRESS], cur[col::ROM_WORD]);
+    let multiplicity = cur[col::ROM_MULT];
+    emit(
+        "no ROM entries without a ROM",
+        (one - rom.present) * multiplicity,
+    );
+    emit(
+        "ROM running sum",
+        (nxt[col::ROM_SUM] - cur[col::ROM_SUM]) * fetch * entry - cur[col::ROM_FETCH] * entry
+            + multiplicity * fetch,
+    );
+}
+
+/// Build the running sum column from the main columns and the periodic ROM columns
+pub(super) fn build_sum_column<E: FieldElement>(
+    table: &TraceTable,
+    challenges: &[E],
+    periodic: &[Vec<Felt>],
+) -> Vec<E> {
+    let length = table.length();
+    let column = |index: usize| &table.columns[index];
+    let (addresses, words) = (&periodic[0], &periodic[1]);
+    let mut fingerprints = Vec::with_capacity(2 * length);
+    for row in 0..length {
+        fingerprints.push(fingerprint(
+            challenges,
+            E::from(column(col::PC)[row]),
+            E::from(column(col::INSTRUCTION)[row]),
+        ));
+        fingerprints.push(fingerprint(
+            challenges,
+            E::from(addresses[row % addresses.len()]),
+            E::from(words[row % words.len()]),
+        ));
+    }
+    let inverses = batch_inverse(&fingerprints);
+    let row_sums = inverses.chunks(2).enumerate().map(|(row, pair)| {
+        pair[0].mul_base(column(col::ROM_FETCH)[row]) - pair[1].mul_base(column(col::ROM_MULT)[row])
+    });
+    bus::running_sum(row_sums)
+}
@@ -0,0 +1,907 @@
+//! SHA-256 Chip
+//!
+//! This module proves the SHA-256 hashes requested by `TRAP x27` in a table of its own, connected
+//! to the [LC3 table](super::lc3) by the [bus](super::bus). The LC3 table sends the message words
+//! it reads and the digest words it writes, one per row; this table receives them. A hash costs
+//! the LC3 table one row per word and this table 64 rows per block, instead of the hundreds of
+//! thousands of cycles of a hash written in LC3 instructions.
+//!
+//! ## Trace Layout
+//! - Every 64-byte block takes 64 rows, one per round. Row `t` of a block holds the working
+//!   variables `a` to `h` before round `t` as bits, the outputs of the round, the schedule word
+//!   `W_t` as bits and the 16 schedule words before it.
+//! - Columns constant over a block tell whether the block belongs to a hash the LC3 table
+//!   requested (`ACTIVE`), continues the hash of the previous block (`CONT`) or ends its hash
+//!   (`FINAL`), which hash it belongs to (`CALL`, counting the hashes of the run) and its index
+//!   within the hash (`BLOCK`). The chaining value the block starts from is kept in 8 word
+//!   columns, and the last row adds it to the output of round 63 to produce the block's digest.
+//! - Periodic columns give the round number, the round constant `K_t` and flags for the first
+//!   row, the 16 rows taking a message word and the last row of a block.
+//! - Inactive blocks hashing zeros pad the table to a power of two. The last block is always such
+//!   a padding block.
+//!
+//! ## Constraints
+//! - `Σ0`, `Σ1`, `σ0`, `σ1`, `Ch` and `Maj` are polynomials of degree at most 3 in the bits of
+//!   their inputs; rotations and shifts only reorder bits. Additions modulo `2^32` are checked
+//!   over the integers with carry bits: 3 for the new `a` and `e`, 2 for a schedule word and 1 for
+//!   a digest word. `σ0` and `σ1` get columns of their own, since the schedule recurrence only
+//!   holds from round 16 and gating it would raise their degree.
+//! - The working variables shift from row to row, compared as packed words. From round 16, `W_t`
+//!   follows the schedule recurrence; the bits of `W_(t-2)` and `W_(t-15)` are repeated in columns
+//!   of their own for `σ1` and `σ0`.
+//! - A block starts from the initial hash value if it starts a hash, and from the digest of the
+//!   previous block otherwise.
+//! - On the bus, rows 0-15 of an active block receive the two halves of `W_t` as the message words
+//!   `32 * BLOCK + 2t` and `32 * BLOCK + 2t + 1` of their hash, and the last row of the final block
+//!   of a hash receives the 16 digest words. The LC3 table sends each word once, and the words of
+//!   every hash from index 0, so the blocks of a hash are exactly the blocks it read. Digest words
+//!   are range-checked by the LC3 table, which writes them to memory.
+
+use super::bus::{self, Message, MESSAGE_WIDTH};
+use super::{Air, BoundaryConstraint, TraceTable};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::trace::ExecutionTrace;
+use alloc::{vec, vec::Vec};
+
+/// Number of rounds, and of rows per block
+pub const ROUNDS: usize = 64;
+
+/// Number of LC3 words of a 64-byte block
+pub const WORDS_PER_BLOCK: usize = 32;
+
+/// Number of LC3 words of a digest
+pub const DIGEST_WORDS: usize = 16;
+
+/// Number of bus messages a row can receive: the digest words of the last row
+pub const MESSAGES_PER_ROW: usize = DIGEST_WORDS;
+
+/// Smallest table: one block and the padding block
+pub const MIN_TRACE_LENGTH: usize = 2 * ROUNDS;
+
+/// Highest degree of the constraints
+pub const MAX_CONSTRAINT_DEGREE: usize = 3;
+
+/// Initial hash value
+pub const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Round constants
+const K: [u32; ROUNDS] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Rotation amounts of `Σ0`, `Σ1`, and rotations and shift of `σ0`, `σ1`
+const BIG_SIGMA0: [usize; 3] = [2, 13, 22];
+const BIG_SIGMA1: [usize; 3] = [6, 11, 25];
+const SMALL_SIGMA0: [usize; 3] = [7, 18, 3];
+const SMALL_SIGMA1: [usize; 3] = [17, 19, 10];
+
+/// Column indices of the SHA-256 trace table
+pub mod col {
+    /// Set on the blocks of the hashes of the run, constant over a block
+    pub const ACTIVE: usize = 0;
+    /// Set on blocks continuing the hash of the previous block
+    pub const CONT: usize = ACTIVE + 1;
+    /// Set on the last block of a hash
+    pub const FINAL: usize = CONT + 1;
+    /// Index of the block's hash among the hashes of the run
+    pub const CALL: usize = FINAL + 1;
+    /// Index of the block within its hash
+    pub const BLOCK: usize = CALL + 1;
+    /// 32 bits of each of the working variables `a` to `h`, least significant first
+    pub const STATE: usize = BLOCK + 1;
+    /// The new `a` computed by the round, and its 3 carry bits
+    pub const NEW_A: usize = STATE + 8 * 32;
+    pub const NEW_A_CARRY: usize = NEW_A + 1;
+    /// The new `e` computed by the round, and its 3 carry bits
+    pub const NEW_E: usize = NEW_A_CARRY + 3;
+    pub const NEW_E_CARRY: usize = NEW_E + 1;
+    /// 32 bits of the schedule word `W_t`
+    pub const W: usize = NEW_E_CARRY + 3;
+    /// The schedule words `W_(t-16)` to `W_(t-1)`
+    pub const WINDOW: usize = W + 32;
+    /// 32 bits of `W_(t-15)`, the input of `σ0`
+    pub const SIGMA0_BITS: usize = WINDOW + 16;
+    /// 32 bits of `W_(t-2)`, the input of `σ1`
+    pub const SIGMA1_BITS: usize = SIGMA0_BITS + 32;
+    /// `σ0(W_(t-15))` and `σ1(W_(t-2))`
+    pub const SIGMAS: usize = SIGMA1_BITS + 32;
+    /// 2 carry bits of `W_t`
+    pub const W_CARRY: usize = SIGMAS + 2;
+    /// The 8 words of the chaining value the block starts from
+    pub const H_IN: usize = W_CARRY + 2;
+    /// On the last row of a block, the high and low halves of the 8 digest words and their carries
+    pub const DIGEST_HI: usize = H_IN + 8;
+    pub const DIGEST_LO: usize = DIGEST_HI + 8;
+    pub const DIGEST_CARRY: usize = DIGEST_LO + 8;
+    /// Number of main columns
+    pub const WIDTH: usize = DIGEST_CARRY + 8;
+
+    /// Auxiliary: `multiplicity / fingerprint` of each bus message of the row
+    pub const BUS_TERMS: usize = WIDTH;
+    /// Auxiliary: running sum of the bus
+    pub const BUS_SUM: usize = BUS_TERMS + super::MESSAGES_PER_ROW;
+    /// Number of main and auxiliary columns
+    pub const FULL_WIDTH: usize = BUS_SUM + 1;
+
+    /// Periodic: round number
+    pub const ROUND: usize = FULL_WIDTH;
+    /// Periodic: set on the first row of a block
+    pub const FIRST: usize = ROUND + 1;
+    /// Periodic: set on rows 0-15 of a block, whose schedule word is a message word
+    pub const INPUT: usize = FIRST + 1;
+    /// Periodic: set on the last row of a block
+    pub const LAST: usize = INPUT + 1;
+    /// Periodic: round constant `K_t`
+    pub const K: usize = LAST + 1;
+    /// Number of columns of the rows given to the constraints
+    pub const ROW_WIDTH: usize = K + 1;
+}
+
+fn rotate_xor(x: u32, [r1, r2, r3]: [usize; 3]) -> u32 {
+    x.rotate_right(r1 as u32) ^ x.rotate_right(r2 as u32) ^ x.rotate_right(r3 as u32)
+}
+
+fn rotate_shift_xor(x: u32, [r1, r2, s]: [usize; 3]) -> u32 {
+    x.rotate_right(r1 as u32) ^ x.rotate_right(r2 as u32) ^ x >> s
+}
+
+/// The 64 schedule words of a block
+fn schedule(block: &[u32; 16]) -> [u32; ROUNDS] {
+    let mut w = [0; ROUNDS];
+    w[..16].copy_from_slice(block);
+    for t in 16..ROUNDS {
+        w[t] = rotate_shift_xor(w[t - 2], SMALL_SIGMA1)
+            .wrapping_add(w[t - 7])
+            .wrapping_add(rotate_shift_xor(w[t - 15], SMALL_SIGMA0))
+            .wrapping_add(w[t - 16]);
+    }
+    w
+}
+
+/// `T1` and `T2` of a round, as sums without reduction modulo `2^32`
+fn round_sums(state: &[u32; 8], k: u32, w: u32) -> (u64, u64) {
+    let [a, b, c, _, e, f, g, h] = *state;
+    let t1 = h as u64
+        + rotate_xor(e, BIG_SIGMA1) as u64
+        + ((e & f) ^ (!e & g)) as u64
+        + k as u64
+        + w as u64;
+    let t2 = rotate_xor(a, BIG_SIGMA0) as u64 + ((a & b) ^ (a & c) ^ (b & c)) as u64;
+    (t1, t2)
+}
+
+/// Compress one block of 16 big-endian words into the hash state
+pub fn compress(state: &mut [u32; 8], block: &[u32; 16]) {
+    let w = schedule(block);
+    let mut v = *state;
+    for t in 0..ROUNDS {
+        let (t1, t2) = round_sums(&v, K[t], w[t]);
+        v = [
+            (t1 + t2) as u32,
+            v[0],
+            v[1],
+            v[2],
+            (v[3] as u64 + t1) as u32,
+            v[4],
+            v[5],
+            v[6],
+        ];
+    }
+    for (word, value) in state.iter_mut().zip(v) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+/// The SHA-256 chip AIR
+pub struct Sha256Air {
+    trace_length: usize,
+    num_constraints: usize,
+}
+
+impl Sha256Air {
+    pub fn new(trace_length: usize) -> Self {
+        let mut num_constraints = 0;
+        let zeros = vec![Felt::ZERO; col::ROW_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |_, _| num_constraints += 1);
+        Sha256Air {
+            trace_length,
+            num_constraints,
+        }
+    }
+}
+
+impl Air for Sha256Air {
+    fn trace_width(&self) -> usize {
+        col::WIDTH
+    }
+
+    fn aux_width(&self) -> usize {
+        col::FULL_WIDTH - col::WIDTH
+    }
+
+    fn num_aux_challenges(&self) -> usize {
+        bus::SHARED_CHALLENGES
+    }
+
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn num_transition_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    fn max_constraint_degree(&self) -> usize {
+        MAX_CONSTRAINT_DEGREE
+    }
+
+    fn evaluate_transition<E: FieldElement>(
+        &self,
+        current: &[E],
+        next: &[E],
+        challenges: &[E],
+        result: &mut [E],
+    ) {
+        let mut index = 0;
+        evaluate(current, next, challenges, |_, value| {
+            result[index] = value;
+            index += 1;
+        });
+    }
+
+    fn transition_constraint_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::with_capacity(self.num_constraints);
+        let zeros = vec![Felt::ZERO; col::ROW_WIDTH];
+        evaluate(&zeros, &zeros, &zeros, |name, _| names.push(name));
+        names
+    }
+
+    fn boundary_constraints(&self, _challenges: &[QuadExt]) -> Vec<BoundaryConstraint> {
+        [col::CONT, col::CALL, col::BLOCK, col::BUS_SUM]
+            .into_iter()
+            .map(|column| BoundaryConstraint {
+                column,
+                row: 0,
+                value: QuadExt::ZERO,
+            })
+            .collect()
+    }
+
+    fn build_aux_columns(&self, table: &TraceTable, challenges: &[QuadExt]) -> Vec<Vec<QuadExt>> {
+        let length = table.length();
+        let periodic = periodic_columns();
+        let mut row = vec![QuadExt::ZERO; col::ROW_WIDTH];
+        let mut messages = Vec::with_capacity(length * MESSAGES_PER_ROW);
+        for index in 0..length {
+            table.read_row(index, &mut row);
+            for (value, column) in row[col::ROUND..].iter_mut().zip(&periodic) {
+                *value = QuadExt::from(column[index % ROUNDS]);
+            }
+            messages.extend(row_messages(&row));
+        }
+        let terms = bus::terms(challenges, &messages);
+
+        let mut columns: Vec<Vec<QuadExt>> = (0..MESSAGES_PER_ROW)
+            .map(|slot| {
+                terms
+                    .iter()
+                    .skip(slot)
+                    .step_by(MESSAGES_PER_ROW)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        let row_sums = terms
+            .chunks(MESSAGES_PER_ROW)
+            .map(|row| row.iter().fold(QuadExt::ZERO, |acc, term| acc + *term));
+        columns.push(bus::running_sum(row_sums));
+        columns
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<Felt>> {
+        periodic_columns()
+    }
+
+    fn bus_columns(&self) -> Vec<usize> {
+        vec![col::BUS_SUM]
+    }
+}
+
+/// Values over one block of the periodic columns, in column order
+fn periodic_columns() -> Vec<Vec<Felt>> {
+    let flag = |set: fn(usize) -> bool| (0..ROUNDS).map(|t| Felt::from(set(t))).collect();
+    vec![
+        (0..ROUNDS).map(|t| Felt::new(t as u64)).collect(),
+        flag(|t| t == 0),
+        flag(|t| t < 16),
+        flag(|t| t == ROUNDS - 1),
+        K.iter().map(|k| Felt::new(*k as u64)).collect(),
+    ]
+}
+
+fn constant<E: FieldElement>(value: u64) -> E {
+    E::from(Felt::new(value))
+}
+
+/// `sum(2^i * bits[i])`
+fn pack<E: FieldElement>(bits: &[E]) -> E {
+    bits.iter().rev().fold(E::ZERO, |acc, bit| acc + acc + *bit)
+}
+
+/// `x ^ y ^ z` for bits
+fn xor3<E: FieldElement>(x: E, y: E, z: E) -> E {
+    let two = constant::<E>(2);
+    x + y + z - two * (x * y + y * z + x * z) + two * two * x * y * z
+}
+
+/// `ROTR(x, r1) ^ ROTR(x, r2) ^ ROTR(x, r3)` from the bits of `x`, packed
+fn big_sigma<E: FieldElement>(bits: &[E], [r1, r2, r3]: [usize; 3]) -> E {
+    let bits: Vec<E> = (0..32)
+        .map(|i| {
+            xor3(
+                bits[(i + r1) % 32],
+                bits[(i + r2) % 32],
+                bits[(i + r3) % 32],
+            )
+        })
+        .collect();
+    pack(&bits)
+}
+
+/// `ROTR(x, r1) ^ ROTR(x, r2) ^ SHR(x, s)` from the bits of `x`, packed
+fn small_sigma<E: FieldElement>(bits: &[E], [r1, r2, s]: [usize; 3]) -> E {
+    let bits: Vec<E> = (0..32)
+        .map(|i| {
+            let shifted = if i + s < 32 { bits[i + s] } else { E::ZERO };
+            xor3(bits[(i + r1) % 32], bits[(i + r2) % 32], shifted)
+        })
+        .collect();
+    pack(&bits)
+}
+
+/// Bits of working variable `k` (0 for `a` to 7 for `h`)
+fn state_bits<E>(row: &[E], k: usize) -> &[E] {
+    &row[col::STATE + 32 * k..col::STATE + 32 * (k + 1)]
+}
+
+/// Digest word `k` of the block, on its last row
+fn digest_word<E: FieldElement>(row: &[E], k: usize) -> E {
+    row[col::DIGEST_HI + k] * constant(1 << 16) + row[col::DIGEST_LO + k]
+}
+
+/// The messages a row receives: message words on rows 0-15 and digest words on the last row,
+/// with zero multiplicity on inactive blocks and on the last row of a block that is not final
+fn row_messages<E: FieldElement>(row: &[E]) -> [Message<E>; MESSAGES_PER_ROW] {
+    let (input, last) = (row[col::INPUT], row[col::LAST]);
+    let w = &row[col::W..col::W + 32];
+    let halves = [pack(&w[16..]), pack(&w[..16])];
+    let base_index =
+        row[col::BLOCK] * constant(WORDS_PER_BLOCK as u64) + row[col::ROUND] * constant(2);
+    let tag = input * constant(bus::SHA256_INPUT) + last * constant(bus::SHA256_DIGEST);
+    core::array::from_fn(|slot| {
+        let digest_half = if slot % 2 == 0 {
+            row[col::DIGEST_HI + slot / 2]
+        } else {
+            row[col::DIGEST_LO + slot / 2]
+        };
+        let mut received = row[col::FINAL] * last;
+        let mut index = last * constant(slot as u64);
+        let mut value = last * digest_half;
+        if slot < 2 {
+            received += input;
+            index += input * (base_index + constant(slot as u64));
+            value += input * halves[slot];
+        }
+        let values: [E; MESSAGE_WIDTH] = [tag, row[col::CALL], index, value, E::ZERO];
+        Message {
+            multiplicity: -(row[col::ACTIVE] * received),
+            values,
+        }
+    })
+}
+
+/// Evaluate all transition constraints, passing each to `emit` with its name
+fn evaluate<E: FieldElement, F: FnMut(&'static str, E)>(
+    cur: &[E],
+    nxt: &[E],
+    challenges: &[E],
+    mut emit: F,
+) {
+    let one = E::ONE;
+    let two_32 = constant::<E>(1 << 32);
+    let (first, input, last) = (cur[col::FIRST], cur[col::INPUT], cur[col::LAST]);
+    let not_last = one - last;
+
+    // Booleans
+    for column in [col::ACTIVE, col::CONT, col::FINAL] {
+        let flag = cur[column];
+        emit("block flag is boolean", flag * (flag - one));
+    }
+    for bit in &cur[col::STATE..col::STATE + 8 * 32] {
+        emit("state bit is boolean", *bit * (*bit - one));
+    }
+    for bit in &cur[col::W..col::W + 32] {
+        emit("schedule bit is boolean", *bit * (*bit - one));
+    }
+    for bit in &cur[col::SIGMA0_BITS..col::SIGMA1_BITS + 32] {
+        emit("sigma input bit is boolean", *bit * (*bit - one));
+    }
+    let carries = [
+        col::NEW_A_CARRY..col::NEW_A_CARRY + 3,
+        col::NEW_E_CARRY..col::NEW_E_CARRY + 3,
+        col::W_CARRY..col::W_CARRY + 2,
+        col::DIGEST_CARRY..col::DIGEST_CARRY + 8,
+    ];
+    for bit in carries.into_iter().flat_map(|range| &cur[range]) {
+        emit("carry bit is boolean", *bit * (*bit - one));
+    }
+
+    // Round
+    let word = |k: usize| pack(state_bits(cur, k));
+    let (a, b, c, e, f, g) = (
+        state_bits(cur, 0),
+        state_bits(cur, 1),
+        state_bits(cur, 2),
+        state_bits(cur, 4),
+        state_bits(cur, 5),
+        state_bits(cur, 6),
+    );
+    let ch: Vec<E> = (0..32).map(|i| e[i] * f[i] + (one - e[i]) * g[i]).collect();
+    let maj: Vec<E> = (0..32)
+        .map(|i| a[i] * b[i] + a[i] * c[i] + b[i] * c[i] - constant::<E>(2) * a[i] * b[i] * c[i])
+        .collect();
+    let w = pack(&cur[col::W..col::W + 32]);
+    let t1 = word(7) + big_sigma(e, BIG_SIGMA1) + pack(&ch) + cur[col::K] + w;
+    let t2 = big_sigma(a, BIG_SIGMA0) + pack(&maj);
+    let carry = |column: usize, bits: usize| pack(&cur[column..column + bits]);
+    let new_a = cur[col::NEW_A];
+    let new_e = cur[col::NEW_E];
+    emit(
+        "new a",
+        new_a + carry(col::NEW_A_CARRY, 3) * two_32 - t1 - t2,
+    );
+    emit(
+        "new e",
+        new_e + carry(col::NEW_E_CARRY, 3) * two_32 - word(3) - t1,
+    );
+    let next_word = |k: usize| pack(state_bits(nxt, k));
+    emit("a takes the new a", not_last * (next_word(0) - new_a));
+    emit("e takes the new e", not_last * (next_word(4) - new_e));
+    for k in [1, 2, 3, 5, 6, 7] {
+        emit(
+            "working variables shift",
+            not_last * (next_word(k) - word(k - 1)),
+        );
+    }
+
+    // Message schedule
+    let window = |i: usize| cur[col::WINDOW + i];
+    let sigma0_bits = &cur[col::SIGMA0_BITS..col::SIGMA0_BITS + 32];
+    let sigma1_bits = &cur[col::SIGMA1_BITS..col::SIGMA1_BITS + 32];
+    emit("sigma0 input is W(t-15)", pack(sigma0_bits) - window(1));
+    emit("sigma1 input is W(t-2)", pack(sigma1_bits) - window(14));
+    let (sigma0, sigma1) = (cur[col::SIGMAS], cur[col::SIGMAS + 1]);
+    emit("sigma0", sigma0 - small_sigma(sigma0_bits, SMALL_SIGMA0));
+    emit("sigma1", sigma1 - small_sigma(sigma1_bits, SMALL_SIGMA1));
+    emit(
+        "schedule word",
+        (one - input)
+            * (w + carry(col::W_CARRY, 2) * two_32 - sigma1 - window(9) - sigma0 - window(0)),
+    );
+    for i in 0..15 {
+        emit(
+            "schedule window shifts",
+            nxt[col::WINDOW + i] - window(i + 1),
+        );
+    }
+    emit("schedule window takes W", nxt[col::WINDOW + 15] - w);
+
+    // Chaining
+    for column in [col::ACTIVE, col::CONT, col::FINAL, col::CALL, col::BLOCK] {
+        emit(
+            "block column is constant",
+            not_last * (nxt[column] - cur[column]),
+        );
+    }
+    let outputs = [
+        new_a,
+        word(0),
+        word(1),
+        word(2),
+        new_e,
+        word(4),
+        word(5),
+        word(6),
+    ];
+    for (k, output) in outputs.into_iter().enumerate() {
+        let h_in = cur[col::H_IN + k];
+        emit(
+            "chaining value is constant",
+            not_last * (nxt[col::H_IN + k] - h_in),
+        );
+        emit(
+            "block starts from its chaining value",
+            first * (h_in - word(k)),
+        );
+        emit(
+            "hash starts from the initial hash value",
+            first * (one - cur[col::CONT]) * (h_in - constant(INITIAL_STATE[k] as u64)),
+        );
+        emit(
+            "digest word",
+            last * (digest_word(cur, k) + cur[col::DIGEST_CARRY + k] * two_32 - h_in - output),
+        );
+        emit(
+            "next block continues from the digest",
+            last * nxt[col::CONT] * (nxt[col::H_IN + k] - digest_word(cur, k)),
+        );
+    }
+    emit(
+        "hash ends on its final block",
+        last * (nxt[col::CONT] + cur[col::FINAL] - one),
+    );
+    emit(
+        "hash counter",
+        last * (nxt[col::CALL] - cur[col::CALL] - one + nxt[col::CONT]),
+    );
+    emit(
+        "block counter",
+        last * (nxt[col::BLOCK] - nxt[col::CONT] * (cur[col::BLOCK] + one)),
+    );
+
+    // Bus
+    let mut sum = E::ZERO;
+    for (slot, message) in row_messages(cur).iter().enumerate() {
+        let term = cur[col::BUS_TERMS + slot];
+        emit(
+            "bus term",
+            term * bus::fingerprint(challenges, &message.values) - message.multiplicity,
+        );
+        sum += term;
+    }
+    emit(
+        "bus running sum",
+        nxt[col::BUS_SUM] - cur[col::BUS_SUM] - sum,
+    );
+}
+
+/// The message blocks of every `TRAP x27` hash of a run, in order
+fn hashes(trace: &ExecutionTrace) -> Result<Vec<Vec<[u32; 16]>>, &'static str> {
+    // The message reads follow the instruction fetch, unless the fetch is from a program ROM
+    let first = usize::from(trace.rom.is_none());
+    let mut hashes = Vec::new();
+    for step in trace.steps.iter().filter(|step| step.instruction == 0xF027) {
+        let blocks = step.before.gprs[1] as usize;
+        let words = step
+            .memory_accesses
+            .get(first..first + WORDS_PER_BLOCK * blocks)
+            .ok_or("Missing memory access in execution trace")?;
+        let blocks = words
+            .chunks(WORDS_PER_BLOCK)
+            .map(|block| {
+                core::array::from_fn(|i| {
+                    (block[2 * i].old_value as u32) << 16 | block[2 * i + 1].old_value as u32
+                })
+            })
+            .collect();
+        hashes.push(blocks);
+    }
+    Ok(hashes)
+}
+
+/// Position of a block among the blocks of the table
+struct BlockInfo {
+    active: bool,
+    cont: bool,
+    last_of_hash: bool,
+    call: usize,
+    index: usize,
+}
+
+/// Write the 64 rows of a block starting at `start`, from the chaining value `h_in`, and return
+/// its digest. `history` holds the last 16 schedule words of the previous block.
+fn fill_block(
+    columns: &mut [Vec<Felt>],
+    start: usize,
+    info: &BlockInfo,
+    h_in: [u32; 8],
+    block: &[u32; 16],
+    history: &mut [u32; 16],
+) -> [u32; 8] {
+    let mut set =
+        |column: usize, t: usize, value: u64| columns[column][start + t] = Felt::new(value);
+    let w = schedule(block);
+    let mut state = h_in;
+    for t in 0..ROUNDS {
+        for (column, value) in [
+            (col::ACTIVE, info.active as u64),
+            (col::CONT, info.cont as u64),
+            (col::FINAL, info.last_of_hash as u64),
+            (col::CALL, info.call as u64),
+            (col::BLOCK, info.index as u64),
+        ] {
+            set(column, t, value);
+        }
+        for (k, word) in state.iter().enumerate() {
+            for i in 0..32 {
+                set(col::STATE + 32 * k + i, t, (word >> i & 1) as u64);
+            }
+        }
+        for (k, word) in h_in.iter().enumerate() {
+            set(col::H_IN + k, t, *word as u64);
+        }
+
+        // Schedule: the window holds W_(t-16) to W_(t-1), reaching into the previous block
+        let window: [u32; 16] = core::array::from_fn(|k| {
+            if t + k < 16 {
+                history[t + k]
+            } else {
+                w[t + k - 16]
+            }
+        });
+        for (k, word) in window.iter().enumerate() {
+            set(col::WINDOW + k, t, *word as u64);
+        }
+        for i in 0..32 {
+            set(col::W + i, t, (w[t] >> i & 1) as u64);
+            set(col::SIGMA0_BITS + i, t, (window[1] >> i & 1) as u64);
+            set(col::SIGMA1_BITS + i, t, (window[14] >> i & 1) as u64);
+        }
+        let sigma0 = rotate_shift_xor(window[1], SMALL_SIGMA0);
+        let sigma1 = rotate_shift_xor(window[14], SMALL_SIGMA1);
+        set(col::SIGMAS, t, sigma0 as u64);
+        set(col::SIGMAS + 1, t, sigma1 as u64);
+        if t >= 16 {
+            let sum = sigma1 as u64 + window[9] as u64 + sigma0 as u64 + window[0] as u64;
+            for i in 0..2 {
+                set(col::W_CARRY + i, t, sum >> (32 + i) & 1);
+            }
+        }
+
+        // Round
+        let (t1, t2) = round_sums(&state, K[t], w[t]);
+        let new_a = t1 + t2;
+        let new_e = state[3] as u64 + t1;
+        set(col::NEW_A, t, new_a & 0xFFFF_FFFF);
+        set(col::NEW_E, t, new_e & 0xFFFF_FFFF);
+        for i in 0..3 {
+            set(col::NEW_A_CARRY + i, t, new_a >> (32 + i) & 1);
+            set(col::NEW_E_CARRY + i, t, new_e >> (32 + i) & 1);
+        }
+        state = [
+            new_a as u32,
+            state[0],
+            state[1],
+            state[2],
+            new_e as u32,
+            state[4],
+            state[5],
+            state[6],
+        ];
+    }
+
+    let mut digest = [0; 8];
+    for k in 0..8 {
+        let sum = h_in[k] as u64 + state[k] as u64;
+        digest[k] = sum as u32;
+        set(col::DIGEST_HI + k, ROUNDS - 1, (digest[k] >> 16) as u64);
+        set(col::DIGEST_LO + k, ROUNDS - 1, (digest[k] & 0xFFFF) as u64);
+        set(col::DIGEST_CARRY + k, ROUNDS - 1, sum >> 32);
+    }
+    history.copy_from_slice(&w[ROUNDS - 16..]);
+    digest
+}
+
+/// Lay out the `TRAP x27` hashes of a run as a SHA-256 trace table of at least `min_length` rows,
+/// or return `None` if the run hashes nothing
+pub fn build_trace_table(
+    trace: &ExecutionTrace,
+    min_length: usize,
+) -> Result<Option<TraceTable>, &'static str> {
+    let hashes = hashes(trace)?;
+    if hashes.is_empty() {
+        return Ok(None);
+    }
+    let blocks: usize = hashes.iter().map(Vec::len).sum();
+    let length = (ROUNDS * (blocks + 1))
+        .next_power_of_two()
+        .max(min_length.next_power_of_two())
+        .max(MIN_TRACE_LENGTH);
+    if length > super::MAX_TRACE_LENGTH {
+        return Err("Too many SHA-256 blocks");
+    }
+
+    let mut columns = vec![vec![Felt::ZERO; length]; col::WIDTH];
+    let mut history = [0; 16];
+    let mut start = 0;
+    for (call, blocks) in hashes.iter().enumerate() {
+        let mut h_in = INITIAL_STATE;
+        for (index, block) in blocks.iter().enumerate() {
+            let info = BlockInfo {
+                active: true,
+                cont: index > 0,
+                last_of_hash: index == blocks.len() - 1,
+                call,
+                index,
+            };
+            h_in = fill_block(&mut columns, start, &info, h_in, block, &mut history);
+            start += ROUNDS;
+        }
+    }
+    for call in hashes.len().. {
+        if start == length {
+            break;
+        }
+        let info = BlockInfo {
+            active: false,
+            cont: false,
+            last_of_hash: true,
+            call,
+            index: 0,
+        };
+        fill_block(
+            &mut columns,
+            start,
+            &info,
+            INITIAL_STATE,
+            &[0; 16],
+            &mut history,
+        );
+        start += ROUNDS;
+    }
+    Ok(Some(TraceTable { columns }))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::air::lc3::{self, Lc3Air};
+    use crate::air::test_utils::{bus_balance, bus_sum, challenges, public_inputs};
+    use crate::air::{check_constraints, Chip, ConstraintViolation};
+    use crate::io::IoTape;
+    use crate::memory::Memory;
+    use crate::register::{Register, RegisterFile};
+    use crate::utils::{execute_program_with_io, sha256_message_words};
+    use sha2::{Digest, Sha256};
+
+    /// Hash every message with `TRAP x27`, the message `k` at `x4000 + x100 * k` and its digest at
+    /// `x6000 + x10 * k`
+    fn hash_run(messages: &[&[u8]]) -> (Vec<(u16, u16)>, ExecutionTrace) {
+        let mut memory = Memory::new();
+        let mut address = 0x3000;
+        for (k, message) in messages.iter().enumerate() {
+            let words = sha256_message_words(message);
+            let source = 0x4000 + 0x100 * k as u16;
+            for (i, word) in words.iter().enumerate() {
+                memory.write(source + i as u16, *word);
+            }
+            let code = [
+                0b0010_000_000000100, // LD R0, #4
+                0b0010_001_000000100, // LD R1, #4
+                0b0010_010_000000100, // LD R2, #4
+                0xF027,               // TRAP x27
+                0b0000_111_000000011, // BRnzp #3
+                source,
+                (words.len() / WORDS_PER_BLOCK) as u16,
+                0x6000 + 0x10 * k as u16,
+            ];
+            for word in code {
+                memory.write(address, word);
+                address += 1;
+            }
+        }
+        memory.write(address, 0xF025); // HALT
+        let mut registers = RegisterFile::new();
+        registers.write(Register::PC, 0x3000);
+        let image = memory.image();
+        let mut tape = IoTape::new(Vec::new());
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+
+        for (k, message) in messages.iter().enumerate() {
+            let digest: Vec<u16> = (0..DIGEST_WORDS)
+                .map(|i| memory.read(0x6000 + 0x10 * k as u16 + i as u16))
+                .collect();
+            let expected: Vec<u16> = Sha256::digest(message)
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            assert_eq!(digest, expected);
+        }
+        (image, trace)
+    }
+
+    #[test]
+    fn test_compress() {
+        // One block, two blocks, and a block of padding only
+        let messages: [&[u8]; 3] = [b"abc", &[0x5A; 100], &[7; 56]];
+        hash_run(&messages);
+
+        let mut state = INITIAL_STATE;
+        let words = sha256_message_words(b"abc");
+        let block = core::array::from_fn(|i| (words[2 * i] as u32) << 16 | words[2 * i + 1] as u32);
+        compress(&mut state, &block);
+        assert_eq!(state[0], 0xba7816bf);
+        assert_eq!(state[7], 0xf20015ad);
+    }
+
+    #[test]
+    fn test_chip_satisfies_constraints() {
+        let (image, trace) = hash_run(&[&[0x5A; 100], b"abc"]);
+        let table = build_trace_table(&trace, MIN_TRACE_LENGTH)
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.width(), col::WIDTH);
+        // Three blocks and a padding block
+        assert_eq!(table.length(), 4 * ROUNDS);
+        let air = Sha256Air::new(table.length());
+        assert_eq!(
+            air.transition_constraint_names().len(),
+            air.num_transition_constraints()
+        );
+        assert_eq!(check_constraints(&air, &table), Ok(()));
+
+        let lc3_table = lc3::build_trace_table(&trace).unwrap();
+        let lc3_air = Lc3Air::new(lc3_table.length(), public_inputs(&image, &trace));
+        assert_eq!(check_constraints(&lc3_air, &lc3_table), Ok(()));
+
+        // A wrong round output
+        let mut forged = table.clone();
+        forged.columns[col::NEW_E][ROUNDS + 5] += Felt::ONE;
+        match check_constraints(&air, &forged) {
+            Err(ConstraintViolation::Transition { name, row, .. }) => {
+                assert_eq!(name, "new e");
+                assert_eq!(row, ROUNDS + 5);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // A second block that does not continue from the digest of the first
+        let mut forged = table.clone();
+        forged.columns[col::H_IN + 3][ROUNDS] += Felt::ONE;
+        assert!(check_constraints(&air, &forged).is_err());
+
+        // A run without TRAP x27 needs no chip
+        let (_, trace) = hash_run(&[]);
+        assert!(build_trace_table(&trace, MIN_TRACE_LENGTH)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_bus_links_the_tables() {
+        let (image, trace) = hash_run(&[&[0x5A; 100], b"abc"]);
+        let table = build_trace_table(&trace, MIN_TRACE_LENGTH)
+            .unwrap()
+            .unwrap();
+        let air = Sha256Air::new(table.length());
+        let challenges = challenges();
+        let public_inputs = public_inputs(&image, &trace);
+        let sent = bus_balance(&public_inputs, &trace, Some(Chip::Sha256), &challenges);
+        assert_ne!(sent, QuadExt::ZERO);
+        assert_eq!(sent + bus_sum(&air, &table, &challenges), QuadExt::ZERO);
+
+        // A chip table hashing another message satisfies its own constraints, but receives
+        // words the LC3 table did not send
+        let mut other = trace.clone();
+        let step = other
+            .steps
+            .iter_mut()
+            .find(|step| step.instruction == 0xF027)
+            .unwrap();
+        step.memory_accesses[5].old_value ^= 1;
+        let forged = build_trace_table(&other, MIN_TRACE_LENGTH)
+            .unwrap()
+            .unwrap();
+        assert_eq!(check_constraints(&air, &forged), Ok(()));
+        assert_ne!(sent + bus_sum(&air, &forged, &challenges), QuadExt::ZERO);
+    }
+}
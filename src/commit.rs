@@ -0,0 +1,222 @@
+//! Memory Commitment Module
+//!
+//! This module commits to a 65,536-word memory image with a Merkle tree. The program ID hashes the
+//! root of the tree over the initial memory image with the registers the run starts from: it
+//! identifies the loaded program, its data and its entry point, and verifiers reference programs
+//! by it.
+//!
+//! ## Design
+//! - Leaf `i` holds the word at address `i`, encoded as the digest whose first eight bytes are the
+//!   word as a little-endian `u64` and whose other bytes are zero. This is also a valid encoding of
+//!   four field elements, so the same leaves work with every [`Hasher`]. The tree has a fixed depth
+//!   of 16, so a leaf can never be mistaken for an internal node.
+//! - The hash is selectable: [`Sha256Hasher`] (the default, used for program IDs), or
+//!   [`PoseidonHasher`](crate::poseidon::PoseidonHasher) or
+//!   [`Poseidon2Hasher`](crate::poseidon2::Poseidon2Hasher) for commitments that a recursive
+//!   verifier opens.
+//! - Most of a memory image is zero, so the tree is built sparsely.
+//! - A [`MemoryProof`] shows that a word holds a value under a root, and also that writing a new
+//!   value to that word turns the root into a new one.
+
+use crate::memory::{Memory, MEMORY_SIZE};
+use crate::merkle::{hash_bytes, Digest, Hasher, MerkleTree, Sha256Hasher};
+use crate::trace::RegisterState;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// Number of levels between a word and the root
+pub const DEPTH: usize = 16;
+
+/// Leaf digest of a memory word
+pub fn leaf(value: u16) -> Digest {
+    let mut digest = [0; 32];
+    digest[..8].copy_from_slice(&(value as u64).to_le_bytes());
+    digest
+}
+
+/// Merkle commitment to a memory image
+pub struct MemoryCommitment<H = Sha256Hasher> {
+    tree: MerkleTree<H>,
+}
+
+impl<H: Hasher> MemoryCommitment<H> {
+    /// Commit to the contents of `memory`
+    pub fn new(memory: &Memory) -> Self {
+        let leaves: Vec<(usize, Digest)> = memory
+            .image()
+            .into_iter()
+            .map(|(address, value)| (address as usize, leaf(value)))
+            .collect();
+        MemoryCommitment {
+            tree: MerkleTree::sparse(MEMORY_SIZE, leaf(0), &leaves),
+        }
+    }
+
+    pub fn root(&self) -> Digest {
+        self.tree.root()
+    }
+
+    /// Prove the value of the word at `address`
+    pub fn prove(&self, address: u16) -> MemoryProof<H> {
+        MemoryProof {
+            address,
+            path: self.tree.prove(address as usize),
+            hasher: PhantomData,
+        }
+    }
+
+    /// Write `value` to the word at `address`
+    pub fn update(&mut self, address: u16, value: u16) {
+        self.tree.update(address as usize, leaf(value));
+    }
+}
+
+/// Inclusion proof of one memory word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryProof<H = Sha256Hasher> {
+    pub address: u16,
+    /// Sibling digests from the word up to the root
+    pub path: Vec<Digest>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> MemoryProof<H> {
+    pub fn new(address: u16, path: Vec<Digest>) -> Self {
+        MemoryProof {
+            address,
+            path,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Root of the memory in which the word holds `value`
+    pub fn root_for(&self, value: u16) -> Option<Digest> {
+        if self.path.len() != DEPTH {
+            return None;
+        }
+        MerkleTree::<H>::root_from_path(self.address as usize, &leaf(value), &self.path)
+    }
+
+    /// Check that the word holds `value` in the memory committed to by `root`
+    pub fn verify(&self, root: &Digest, value: u16) -> bool {
+        self.root_for(value) == Some(*root)
+    }
+
+    /// Check that the word holds `old_value` under `old_root`, and return the root after writing
+    /// `new_value` to it
+    pub fn verify_update(
+        &self,
+        old_root: &Digest,
+        old_value: u16,
+        new_value: u16,
+    ) -> Option<Digest> {
+        if !self.verify(old_root, old_value) {
+            return None;
+        }
+        self.root_for(new_value)
+    }
+}
+
+/// Root of the SHA-256 commitment to a memory image
+pub fn memory_root(memory: &Memory) -> Digest {
+    MemoryCommitment::<Sha256Hasher>::new(memory).root()
+}
+
+/// Program ID of a run starting from `entry` with the memory committed to by `memory_root`: the
+/// SHA-256 hash of the root, R0-R7, PC and COND as little-endian `u16`s
+pub fn program_id(memory_root: &Digest, entry: &RegisterState) -> Digest {
+    let mut bytes = memory_root.to_vec();
+    for word in entry.gprs.iter().chain([&entry.pc, &entry.cond]) {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    hash_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::poseidon::PoseidonHasher;
+    #[cfg(feature = "std")]
+    use crate::poseidon2::Poseidon2Hasher;
+
+    fn check_commitment<H: Hasher>() {
+        let mut memory = Memory::new();
+        let empty = MemoryCommitment::<H>::new(&memory).root();
+        memory.write(0x3000, 0x1234);
+        memory.write(0xFFFF, 0x0001);
+        let mut commitment = MemoryCommitment::<H>::new(&memory);
+        let root = commitment.root();
+        assert_ne!(root, empty);
+
+        // Inclusion
+        let proof = commitment.prove(0x3000);
+        assert!(proof.verify(&root, 0x1234));
+        assert!(!proof.verify(&root, 0x1235));
+        assert!(commitment.prove(0x3001).verify(&root, 0));
+        assert!(commitment.prove(0xFFFF).verify(&root, 1));
+        let moved = MemoryProof::<H>::new(0x3001, proof.path.clone());
+        assert!(!moved.verify(&root, 0x1234));
+
+        // Update
+        let new_root = proof.verify_update(&root, 0x1234, 0x5678).unwrap();
+        assert!(proof.verify_update(&root, 0x1111, 0x5678).is_none());
+        commitment.update(0x3000, 0x5678);
+        assert_eq!(commitment.root(), new_root);
+        memory.write(0x3000, 0x5678);
+        assert_eq!(MemoryCommitment::<H>::new(&memory).root(), new_root);
+    }
+
+    #[test]
+    fn test_memory_commitment() {
+        check_commitment::<Sha256Hasher>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_poseidon_memory_commitment() {
+        check_commitment::<PoseidonHasher>();
+        check_commitment::<Poseidon2Hasher>();
+
+        let memory = Memory::new();
+        assert_ne!(
+            MemoryCommitment::<Sha256Hasher>::new(&memory).root(),
+            MemoryCommitment::<PoseidonHasher>::new(&memory).root()
+        );
+        assert_ne!(
+            MemoryCommitment::<PoseidonHasher>::new(&memory).root(),
+            MemoryCommitment::<Poseidon2Hasher>::new(&memory).root()
+        );
+    }
+
+    #[test]
+    fn test_program_id() {
+        let mut memory = Memory::new();
+        memory.write(0x3000, 0xF025);
+        let entry = RegisterState {
+            pc: 0x3000,
+            ..Default::default()
+        };
+        let id = program_id(&memory_root(&memory), &entry);
+        assert_eq!(id, program_id(&memory_root(&memory.clone()), &entry));
+        memory.write(0x3001, 0x0001);
+        assert_ne!(id, program_id(&memory_root(&memory), &entry));
+
+        // The entry point and registers are part of the ID
+        memory.write(0x3001, 0);
+        let root = memory_root(&memory);
+        assert_ne!(
+            id,
+            program_id(
+                &root,
+                &RegisterState {
+                    pc: 0x3001,
+                    ..entry
+                }
+            )
+        );
+        let mut gprs = entry.gprs;
+        gprs[0] = 0x41;
+        assert_ne!(id, program_id(&root, &RegisterState { gprs, ..entry }));
+    }
+}
@@ -0,0 +1,200 @@
+//! Continuations Module
+//!
+//! This module makes runs of any length provable by splitting them into segments of bounded length,
+//! which are proven independently and linked by the states at their boundaries.
+//!
+//! ## Design
+//! - [`execute_segments`] runs a program at most `segment_steps` instructions at a time. Every
+//!   segment but the last pauses; the last one ends with `HALT`.
+//! - A [`StateCommitment`] holds the registers (R0-R7, PC, COND) and the Merkle root of memory.
+//!   The proof of a segment binds the commitments to the states the segment starts and ends in.
+//! - [`verify_segments`] checks every segment proof, that the first segment starts from the program
+//!   ID, that every other segment starts from the state the previous one ended in, that only the
+//!   last segment halts, and that all segments fetch from the same program ROM, if any.
+//! - I/O carries over from segment to segment. Each segment has the bytes it read and wrote as
+//!   public inputs, and the input and journal of the run are their concatenations.
+
+use crate::io::Io;
+use crate::memory::Memory;
+use crate::merkle::Digest;
+pub use crate::proof::StateCommitment;
+use crate::proof::{Proof, ProofOptions, PublicInputs};
+use crate::prover::prove;
+use crate::register::RegisterFile;
+use crate::trace::ExecutionTrace;
+use crate::utils::execute_segment;
+use crate::verifier::verify;
+
+/// A bounded part of a run
+pub struct Segment {
+    /// Memory when the segment starts
+    pub initial_memory: Memory,
+    pub trace: ExecutionTrace,
+}
+
+/// A segment proof with the public inputs it proves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentProof {
+    pub public_inputs: PublicInputs,
+    pub proof: Proof,
+}
+
+/// Execute the loaded program until HALT, cutting the run into segments of at most
+/// `segment_steps` instructions
+pub fn execute_segments(
+    memory: &mut Memory,
+    registers: &mut RegisterFile,
+    io: &mut dyn Io,
+    segment_steps: usize,
+) -> Result<Vec<Segment>, &'static str> {
+    if segment_steps == 0 {
+        return Err("Segments must have at least one step");
+    }
+    let mut segments = Vec::new();
+    loop {
+        let initial_memory = memory.clone();
+        let trace = execute_segment(memory, registers, io, segment_steps)?;
+        let halted = trace.halted;
+        segments.push(Segment {
+            initial_memory,
+            trace,
+        });
+        if halted {
+            return Ok(segments);
+        }
+    }
+}
+
+/// Prove every segment of a run
+pub fn prove_segments(
+    segments: &[Segment],
+    options: &ProofOptions,
+) -> Result<Vec<SegmentProof>, &'static str> {
+    segments
+        .iter()
+        .map(|segment| {
+            let (public_inputs, proof) = prove(&segment.initial_memory, &segment.trace, options)?;
+            Ok(SegmentProof {
+                public_inputs,
+                proof,
+            })
+        })
+        .collect()
+}
+
+/// Verify the segment proofs of a run of the program `program_id`, returning the state it halts in
+pub fn verify_segments(
+    program_id: &Digest,
+    segments: &[SegmentProof],
+) -> Result<StateCommitment, &'static str> {
+    let first = segments.first().ok_or("No segments")?;
+    if first.public_inputs.program_id() != *program_id {
+        return Err("First segment does not start from the program");
+    }
+    let mut previous: Option<StateCommitment> = None;
+    for (index, segment) in segments.iter().enumerate() {
+        let public_inputs = &segment.public_inputs;
+        if public_inputs.halted != (index + 1 == segments.len()) {
+            return Err("Only the last segment may halt");
+        }
+        if public_inputs.rom != first.public_inputs.rom {
+            return Err("Segments fetch from different program ROMs");
+        }
+        if let Some(previous) = previous {
+            if public_inputs.initial_commitment() != previous {
+                return Err("Segment does not start where the previous one ended");
+            }
+        }
+        verify(public_inputs, &segment.proof)?;
+        previous = Some(public_inputs.final_commitment());
+    }
+    Ok(previous.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::{memory_root, program_id};
+    use crate::io::IoTape;
+    use crate::register::Register;
+    use crate::trace::RegisterState;
+    use crate::utils::execute_program_with_io;
+
+    /// Counts R0 down from 5, storing every value and printing a character per iteration
+    fn load(memory: &mut Memory, registers: &mut RegisterFile) {
+        let program = [
+            0b0101_000_000_1_00000, // 3000 AND R0, R0, #0
+            0b0001_000_000_1_00101, // 3001 ADD R0, R0, #5
+            0b0011_000_000000110,   // 3002 ST R0, #6 -> 0x3009
+            0b0001_001_000_1_00000, // 3003 ADD R1, R0, #0
+            0b1111_0000_00100001,   // 3004 OUT
+            0b0001_000_001_1_11111, // 3005 ADD R0, R1, #-1
+            0b0000_001_111111011,   // 3006 BRp #-5 -> 0x3002
+            0xF025,                 // 3007 HALT
+        ];
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        registers.write(Register::PC, 0x3000);
+    }
+
+    #[test]
+    fn test_segments() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        load(&mut memory, &mut registers);
+        let id = program_id(&memory_root(&memory), &RegisterState::capture(&registers));
+        let mut tape = IoTape::new(Vec::new());
+        let segments = execute_segments(&mut memory, &mut registers, &mut tape, 8).unwrap();
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|segment| segment.trace.len() <= 8));
+
+        // The segments replay the whole run
+        let mut whole_memory = Memory::new();
+        let mut whole_registers = RegisterFile::new();
+        load(&mut whole_memory, &mut whole_registers);
+        let whole = execute_program_with_io(
+            &mut whole_memory,
+            &mut whole_registers,
+            &mut IoTape::new(Vec::new()),
+        )
+        .unwrap();
+        let steps: Vec<_> = segments
+            .iter()
+            .flat_map(|segment| segment.trace.steps.iter().cloned())
+            .collect();
+        assert_eq!(steps, whole.steps);
+        let journal: Vec<u8> = segments
+            .iter()
+            .flat_map(|segment| segment.trace.output.iter().copied())
+            .collect();
+        assert_eq!(journal, whole.output);
+
+        let options = ProofOptions::new(4, 8, 4, 4);
+        let proofs = prove_segments(&segments, &options).unwrap();
+        let end = verify_segments(&id, &proofs).unwrap();
+        assert_eq!(end.state, whole.final_state().unwrap());
+        assert_eq!(end.memory_root, memory_root(&whole_memory));
+
+        // Segments must be complete, in order and from the right program
+        assert_eq!(
+            verify_segments(&id, &proofs[..3]),
+            Err("Only the last segment may halt")
+        );
+        let mut reordered = proofs.clone();
+        reordered.swap(1, 2);
+        assert_eq!(
+            verify_segments(&id, &reordered),
+            Err("Segment does not start where the previous one ended")
+        );
+        assert_eq!(
+            verify_segments(&id, &proofs[1..]),
+            Err("First segment does not start from the program")
+        );
+
+        // A segment cannot claim to end in another memory
+        let mut forged = proofs.clone();
+        forged[0].public_inputs.final_memory_image[0].1 ^= 1;
+        assert!(verify_segments(&id, &forged).is_err());
+    }
+}
@@ -0,0 +1,740 @@
+//! Finite Field Module
+//!
+//! This module implements arithmetic over the Goldilocks prime field `p = 2^64 - 2^32 + 1`, which
+//! is used by the STARK prover and verifier.
+//!
+//! ## Design
+//! - Elements are stored in canonical form as a `u64` in `[0, p)`.
+//! - The multiplicative group has order `p - 1 = 2^32 * (2^32 - 1)`, so the field contains
+//!   power-of-two roots of unity up to `2^32`, which is what the NTT relies on.
+//! - [`FieldElement`] abstracts over the field so that the same polynomial and constraint code can
+//!   be reused for challenges drawn from larger fields.
+//! - [`QuadExt`] is the quadratic extension `F[u] / (u^2 - 7)`, about 128 bits, for challenges
+//!   whose soundness must not be limited by the 64-bit base field.
+
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// The Goldilocks prime `2^64 - 2^32 + 1`
+pub const MODULUS: u64 = 0xFFFF_FFFF_0000_0001;
+
+/// `2^64 mod p`
+const EPSILON: u64 = 0xFFFF_FFFF;
+
+/// Largest `k` such that `2^k` divides `p - 1`
+pub const TWO_ADICITY: u32 = 32;
+
+/// Non-residue defining the quadratic extension: `u^2 = 7`
+const EXTENSION_NON_RESIDUE: Felt = Felt(7);
+
+/// Common interface of the base field and its extensions
+pub trait FieldElement:
+    Copy
+    + Clone
+    + fmt::Debug
+    + Default
+    + PartialEq
+    + Eq
+    + Send
+    + Sync
+    + From<Felt>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Number of base field coordinates of an element
+    const EXTENSION_DEGREE: usize;
+
+    /// The element with the given coordinates over the base field, of which there are
+    /// [`EXTENSION_DEGREE`](Self::EXTENSION_DEGREE)
+    fn from_base_elements(elements: &[Felt]) -> Self;
+
+    /// Multiplicative inverse; the inverse of zero is defined as zero
+    fn inverse(&self) -> Self;
+
+    /// Multiply by a base field element
+    fn mul_base(self, rhs: Felt) -> Self;
+
+    /// Append the canonical little-endian encoding of this element
+    fn write_bytes(&self, out: &mut Vec<u8>);
+
+    /// Append the coordinates of this element over the base field
+    fn write_base_elements(&self, out: &mut Vec<Felt>);
+
+    fn square(self) -> Self {
+        self * self
+    }
+
+    fn pow(self, mut exp: u64) -> Self {
+        let mut base = self;
+        let mut acc = Self::ONE;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                acc *= base;
+            }
+            base = base.square();
+            exp >>= 1;
+        }
+        acc
+    }
+}
+
+/// An element of the Goldilocks field
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Felt(u64);
+
+impl Felt {
+    pub const ZERO: Felt = Felt(0);
+    pub const ONE: Felt = Felt(1);
+
+    /// Generator of the multiplicative group
+    pub const GENERATOR: Felt = Felt(7);
+
+    /// Create an element from any `u64`, reducing it modulo `p`
+    pub const fn new(value: u64) -> Self {
+        if value >= MODULUS {
+            Felt(value - MODULUS)
+        } else {
+            Felt(value)
+        }
+    }
+
+    /// Canonical representative in `[0, p)`
+    pub const fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Primitive `2^log_n`-th root of unity
+    pub fn two_adic_root(log_n: u32) -> Felt {
+        assert!(
+            log_n <= TWO_ADICITY,
+            "no root of unity of order 2^{}",
+            log_n
+        );
+        // 7^((p - 1) / 2^32) generates the subgroup of order 2^32
+        let root = Felt::GENERATOR.pow((MODULUS - 1) >> TWO_ADICITY);
+        root.pow(1 << (TWO_ADICITY - log_n))
+    }
+
+    fn reduce128(x: u128) -> u64 {
+        let lo = x as u64;
+        let hi = (x >> 64) as u64;
+        let hi_hi = hi >> 32;
+        let hi_lo = hi & EPSILON;
+
+        // 2^96 = -1 (mod p)
+        let (mut t0, borrow) = lo.overflowing_sub(hi_hi);
+        if borrow {
+            t0 = t0.wrapping_sub(EPSILON);
+        }
+        // 2^64 = 2^32 - 1 (mod p)
+        let t1 = hi_lo * EPSILON;
+        let (mut res, carry) = t0.overflowing_add(t1);
+        if carry {
+            res = res.wrapping_add(EPSILON);
+        }
+        if res >= MODULUS {
+            res -= MODULUS;
+        }
+        res
+    }
+}
+
+impl FieldElement for Felt {
+    const ZERO: Self = Felt::ZERO;
+    const ONE: Self = Felt::ONE;
+    const EXTENSION_DEGREE: usize = 1;
+
+    fn from_base_elements(elements: &[Felt]) -> Self {
+        elements[0]
+    }
+
+    fn inverse(&self) -> Self {
+        // Fermat's little theorem
+        self.pow(MODULUS - 2)
+    }
+
+    fn mul_base(self, rhs: Felt) -> Self {
+        self * rhs
+    }
+
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0.to_le_bytes());
+    }
+
+    fn write_base_elements(&self, out: &mut Vec<Felt>) {
+        out.push(*self);
+    }
+}
+
+impl From<u64> for Felt {
+    fn from(value: u64) -> Self {
+        Felt::new(value)
+    }
+}
+
+impl From<u16> for Felt {
+    fn from(value: u16) -> Self {
+        Felt(value as u64)
+    }
+}
+
+impl From<bool> for Felt {
+    fn from(value: bool) -> Self {
+        Felt(value as u64)
+    }
+}
+
+impl fmt::Debug for Felt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Felt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for Felt {
+    type Output = Felt;
+
+    fn add(self, rhs: Felt) -> Felt {
+        let (sum, carry) = self.0.overflowing_add(rhs.0);
+        let mut sum = if carry { sum + EPSILON } else { sum };
+        if sum >= MODULUS {
+            sum -= MODULUS;
+        }
+        Felt(sum)
+    }
+}
+
+impl Sub for Felt {
+    type Output = Felt;
+
+    fn sub(self, rhs: Felt) -> Felt {
+        if self.0 >= rhs.0 {
+            Felt(self.0 - rhs.0)
+        } else {
+            Felt(self.0.wrapping_sub(rhs.0).wrapping_add(MODULUS))
+        }
+    }
+}
+
+impl Mul for Felt {
+    type Output = Felt;
+
+    fn mul(self, rhs: Felt) -> Felt {
+        Felt(Felt::reduce128(self.0 as u128 * rhs.0 as u128))
+    }
+}
+
+impl Div for Felt {
+    type Output = Felt;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Felt) -> Felt {
+        self * rhs.inverse()
+    }
+}
+
+impl Neg for Felt {
+    type Output = Felt;
+
+    fn neg(self) -> Felt {
+        Felt::ZERO - self
+    }
+}
+
+impl AddAssign for Felt {
+    fn add_assign(&mut self, rhs: Felt) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Felt {
+    fn sub_assign(&mut self, rhs: Felt) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Felt {
+    fn mul_assign(&mut self, rhs: Felt) {
+        *self = *self * rhs;
+    }
+}
+
+/// An element `a + b * u` of the quadratic extension `F[u] / (u^2 - 7)`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct QuadExt(pub Felt, pub Felt);
+
+impl QuadExt {
+    pub const ZERO: QuadExt = QuadExt(Felt::ZERO, Felt::ZERO);
+    pub const ONE: QuadExt = QuadExt(Felt::ONE, Felt::ZERO);
+
+    pub const fn new(a: Felt, b: Felt) -> Self {
+        QuadExt(a, b)
+    }
+
+    /// The conjugate `a - b * u`
+    pub fn conjugate(&self) -> Self {
+        QuadExt(self.0, -self.1)
+    }
+
+    /// The norm `a^2 - 7 * b^2`, a base field element
+    pub fn norm(&self) -> Felt {
+        self.0 * self.0 - EXTENSION_NON_RESIDUE * self.1 * self.1
+    }
+}
+
+impl FieldElement for QuadExt {
+    const ZERO: Self = QuadExt::ZERO;
+    const ONE: Self = QuadExt::ONE;
+    const EXTENSION_DEGREE: usize = 2;
+
+    fn from_base_elements(elements: &[Felt]) -> Self {
+        QuadExt(elements[0], elements[1])
+    }
+
+    fn inverse(&self) -> Self {
+        // (a + bu)^-1 = (a - bu) / (a^2 - 7b^2)
+        let norm_inv = self.norm().inverse();
+        let conjugate = self.conjugate();
+        QuadExt(conjugate.0 * norm_inv, conjugate.1 * norm_inv)
+    }
+
+    fn mul_base(self, rhs: Felt) -> Self {
+        QuadExt(self.0 * rhs, self.1 * rhs)
+    }
+
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        self.0.write_bytes(out);
+        self.1.write_bytes(out);
+    }
+
+    fn write_base_elements(&self, out: &mut Vec<Felt>) {
+        out.push(self.0);
+        out.push(self.1);
+    }
+}
+
+impl From<Felt> for QuadExt {
+    fn from(value: Felt) -> Self {
+        QuadExt(value, Felt::ZERO)
+    }
+}
+
+impl fmt::Debug for QuadExt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} + {}u", self.0, self.1)
+    }
+}
+
+impl fmt::Display for QuadExt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} + {}u", self.0, self.1)
+    }
+}
+
+impl Add for QuadExt {
+    type Output = QuadExt;
+
+    fn add(self, rhs: QuadExt) -> QuadExt {
+        QuadExt(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl Sub for QuadExt {
+    type Output = QuadExt;
+
+    fn sub(self, rhs: QuadExt) -> QuadExt {
+        QuadExt(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl Mul for QuadExt {
+    type Output = QuadExt;
+
+    fn mul(self, rhs: QuadExt) -> QuadExt {
+        // Karatsuba: (a0 + a1 u)(b0 + b1 u)
+        //   = a0 b0 + 7 a1 b1 + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) u
+        let a0b0 = self.0 * rhs.0;
+        let a1b1 = self.1 * rhs.1;
+        let cross = (self.0 + self.1) * (rhs.0 + rhs.1) - a0b0 - a1b1;
+        QuadExt(a0b0 + EXTENSION_NON_RESIDUE * a1b1, cross)
+    }
+}
+
+impl Div for QuadExt {
+    type Output = QuadExt;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: QuadExt) -> QuadExt {
+        self * rhs.inverse()
+    }
+}
+
+impl Neg for QuadExt {
+    type Output = QuadExt;
+
+    fn neg(self) -> QuadExt {
+        QuadExt(-self.0, -self.1)
+    }
+}
+
+impl AddAssign for QuadExt {
+    fn add_assign(&mut self, rhs: QuadExt) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for QuadExt {
+    fn sub_assign(&mut self, rhs: QuadExt) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for QuadExt {
+    fn mul_assign(&mut self, rhs: QuadExt) {
+        *self = *self * rhs;
+    }
+}
+
+/// Invert every element with a single field inversion (Montgomery's trick); zeros map to zero
+pub fn batch_inverse<E: FieldElement>(values: &[E]) -> Vec<E> {
+    // prefix[i] is the product of the non-zero values before index i
+    let mut prefix = Vec::with_capacity(values.len());
+    let mut acc = E::ONE;
+    for value in values {
+        prefix.push(acc);
+        if *value != E::ZERO {
+            acc *= *value;
+        }
+    }
+
+    let mut acc_inv = acc.inverse();
+    let mut result = vec![E::ZERO; values.len()];
+    for i in (0..values.len()).rev() {
+        if values[i] != E::ZERO {
+            result[i] = prefix[i] * acc_inv;
+            acc_inv *= values[i];
+        }
+    }
+    result
+}
+
+/// In-place forward NTT: turns coefficients into evaluations over the subgroup of size
+/// `values.len()`
+pub fn ntt<E: FieldElement>(values: &mut [E]) {
+    let n = values.len();
+    assert!(n.is_power_of_two(), "NTT size must be a power of two");
+    bit_reverse(values);
+
+    let mut len = 2;
+    while len <= n {
+        let root = Felt::two_adic_root(len.trailing_zeros());
+        let half = len / 2;
+        let twiddles: Vec<Felt> = core::iter::successors(Some(Felt::ONE), |w| Some(*w * root))
+            .take(half)
+            .collect();
+        for chunk in values.chunks_mut(len) {
+            let (lo, hi) = chunk.split_at_mut(half);
+            for ((a, b), w) in lo.iter_mut().zip(hi.iter_mut()).zip(&twiddles) {
+                let t = b.mul_base(*w);
+                *b = *a - t;
+                *a += t;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// In-place inverse NTT: turns evaluations over the subgroup of size `values.len()` into
+/// coefficients
+pub fn intt<E: FieldElement>(values: &mut [E]) {
+    let n = values.len();
+    ntt(values);
+    // Evaluating at w^-i is the same as evaluating at w^(n-i)
+    values[1..].reverse();
+    let n_inv = Felt::new(n as u64).inverse();
+    for value in values.iter_mut() {
+        *value = value.mul_base(n_inv);
+    }
+}
+
+/// Evaluate a polynomial over the coset `offset * <w>` of size `domain_size`
+pub fn evaluate_on_coset<E: FieldElement>(
+    coefficients: &[E],
+    offset: Felt,
+    domain_size: usize,
+) -> Vec<E> {
+    assert!(coefficients.len() <= domain_size);
+    let mut values = vec![E::ZERO; domain_size];
+    let mut shift = Felt::ONE;
+    for (value, coefficient) in values.iter_mut().zip(coefficients) {
+        *value = coefficient.mul_base(shift);
+        shift *= offset;
+    }
+    ntt(&mut values);
+    values
+}
+
+/// Interpolate evaluations over the coset `offset * <w>` back into coefficients
+pub fn interpolate_coset<E: FieldElement>(evaluations: &[E], offset: Felt) -> Vec<E> {
+    let mut coefficients = evaluations.to_vec();
+    intt(&mut coefficients);
+    let offset_inv = offset.inverse();
+    let mut shift = Felt::ONE;
+    for coefficient in coefficients.iter_mut() {
+        *coefficient = coefficient.mul_base(shift);
+        shift *= offset_inv;
+    }
+    coefficients
+}
+
+/// Interpolate the polynomial of degree `< points.len()` through the given `(x, y)` points.
+///
+/// This is Lagrange interpolation in `O(n^2)`; it is meant for a handful of points at arbitrary
+/// positions. Evaluations over a coset are interpolated with [`interpolate_coset`] instead.
+pub fn interpolate<E: FieldElement>(points: &[(E, E)]) -> Vec<E> {
+    let n = points.len();
+    let mut result = vec![E::ZERO; n];
+    for (i, &(x_i, y_i)) in points.iter().enumerate() {
+        // Numerator prod_{j != i} (X - x_j), built up coefficient by coefficient
+        let mut basis = vec![E::ZERO; n];
+        basis[0] = E::ONE;
+        let mut degree = 0;
+        let mut denominator = E::ONE;
+        for (j, &(x_j, _)) in points.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            degree += 1;
+            for k in (1..=degree).rev() {
+                basis[k] = basis[k - 1] - basis[k] * x_j;
+            }
+            basis[0] = -(basis[0] * x_j);
+            denominator *= x_i - x_j;
+        }
+        let scale = y_i / denominator;
+        for (coefficient, b) in result.iter_mut().zip(&basis) {
+            *coefficient += *b * scale;
+        }
+    }
+    result
+}
+
+/// Evaluate a polynomial given by its coefficients at a point using Horner's rule. The point may
+/// lie in an extension of the field of the coefficients.
+pub fn evaluate_polynomial<F: FieldElement, E: FieldElement + From<F>>(
+    coefficients: &[F],
+    x: E,
+) -> E {
+    coefficients
+        .iter()
+        .rev()
+        .fold(E::ZERO, |acc, coefficient| acc * x + E::from(*coefficient))
+}
+
+fn bit_reverse<E>(values: &mut [E]) {
+    let n = values.len();
+    let shift = usize::BITS - n.trailing_zeros();
+    if n <= 1 {
+        return;
+    }
+    for i in 0..n {
+        let j = i.reverse_bits() >> shift;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift generator for the property tests
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn felt(&mut self) -> Felt {
+            Felt::new(self.next())
+        }
+
+        fn ext(&mut self) -> QuadExt {
+            QuadExt(self.felt(), self.felt())
+        }
+    }
+
+    fn check_field_axioms<E: FieldElement>(mut sample: impl FnMut() -> E) {
+        for _ in 0..200 {
+            let (a, b, c) = (sample(), sample(), sample());
+            assert_eq!(a + b, b + a);
+            assert_eq!(a * b, b * a);
+            assert_eq!((a + b) + c, a + (b + c));
+            assert_eq!((a * b) * c, a * (b * c));
+            assert_eq!(a * (b + c), a * b + a * c);
+            assert_eq!((a + b) - b, a);
+            assert_eq!(a + (-a), E::ZERO);
+            assert_eq!(a * E::ONE, a);
+            if a != E::ZERO {
+                assert_eq!(a * a.inverse(), E::ONE);
+                assert_eq!(b / a * a, b);
+            }
+            assert_eq!(a.pow(3), a * a * a);
+        }
+    }
+
+    #[test]
+    fn test_field_properties() {
+        let mut rng = Rng(0x1234_5678_9ABC_DEF1);
+        check_field_axioms(|| rng.felt());
+        check_field_axioms(|| rng.ext());
+
+        // Values near the modulus exercise the reductions
+        let mut edge = [0, 1, 2, EPSILON, MODULUS - 2, MODULUS - 1].iter().cycle();
+        check_field_axioms(|| Felt::new(*edge.next().unwrap()));
+    }
+
+    #[test]
+    fn test_extension_field() {
+        // 7 is a quadratic non-residue, so u^2 = 7 defines a field
+        assert_eq!(Felt::new(7).pow((MODULUS - 1) / 2), -Felt::ONE);
+        let u = QuadExt(Felt::ZERO, Felt::ONE);
+        assert_eq!(u * u, QuadExt::from(Felt::new(7)));
+
+        // The base field embeds into the extension
+        let mut rng = Rng(42);
+        for _ in 0..100 {
+            let (a, b) = (rng.felt(), rng.felt());
+            assert_eq!(QuadExt::from(a) * QuadExt::from(b), QuadExt::from(a * b));
+            let x = rng.ext();
+            assert_eq!(x.mul_base(a), x * QuadExt::from(a));
+            assert_eq!(QuadExt::from(x.norm()), x * x.conjugate());
+        }
+    }
+
+    #[test]
+    fn test_batch_inverse() {
+        let mut rng = Rng(7);
+        let mut values: Vec<Felt> = (0..33).map(|_| rng.felt()).collect();
+        values[0] = Felt::ZERO;
+        values[17] = Felt::ZERO;
+        let inverses = batch_inverse(&values);
+        for (value, inverse) in values.iter().zip(&inverses) {
+            assert_eq!(*inverse, value.inverse());
+        }
+
+        let values: Vec<QuadExt> = (0..10).map(|_| rng.ext()).collect();
+        for (value, inverse) in values.iter().zip(batch_inverse(&values)) {
+            assert_eq!(*value * inverse, QuadExt::ONE);
+        }
+        assert!(batch_inverse::<Felt>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_polynomial_properties() {
+        let mut rng = Rng(99);
+        for log_n in 0..8 {
+            let n = 1 << log_n;
+            let coefficients: Vec<QuadExt> = (0..n).map(|_| rng.ext()).collect();
+
+            // NTT and inverse NTT are inverse to each other, also over the extension
+            let mut values = coefficients.clone();
+            ntt(&mut values);
+            let root = Felt::two_adic_root(log_n);
+            let i = (rng.next() % n as u64) as usize;
+            assert_eq!(
+                values[i],
+                evaluate_polynomial(&coefficients, QuadExt::from(root.pow(i as u64)))
+            );
+            intt(&mut values);
+            assert_eq!(values, coefficients);
+
+            // Coset evaluation and interpolation round-trip with any blowup
+            let offset = rng.felt();
+            let lde = evaluate_on_coset(&coefficients, offset, 4 * n);
+            let recovered = interpolate_coset(&lde, offset);
+            assert_eq!(&recovered[..n], &coefficients[..]);
+            assert!(recovered[n..].iter().all(|c| *c == QuadExt::ZERO));
+
+            // Lagrange interpolation through n arbitrary points recovers the polynomial
+            let points: Vec<(QuadExt, QuadExt)> = (0..n)
+                .map(|_| {
+                    let x = rng.ext();
+                    (x, evaluate_polynomial(&coefficients, x))
+                })
+                .collect();
+            assert_eq!(interpolate(&points), coefficients);
+        }
+    }
+
+    #[test]
+    fn test_field_arithmetic() {
+        let a = Felt::new(MODULUS - 1);
+        assert_eq!(a + Felt::ONE, Felt::ZERO);
+        assert_eq!(Felt::ZERO - Felt::ONE, a);
+        assert_eq!(a * a, Felt::ONE);
+        assert_eq!(Felt::new(3) * Felt::new(3).inverse(), Felt::ONE);
+        assert_eq!(Felt::new(u64::MAX), Felt::new(EPSILON - 1));
+
+        let root = Felt::two_adic_root(TWO_ADICITY);
+        assert_eq!(root.pow(1 << 31), -Felt::ONE);
+        assert_eq!(root.pow(1 << 32), Felt::ONE);
+    }
+
+    #[test]
+    fn test_ntt_roundtrip() {
+        let coefficients: Vec<Felt> = (1..=16).map(Felt::new).collect();
+        let mut values = coefficients.clone();
+        ntt(&mut values);
+
+        let root = Felt::two_adic_root(4);
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(
+                *value,
+                evaluate_polynomial(&coefficients, root.pow(i as u64))
+            );
+        }
+
+        intt(&mut values);
+        assert_eq!(values, coefficients);
+
+        let offset = Felt::GENERATOR;
+        let lde = evaluate_on_coset(&coefficients, offset, 64);
+        let lde_root = Felt::two_adic_root(6);
+        assert_eq!(
+            lde[5],
+            evaluate_polynomial(&coefficients, offset * lde_root.pow(5))
+        );
+        let recovered = interpolate_coset(&lde, offset);
+        assert_eq!(&recovered[..16], &coefficients[..]);
+        assert!(recovered[16..].iter().all(|c| *c == Felt::ZERO));
+
+        // Base field coefficients evaluate at an extension point as their embedding does
+        let x = QuadExt(Felt::new(5), Felt::new(9));
+        let embedded: Vec<QuadExt> = coefficients.iter().map(|c| QuadExt::from(*c)).collect();
+        assert_eq!(
+            evaluate_polynomial(&coefficients, x),
+            evaluate_polynomial(&embedded, x)
+        );
+    }
+}
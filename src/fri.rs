@@ -0,0 +1,506 @@
+//! FRI Module
+//!
+//! This module implements the FRI (Fast Reed-Solomon Interactive Oracle Proof of Proximity)
+//! low-degree test used by the STARK prover to show that the DEEP composition polynomial has low
+//! degree. It only depends on the field, Merkle tree and transcript modules, so it can be used on
+//! its own.
+//!
+//! ## Design
+//! - The input is the evaluation of a polynomial of degree `< max_degree` over a coset
+//!   `offset * <w>` of size `N = blowup_factor * max_degree`.
+//! - Each round folds the evaluations by the folding factor `k` with a random challenge `beta`.
+//!   Writing `f(x) = sum_i x^i * f_i(x^k)`, the folded polynomial is
+//!   `f'(y) = sum_i beta^i * f_i(y)`, of `k` times lower degree over a domain of `k` times fewer
+//!   points.
+//! - The points `x_j * w^(t * N / k)` for `t < k` share the same `x^k`, so the folded value at
+//!   index `j` only depends on the values at indices `j + t * N / k`. Each layer is committed in a
+//!   Merkle tree whose leaf `j` holds these `k` values.
+//! - Folding stops once the degree bound drops to `max_remainder_degree`, or below the folding
+//!   factor; the remainder polynomial is sent in the clear.
+//! - Before the query positions are drawn, the prover grinds a proof-of-work nonce, which makes
+//!   each attempt at finding favourable positions `2^grinding_bits` times more expensive.
+
+use crate::field::{evaluate_polynomial, interpolate_coset, intt, Felt, FieldElement};
+use crate::merkle::{hash_row, Digest, MerkleTree, Sha256Hasher};
+use crate::parallel;
+use crate::transcript::{Sponge, Transcript};
+use alloc::{vec, vec::Vec};
+
+/// Bits of the base field, of which the field of the folding challenges has
+/// [`EXTENSION_DEGREE`](FieldElement::EXTENSION_DEGREE) coordinates
+const FIELD_BITS: u32 = 64;
+
+/// Bits of collision resistance of the Merkle tree hash
+const HASH_BITS: u32 = 128;
+
+/// Largest number of query positions; each query adds at least one bit of security, so more
+/// would only make proofs larger
+pub const MAX_QUERIES: usize = 256;
+
+/// Largest folding factor
+pub const MAX_FOLDING_FACTOR: usize = 16;
+
+/// Parameters of the FRI protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FriOptions {
+    /// Ratio between the evaluation domain size and the degree bound
+    pub blowup_factor: usize,
+    /// Factor by which each round reduces the degree bound, a power of two
+    pub folding_factor: usize,
+    /// Number of query positions checked by the verifier
+    pub num_queries: usize,
+    /// Number of leading zero bits required of the proof-of-work
+    pub grinding_bits: u32,
+    /// Folding stops once the degree bound is at most this value
+    pub max_remainder_degree: usize,
+}
+
+impl FriOptions {
+    /// Check that the parameters describe a valid protocol instance
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if !self.blowup_factor.is_power_of_two() || self.blowup_factor < 2 {
+            return Err("Blowup factor must be a power of two of at least 2");
+        }
+        if !self.folding_factor.is_power_of_two() || self.folding_factor < 2 {
+            return Err("Folding factor must be a power of two of at least 2");
+        }
+        if self.folding_factor > MAX_FOLDING_FACTOR {
+            return Err("Folding factor too large");
+        }
+        if self.num_queries == 0 {
+            return Err("FRI needs at least one query");
+        }
+        if self.num_queries > MAX_QUERIES {
+            return Err("Too many FRI queries");
+        }
+        if self.grinding_bits > 32 {
+            return Err("Grinding bits must be at most 32");
+        }
+        Ok(())
+    }
+
+    /// Conjectured security level in bits of FRI over a domain of `domain_size` points, folding
+    /// values of `E` with challenges drawn from `E`.
+    ///
+    /// Each query contributes `log2(blowup_factor)` bits under the usual proximity-gap conjecture,
+    /// and grinding adds its bits on top. The result is capped by the probability that a random
+    /// challenge of `E` lands in the domain, and by the collision resistance of the hash.
+    pub fn conjectured_security<E: FieldElement>(&self, domain_size: usize) -> u32 {
+        let query_security =
+            self.num_queries as u32 * self.blowup_factor.trailing_zeros() + self.grinding_bits;
+        let challenge_bits = FIELD_BITS * E::EXTENSION_DEGREE as u32;
+        let field_security = challenge_bits.saturating_sub(domain_size.trailing_zeros());
+        query_security.min(field_security).min(HASH_BITS)
+    }
+
+    /// Degree bounds of the committed layers and of the remainder, for a first layer of
+    /// `domain_size` points
+    fn degree_bounds(&self, domain_size: usize) -> (Vec<usize>, usize) {
+        let mut degree_bound = domain_size / self.blowup_factor;
+        let mut layers = Vec::new();
+        // Folding a degree bound below the folding factor would lose the bound, so such a layer
+        // becomes the remainder
+        while degree_bound > self.max_remainder_degree && degree_bound >= self.folding_factor {
+            layers.push(degree_bound);
+            degree_bound /= self.folding_factor;
+        }
+        (layers, degree_bound)
+    }
+}
+
+/// Opening of one FRI layer at one query position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriLayerOpening<E> {
+    /// The `folding_factor` values folded together with the queried one
+    pub values: Vec<E>,
+    pub path: Vec<Digest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriProof<E> {
+    /// Merkle roots of the committed layers
+    pub layer_roots: Vec<Digest>,
+    /// Coefficients of the final, uncommitted layer
+    pub remainder: Vec<E>,
+    /// Proof-of-work nonce ground after the commit phase
+    pub pow_nonce: u64,
+    /// `queries[i][k]` opens layer `k` for the `i`-th query position
+    pub queries: Vec<Vec<FriLayerOpening<E>>>,
+}
+
+struct FriLayer<E> {
+    evaluations: Vec<E>,
+    tree: MerkleTree,
+}
+
+/// Prover side of FRI, holding the committed layers until the query positions are known
+pub struct FriProver<E> {
+    folding_factor: usize,
+    layers: Vec<FriLayer<E>>,
+    remainder: Vec<E>,
+    pow_nonce: u64,
+}
+
+/// Values at indices `index + t * stride` for `t < folding_factor`
+fn coset_values<E: FieldElement>(
+    evaluations: &[E],
+    index: usize,
+    stride: usize,
+    folding_factor: usize,
+) -> Vec<E> {
+    (0..folding_factor)
+        .map(|t| evaluations[index + t * stride])
+        .collect()
+}
+
+/// Fold the values `f(x * w_k^t)` at the `k`-th roots of unity `w_k^t` into `f'(x^k)`
+fn fold<E: FieldElement>(values: &[E], beta: E, x_inv: Felt) -> E {
+    // intt(values)[i] = x^i * f_i(x^k), so f'(x^k) = sum_i (beta / x)^i * intt(values)[i]
+    let mut coefficients = values.to_vec();
+    intt(&mut coefficients);
+    evaluate_polynomial(&coefficients, beta.mul_base(x_inv))
+}
+
+impl<E: FieldElement> FriProver<E> {
+    /// Commit to `evaluations` over `offset * <w>` and fold them down to the remainder.
+    ///
+    /// Layer roots and the remainder are absorbed into the transcript, and the folding challenges
+    /// are drawn from it. The proof-of-work nonce is ground last, so the caller can draw the query
+    /// positions right after.
+    pub fn commit<S: Sponge>(
+        evaluations: Vec<E>,
+        offset: Felt,
+        options: &FriOptions,
+        transcript: &mut Transcript<S>,
+    ) -> Self {
+        let k = options.folding_factor;
+        let (layer_bounds, degree_bound) = options.degree_bounds(evaluations.len());
+        let mut layers = Vec::with_capacity(layer_bounds.len());
+        let mut evaluations = evaluations;
+        let mut offset = offset;
+
+        for _ in layer_bounds {
+            let n = evaluations.len();
+            let stride = n / k;
+            let leaves = parallel::map_indices(stride, |j| {
+                hash_row(&coset_values(&evaluations, j, stride, k))
+            });
+            let tree = MerkleTree::new(leaves);
+            transcript.absorb_digest("fri layer root", &tree.root());
+            let beta = transcript.challenge("fri folding challenge");
+
+            let root_inv = Felt::two_adic_root(n.trailing_zeros()).inverse();
+            let offset_inv = offset.inverse();
+            let mut folded = vec![E::ZERO; stride];
+            parallel::for_each_chunk(&mut folded, |start, chunk| {
+                let mut x_inv = offset_inv * root_inv.pow(start as u64);
+                for (j, value) in (start..).zip(chunk) {
+                    *value = fold(&coset_values(&evaluations, j, stride, k), beta, x_inv);
+                    x_inv *= root_inv;
+                }
+            });
+
+            layers.push(FriLayer { evaluations, tree });
+            evaluations = folded;
+            offset = offset.pow(k as u64);
+        }
+
+        let mut remainder = interpolate_coset(&evaluations, offset);
+        remainder.truncate(degree_bound);
+        transcript.absorb_elements("fri remainder", &remainder);
+        let pow_nonce = transcript.grind("fri proof of work", options.grinding_bits);
+
+        FriProver {
+            folding_factor: k,
+            layers,
+            remainder,
+            pow_nonce,
+        }
+    }
+
+    /// Open every layer at the given positions of the first layer's domain
+    pub fn prove(&self, positions: &[usize]) -> FriProof<E> {
+        let k = self.folding_factor;
+        let queries = positions
+            .iter()
+            .map(|&position| {
+                let mut position = position;
+                self.layers
+                    .iter()
+                    .map(|layer| {
+                        let stride = layer.evaluations.len() / k;
+                        let index = position % stride;
+                        position = index;
+                        FriLayerOpening {
+                            values: coset_values(&layer.evaluations, index, stride, k),
+                            path: layer.tree.prove(index),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        FriProof {
+            layer_roots: self.layers.iter().map(|layer| layer.tree.root()).collect(),
+            remainder: self.remainder.clone(),
+            pow_nonce: self.pow_nonce,
+            queries,
+        }
+    }
+}
+
+/// Verifier side of FRI, holding the folding challenges re-derived from the transcript
+pub struct FriVerifier<E> {
+    betas: Vec<E>,
+    folding_factor: usize,
+    domain_size: usize,
+    offset: Felt,
+}
+
+impl<E: FieldElement> FriVerifier<E> {
+    /// Replay the commit phase of `proof` on the transcript and check its proof-of-work.
+    ///
+    /// `domain_size` and `offset` describe the domain of the first layer.
+    pub fn new<S: Sponge>(
+        proof: &FriProof<E>,
+        domain_size: usize,
+        offset: Felt,
+        options: &FriOptions,
+        transcript: &mut Transcript<S>,
+    ) -> Result<Self, &'static str> {
+        options.validate()?;
+        let (layer_bounds, degree_bound) = options.degree_bounds(domain_size);
+        if proof.layer_roots.len() != layer_bounds.len() {
+            return Err("Unexpected number of FRI layers");
+        }
+        if proof.remainder.len() > degree_bound {
+            return Err("FRI remainder degree too large");
+        }
+
+        let betas = proof
+            .layer_roots
+            .iter()
+            .map(|root| {
+                transcript.absorb_digest("fri layer root", root);
+                transcript.challenge("fri folding challenge")
+            })
+            .collect();
+        transcript.absorb_elements("fri remainder", &proof.remainder);
+        if !transcript.check_grinding("fri proof of work", options.grinding_bits, proof.pow_nonce) {
+            return Err("Invalid FRI proof-of-work");
+        }
+
+        Ok(FriVerifier {
+            betas,
+            folding_factor: options.folding_factor,
+            domain_size,
+            offset,
+        })
+    }
+
+    /// Check the query openings, given the first layer's values at `positions`
+    pub fn verify(
+        &self,
+        proof: &FriProof<E>,
+        positions: &[usize],
+        values: &[E],
+    ) -> Result<(), &'static str> {
+        if proof.queries.len() != positions.len() || values.len() != positions.len() {
+            return Err("Unexpected number of FRI queries");
+        }
+        let k = self.folding_factor;
+
+        for ((&position, &value), openings) in positions.iter().zip(values).zip(&proof.queries) {
+            if openings.len() != self.betas.len() {
+                return Err("Unexpected number of FRI layer openings");
+            }
+
+            let mut position = position;
+            let mut value = value;
+            let mut domain_size = self.domain_size;
+            let mut offset = self.offset;
+
+            for ((opening, root), beta) in openings.iter().zip(&proof.layer_roots).zip(&self.betas)
+            {
+                let stride = domain_size / k;
+                let index = position % stride;
+                if opening.values.len() != k {
+                    return Err("Malformed FRI layer opening");
+                }
+                if opening.values[position / stride] != value {
+                    return Err("FRI layer value mismatch");
+                }
+                if !MerkleTree::<Sha256Hasher>::verify(
+                    root,
+                    index,
+                    &hash_row(&opening.values),
+                    &opening.path,
+                ) {
+                    return Err("Invalid FRI layer Merkle path");
+                }
+
+                let root_of_unity = Felt::two_adic_root(domain_size.trailing_zeros());
+                let x = offset * root_of_unity.pow(index as u64);
+                value = fold(&opening.values, *beta, x.inverse());
+
+                position = index;
+                domain_size = stride;
+                offset = offset.pow(k as u64);
+            }
+
+            let root_of_unity = Felt::two_adic_root(domain_size.trailing_zeros());
+            let x = offset * root_of_unity.pow(position as u64);
+            if evaluate_polynomial(&proof.remainder, E::from(x)) != value {
+                return Err("FRI remainder mismatch");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::{evaluate_on_coset, QuadExt};
+    use crate::transcript::Sha256Sponge;
+
+    const OPTIONS: FriOptions = FriOptions {
+        blowup_factor: 4,
+        folding_factor: 2,
+        num_queries: 8,
+        grinding_bits: 4,
+        max_remainder_degree: 4,
+    };
+
+    fn prove<E: FieldElement>(evaluations: &[E], options: &FriOptions) -> FriProof<E> {
+        let mut transcript = Transcript::<Sha256Sponge>::new(b"fri-test");
+        let prover = FriProver::commit(
+            evaluations.to_vec(),
+            Felt::GENERATOR,
+            options,
+            &mut transcript,
+        );
+        let positions =
+            transcript.challenge_indices("query positions", options.num_queries, evaluations.len());
+        prover.prove(&positions)
+    }
+
+    fn verify<E: FieldElement>(
+        proof: &FriProof<E>,
+        evaluations: &[E],
+        options: &FriOptions,
+    ) -> Result<(), &'static str> {
+        let domain_size = evaluations.len();
+        let mut transcript = Transcript::<Sha256Sponge>::new(b"fri-test");
+        let verifier = FriVerifier::new(
+            proof,
+            domain_size,
+            Felt::GENERATOR,
+            options,
+            &mut transcript,
+        )?;
+        let positions =
+            transcript.challenge_indices("query positions", options.num_queries, domain_size);
+        let values: Vec<E> = positions.iter().map(|&p| evaluations[p]).collect();
+        verifier.verify(proof, &positions, &values)
+    }
+
+    fn run<E: FieldElement>(
+        coefficients: &[E],
+        domain_size: usize,
+        options: &FriOptions,
+    ) -> Result<(), &'static str> {
+        let evaluations = evaluate_on_coset(coefficients, Felt::GENERATOR, domain_size);
+        verify(&prove(&evaluations, options), &evaluations, options)
+    }
+
+    #[test]
+    fn test_fri_low_degree() {
+        let coefficients: Vec<Felt> = (0..64).map(|i| Felt::new(i * i + 1)).collect();
+        assert_eq!(run(&coefficients, 256, &OPTIONS), Ok(()));
+    }
+
+    #[test]
+    fn test_fri_rejects_high_degree() {
+        // Degree 255 polynomial claimed to have degree < 64
+        let coefficients: Vec<Felt> = (0..256).map(|i| Felt::new(i * 7 + 3)).collect();
+        assert!(run(&coefficients, 256, &OPTIONS).is_err());
+    }
+
+    #[test]
+    fn test_fri_extension_field() {
+        let coefficients: Vec<QuadExt> = (0..64)
+            .map(|i| QuadExt(Felt::new(i * i + 1), Felt::new(3 * i + 2)))
+            .collect();
+        assert_eq!(run(&coefficients, 256, &OPTIONS), Ok(()));
+
+        // Degree 255 polynomial claimed to have degree < 64
+        let high: Vec<QuadExt> = (0..256)
+            .map(|i| QuadExt(Felt::new(i * 7 + 3), Felt::new(i + 5)))
+            .collect();
+        assert!(run(&high, 256, &OPTIONS).is_err());
+    }
+
+    #[test]
+    fn test_fri_folding_factors() {
+        let coefficients: Vec<Felt> = (0..128).map(|i| Felt::new(i * 5 + 2)).collect();
+        for folding_factor in [2, 4, 8, 16] {
+            let options = FriOptions {
+                folding_factor,
+                ..OPTIONS
+            };
+            assert_eq!(run(&coefficients, 512, &options), Ok(()));
+
+            // Degree 255 polynomial claimed to have degree < 128
+            let high: Vec<Felt> = (0..256).map(|i| Felt::new(i * 3 + 1)).collect();
+            assert!(run(&high, 512, &options).is_err());
+        }
+    }
+
+    #[test]
+    fn test_fri_grinding() {
+        let coefficients: Vec<Felt> = (0..64).map(|i| Felt::new(i + 1)).collect();
+        let evaluations = evaluate_on_coset(&coefficients, Felt::GENERATOR, 256);
+        let options = FriOptions {
+            grinding_bits: 12,
+            ..OPTIONS
+        };
+        let mut proof = prove(&evaluations, &options);
+        assert_eq!(verify(&proof, &evaluations, &options), Ok(()));
+
+        proof.pow_nonce += 1;
+        assert_eq!(
+            verify(&proof, &evaluations, &options),
+            Err("Invalid FRI proof-of-work")
+        );
+    }
+
+    #[test]
+    fn test_fri_security_level() {
+        let options = FriOptions {
+            blowup_factor: 8,
+            folding_factor: 4,
+            num_queries: 32,
+            grinding_bits: 16,
+            max_remainder_degree: 8,
+        };
+        // 32 queries * 3 bits + 16 grinding bits, capped by 64 - log2(2^20) for base field
+        // challenges and by 128 - log2(2^20) for challenges from the quadratic extension
+        assert_eq!(options.conjectured_security::<Felt>(1 << 20), 44);
+        assert_eq!(options.conjectured_security::<QuadExt>(1 << 20), 108);
+        let options = FriOptions {
+            num_queries: 8,
+            ..options
+        };
+        assert_eq!(options.conjectured_security::<Felt>(1 << 20), 40);
+        assert_eq!(options.conjectured_security::<QuadExt>(1 << 20), 40);
+
+        assert!(options.validate().is_ok());
+        let invalid = FriOptions {
+            folding_factor: 3,
+            ..options
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
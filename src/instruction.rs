@@ -36,7 +36,8 @@
 //! - `OP_ST`: Store data
 //! - `OP_STI`: Indirect store data
 //! - `OP_STR`: Store data from register
-//! - `OP_TRAP`: System call
+//! - `OP_TRAP`: System call: `GETC`, `OUT`, `PUTS`, `IN`, `PUTSP` and `HALT` (x20-x25), a read
+//!   of private input (x26) and a SHA-256 hash of guest memory (x27)
 //!
 //! # Error Handling
 //!
@@ -64,15 +65,27 @@
 //! }
 //! ```
 
+use crate::air::sha256;
+use crate::io::{Console, Io};
 use crate::memory::Memory;
 use crate::opcode::{extract_opcode, Opcode};
 use crate::register::{condition_flags, Register, RegisterFile};
-use std::io::{self, Read, Write};
 
+/// Execute an instruction, with trap routines reading stdin and writing stdout
 pub fn execute(
     raw: u16,
     registers: &mut RegisterFile,
     memory: &mut Memory,
+) -> Result<(), &'static str> {
+    execute_with_io(raw, registers, memory, &mut Console)
+}
+
+/// Execute an instruction, with trap routines reading and writing `io`
+pub fn execute_with_io(
+    raw: u16,
+    registers: &mut RegisterFile,
+    memory: &mut Memory,
+    io: &mut dyn Io,
 ) -> Result<(), &'static str> {
     let opcode = extract_opcode(raw).ok_or("Unknown opcode")?;
     match opcode {
@@ -89,12 +102,34 @@ pub fn execute(
         Opcode::OP_ST => execute_st(raw, registers, memory),
         Opcode::OP_STI => execute_sti(raw, registers, memory),
         Opcode::OP_STR => execute_str(raw, registers, memory),
-        Opcode::OP_TRAP => execute_trap(raw, registers, memory),
+        Opcode::OP_TRAP => execute_trap(raw, registers, memory, io),
         Opcode::OP_RES => Err("Reserved opcode"),
         Opcode::OP_RTI => Err("RTI not implemented"),
     }
 }
 
+/// Addresses that executing `raw` with `registers` and `memory` would write, found without
+/// executing it. The PC in `registers` is the incremented one, as in [`execute_with_io`].
+pub fn store_addresses(raw: u16, registers: &RegisterFile, memory: &Memory) -> Vec<u16> {
+    let pc = registers.read(Register::PC);
+    let pc_offset = sign_extend(raw & 0x1FF, 9);
+    match extract_opcode(raw) {
+        Some(Opcode::OP_ST) => vec![pc.wrapping_add(pc_offset)],
+        Some(Opcode::OP_STI) => vec![memory[pc.wrapping_add(pc_offset)]],
+        Some(Opcode::OP_STR) => {
+            let base = registers.read(Register::from((raw >> 6) & 0x7));
+            vec![base.wrapping_add(sign_extend(raw & 0x3F, 6))]
+        }
+        Some(Opcode::OP_TRAP) if raw & 0xFF == 0x27 && registers.read(Register::R1) != 0 => {
+            let digest = registers.read(Register::R2);
+            (0..2 * sha256::INITIAL_STATE.len() as u16)
+                .map(|i| digest.wrapping_add(i))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
 /// ADD - Add
 ///
 /// Add two values and store the result in a register.
@@ -347,83 +382,116 @@ fn execute_trap(
     raw: u16,
     registers: &mut RegisterFile,
     memory: &mut Memory,
+    io: &mut dyn Io,
 ) -> Result<(), &'static str> {
     let trapvect8 = raw & 0xFF;
     match trapvect8 {
-        0x20 => trap_getc(registers),
-        0x21 => trap_out(registers),
-        0x22 => trap_puts(registers, memory),
-        0x23 => trap_in(registers),
-        0x24 => trap_putsp(registers, memory),
+        0x20 => trap_getc(registers, io),
+        0x21 => trap_out(registers, io),
+        0x22 => trap_puts(registers, memory, io),
+        0x23 => trap_in(registers, io),
+        0x24 => trap_putsp(registers, memory, io),
         0x25 => trap_halt(),
+        0x26 => trap_private(registers, io),
+        0x27 => trap_sha256(registers, memory),
         _ => Err("Unknown TRAP vector"),
     }
 }
 
-fn trap_getc(registers: &mut RegisterFile) -> Result<(), &'static str> {
-    let mut buffer = [0; 1];
-    if io::stdin().read_exact(&mut buffer).is_ok() {
-        registers.write(Register::R0, buffer[0] as u16);
-        Ok(())
-    } else {
-        Err("Failed to read character")
-    }
+fn trap_getc(registers: &mut RegisterFile, io: &mut dyn Io) -> Result<(), &'static str> {
+    let byte = io.read_byte()?;
+    registers.write(Register::R0, byte as u16);
+    Ok(())
 }
 
-fn trap_out(registers: &mut RegisterFile) -> Result<(), &'static str> {
-    let char = (registers.read(Register::R0) & 0xFF) as u8 as char;
-    print!("{}", char);
-    io::stdout().flush().map_err(|_| "Failed to flush stdout")?;
-    Ok(())
+fn trap_out(registers: &mut RegisterFile, io: &mut dyn Io) -> Result<(), &'static str> {
+    io.write_byte((registers.read(Register::R0) & 0xFF) as u8)?;
+    io.flush()
 }
 
-fn trap_puts(registers: &mut RegisterFile, memory: &Memory) -> Result<(), &'static str> {
+fn trap_puts(
+    registers: &mut RegisterFile,
+    memory: &Memory,
+    io: &mut dyn Io,
+) -> Result<(), &'static str> {
     let mut address = registers.read(Register::R0);
     loop {
-        let char = (memory.read(address) & 0xFF) as u8 as char;
-        if char == '\0' {
+        let char = (memory.read(address) & 0xFF) as u8;
+        if char == 0 {
             break;
         }
-        print!("{}", char);
+        io.write_byte(char)?;
         address += 1;
     }
-    io::stdout().flush().map_err(|_| "Failed to flush stdout")?;
-    Ok(())
+    io.flush()
 }
 
-fn trap_in(registers: &mut RegisterFile) -> Result<(), &'static str> {
-    print!("Enter a character: ");
-    io::stdout().flush().map_err(|_| "Failed to flush stdout")?;
-    let mut buffer = [0; 1];
-    if io::stdin().read_exact(&mut buffer).is_ok() {
-        let char = buffer[0] as char;
-        println!("{}", char);
-        registers.write(Register::R0, buffer[0] as u16);
-        Ok(())
-    } else {
-        Err("Failed to read character")
-    }
+fn trap_in(registers: &mut RegisterFile, io: &mut dyn Io) -> Result<(), &'static str> {
+    let byte = io.read_input()?;
+    registers.write(Register::R0, byte as u16);
+    Ok(())
 }
 
-fn trap_putsp(registers: &mut RegisterFile, memory: &Memory) -> Result<(), &'static str> {
+fn trap_putsp(
+    registers: &mut RegisterFile,
+    memory: &Memory,
+    io: &mut dyn Io,
+) -> Result<(), &'static str> {
     let mut address = registers.read(Register::R0);
     loop {
         let word = memory.read(address);
-        let char1 = (word & 0xFF) as u8 as char;
-        if char1 == '\0' {
+        let char1 = (word & 0xFF) as u8;
+        if char1 == 0 {
             break;
         }
-        print!("{}", char1);
+        io.write_byte(char1)?;
 
-        let char2 = ((word >> 8) & 0xFF) as u8 as char;
-        if char2 != '\0' {
-            print!("{}", char2);
+        let char2 = ((word >> 8) & 0xFF) as u8;
+        if char2 != 0 {
+            io.write_byte(char2)?;
         } else {
             break;
         }
         address += 1;
     }
-    io::stdout().flush().map_err(|_| "Failed to flush stdout")?;
+    io.flush()
+}
+
+/// Read the next word of the private input into R0
+fn trap_private(registers: &mut RegisterFile, io: &mut dyn Io) -> Result<(), &'static str> {
+    let word = io.read_private()?;
+    registers.write(Register::R0, word);
+    Ok(())
+}
+
+/// Hash the padded message at R0, made of R1 blocks of 32 words, with SHA-256 and write the
+/// 16-word digest at R2.
+///
+/// Every word holds two bytes, the first one in its high byte. The guest pads the message, see
+/// [`sha256_message_words`](crate::utils::sha256_message_words).
+fn trap_sha256(registers: &mut RegisterFile, memory: &mut Memory) -> Result<(), &'static str> {
+    let address = registers.read(Register::R0);
+    let blocks = registers.read(Register::R1) as usize;
+    if blocks == 0 {
+        return Err("SHA-256 message has no blocks");
+    }
+    let mut state = sha256::INITIAL_STATE;
+    let mut words =
+        (0..sha256::WORDS_PER_BLOCK * blocks).map(|i| memory.read(address.wrapping_add(i as u16)));
+    for _ in 0..blocks {
+        let block = std::array::from_fn(|_| {
+            let hi = words.next().unwrap() as u32;
+            let lo = words.next().unwrap() as u32;
+            hi << 16 | lo
+        });
+        sha256::compress(&mut state, &block);
+    }
+
+    let digest = registers.read(Register::R2);
+    for (i, word) in state.iter().enumerate() {
+        memory.write(digest.wrapping_add(2 * i as u16), (word >> 16) as u16);
+        memory.write(digest.wrapping_add(2 * i as u16 + 1), *word as u16);
+    }
     Ok(())
 }
 
@@ -438,19 +506,3 @@ fn sign_extend(mut x: u16, bit_count: u16) -> u16 {
     }
     x
 }
-// Helper function: Convert u16 to Register
-impl From<u16> for Register {
-    fn from(value: u16) -> Self {
-        match value {
-            0 => Register::R0,
-            1 => Register::R1,
-            2 => Register::R2,
-            3 => Register::R3,
-            4 => Register::R4,
-            5 => Register::R5,
-            6 => Register::R6,
-            7 => Register::R7,
-            _ => panic!("Invalid register number"),
-        }
-    }
-}
@@ -0,0 +1,210 @@
+//! I/O Module
+//!
+//! This module defines where the trap routines read and write characters.
+//!
+//! ## Design
+//! - [`Io`] is the channel used by `GETC` and `IN` to read bytes and by `OUT`, `PUTS` and `PUTSP`
+//!   to write them.
+//! - [`Console`] reads stdin and writes stdout. `IN` prompts for its character and echoes it, as on
+//!   a terminal; the prompt and the echo are not part of the program's output.
+//! - [`IoTape`] is the public I/O model of proven runs: input bytes come from a declared input
+//!   tape, and every written byte is appended to an output journal. The bytes consumed and the
+//!   journal become public inputs of the proof.
+//! - Programs may also read private words, with `TRAP x26`, from a host-supplied witness. Private
+//!   words are not recorded in the trace's I/O and never become public inputs; proofs should use
+//!   zero-knowledge mode so that the openings do not reveal them either.
+
+use std::io::{self, Read, Write};
+
+/// Character channel of the trap routines
+pub trait Io {
+    /// Next input byte, for `GETC`
+    fn read_byte(&mut self) -> Result<u8, &'static str>;
+
+    /// Append a byte to the output, for `OUT`, `PUTS` and `PUTSP`
+    fn write_byte(&mut self, byte: u8) -> Result<(), &'static str>;
+
+    /// Next input byte, for `IN`
+    fn read_input(&mut self) -> Result<u8, &'static str> {
+        self.read_byte()
+    }
+
+    /// Next word of the private input, for `TRAP x26`
+    fn read_private(&mut self) -> Result<u16, &'static str> {
+        Err("No private input")
+    }
+
+    /// Flush buffered output, at the end of a trap routine
+    fn flush(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+/// Standard input and output
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Console;
+
+impl Io for Console {
+    fn read_byte(&mut self) -> Result<u8, &'static str> {
+        let mut buffer = [0; 1];
+        io::stdin()
+            .read_exact(&mut buffer)
+            .map_err(|_| "Failed to read character")?;
+        Ok(buffer[0])
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), &'static str> {
+        print!("{}", byte as char);
+        Ok(())
+    }
+
+    fn read_input(&mut self) -> Result<u8, &'static str> {
+        print!("Enter a character: ");
+        self.flush()?;
+        let byte = self.read_byte()?;
+        println!("{}", byte as char);
+        Ok(byte)
+    }
+
+    fn flush(&mut self) -> Result<(), &'static str> {
+        io::stdout().flush().map_err(|_| "Failed to flush stdout")
+    }
+}
+
+/// A declared input tape, an output journal and an optional private input
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IoTape {
+    input: Vec<u8>,
+    position: usize,
+    journal: Vec<u8>,
+    private_input: Vec<u16>,
+    private_position: usize,
+}
+
+impl IoTape {
+    pub fn new(input: Vec<u8>) -> Self {
+        IoTape {
+            input,
+            ..IoTape::default()
+        }
+    }
+
+    /// Supply the words read by `TRAP x26`
+    pub fn with_private_input(mut self, words: Vec<u16>) -> Self {
+        self.private_input = words;
+        self.private_position = 0;
+        self
+    }
+
+    /// Input bytes read so far
+    pub fn consumed(&self) -> &[u8] {
+        &self.input[..self.position]
+    }
+
+    /// Bytes written so far
+    pub fn journal(&self) -> &[u8] {
+        &self.journal
+    }
+}
+
+impl Io for IoTape {
+    fn read_byte(&mut self) -> Result<u8, &'static str> {
+        let byte = *self
+            .input
+            .get(self.position)
+            .ok_or("Input tape exhausted")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), &'static str> {
+        self.journal.push(byte);
+        Ok(())
+    }
+
+    fn read_private(&mut self) -> Result<u16, &'static str> {
+        let word = *self
+            .private_input
+            .get(self.private_position)
+            .ok_or("Private input exhausted")?;
+        self.private_position += 1;
+        Ok(word)
+    }
+}
+
+/// Forwards to another channel and records the public bytes read and written
+pub(crate) struct Recorder<'a> {
+    io: &'a mut dyn Io,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+}
+
+impl<'a> Recorder<'a> {
+    pub(crate) fn new(io: &'a mut dyn Io) -> Self {
+        Recorder {
+            io,
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+}
+
+impl Io for Recorder<'_> {
+    fn read_byte(&mut self) -> Result<u8, &'static str> {
+        let byte = self.io.read_byte()?;
+        self.input.push(byte);
+        Ok(byte)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), &'static str> {
+        self.io.write_byte(byte)?;
+        self.output.push(byte);
+        Ok(())
+    }
+
+    fn read_input(&mut self) -> Result<u8, &'static str> {
+        let byte = self.io.read_input()?;
+        self.input.push(byte);
+        Ok(byte)
+    }
+
+    fn read_private(&mut self) -> Result<u16, &'static str> {
+        self.io.read_private()
+    }
+
+    fn flush(&mut self) -> Result<(), &'static str> {
+        self.io.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_tape() {
+        let mut tape = IoTape::new(b"ab".to_vec());
+        assert_eq!(tape.read_byte(), Ok(b'a'));
+        assert_eq!(tape.read_input(), Ok(b'b'));
+        assert_eq!(tape.read_byte(), Err("Input tape exhausted"));
+        assert_eq!(tape.consumed(), b"ab");
+        assert_eq!(tape.read_private(), Err("Private input exhausted"));
+
+        let mut private = IoTape::new(Vec::new()).with_private_input(vec![0xBEEF]);
+        assert_eq!(private.read_private(), Ok(0xBEEF));
+        assert_eq!(private.read_private(), Err("Private input exhausted"));
+        assert_eq!(Console.read_private(), Err("No private input"));
+
+        let mut recorder = Recorder::new(&mut tape);
+        recorder.write_byte(b'x').unwrap();
+        recorder.write_byte(b'y').unwrap();
+        assert_eq!(recorder.output, b"xy");
+        assert_eq!(tape.journal(), b"xy");
+
+        // Private words are forwarded but not recorded
+        let mut private = IoTape::new(Vec::new()).with_private_input(vec![7]);
+        let mut recorder = Recorder::new(&mut private);
+        assert_eq!(recorder.read_private(), Ok(7));
+        assert!(recorder.input.is_empty());
+    }
+}
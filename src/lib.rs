@@ -5,14 +5,45 @@
 //! # Modules
 //!
 //! - [`instruction`]: Contains functions to execute various LC3 instructions.
+//! - [`io`]: Character I/O of the trap routines: the console, or an input tape and output journal.
 //! - [`memory`]: Manages the memory of the LC3 virtual machine.
 //! - [`opcode`]: Defines the opcodes used by the LC3 virtual machine.
 //! - [`register`]: Manages the registers of the LC3 virtual machine.
+//! - [`trace`]: Records the per-step execution trace of a program run.
 //! - [`utils`]: Provides utility functions used throughout the LC3 virtual machine.
 //!
+//! Proving modules:
+//!
+//! - [`field`]: Goldilocks field and its quadratic extension, NTTs and polynomial arithmetic.
+//! - [`merkle`]: Merkle trees, generic over the hash, with sparse construction and updates.
+//! - [`poseidon`]: Poseidon hash over the Goldilocks field.
+//! - [`poseidon2`]: Poseidon2 hash over the Goldilocks field, with the reference constants.
+//! - [`commit`]: Merkle commitments to memory images and program IDs.
+//! - [`transcript`]: Labelled Fiat-Shamir transcript over a SHA-256 or Poseidon sponge.
+//! - [`fri`]: FRI low-degree test.
+//! - [`air`]: AIR of an LC3 run covering every opcode, and a constraint checker.
+//! - [`proof`]: Public inputs, proof options and the proof format.
+//! - [`prover`]: STARK prover for LC3 runs.
+//! - [`verifier`]: STARK verifier for LC3 runs.
+//! - [`receipt`]: Receipts of proven runs and their versioned binary format.
+//! - [`continuation`]: Splitting long runs into segments that are proven independently.
+//!
+//! The default `std` feature enables the virtual machine, the prover and the Poseidon hashes.
+//! Without it, the crate is `no_std` with `alloc` and keeps what verification needs: the field,
+//! SHA-256 Merkle trees and transcripts, FRI, the AIRs, and the proof and receipt formats. It
+//! builds for `wasm32-unknown-unknown`, so receipts can be verified in browsers and embedded hosts:
+//!
+//! ```toml
+//! lc3-zkvm = { version = "0.1", default-features = false }
+//! ```
+//!
+//! With the `parallel` feature, the prover runs its LDEs, constraint evaluation, Merkle hashing
+//! and FRI folding on all cores. Proofs do not depend on the number of threads.
+//!
 //! # Example
 //!
 //! ```rust
+//! # #[cfg(feature = "std")] {
 //! use lc3_zkvm::memory::Memory;
 //! use lc3_zkvm::register::RegisterFile;
 //! use lc3_zkvm::instruction::execute;
@@ -25,13 +56,46 @@
 //!     Ok(_) => println!("Instruction executed successfully"),
 //!     Err(e) => println!("Instruction execution failed: {}", e),
 //! }
+//! # }
 //! ```
 
+// Binary literals are grouped by LC3 instruction fields (opcode, DR, SR1, ...), not by nibbles.
+#![allow(clippy::unusual_byte_groupings)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod air;
+pub mod commit;
+#[cfg(feature = "std")]
+pub mod continuation;
+pub mod field;
+pub mod fri;
+#[cfg(feature = "std")]
 pub mod instruction;
+#[cfg(feature = "std")]
+pub mod io;
 pub mod memory;
+pub mod merkle;
 pub mod opcode;
+#[cfg(feature = "std")]
+pub mod poseidon;
+#[cfg(feature = "std")]
+pub mod poseidon2;
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod prover;
+pub mod receipt;
 pub mod register;
+pub mod trace;
+pub mod transcript;
+#[cfg(feature = "std")]
 pub mod utils;
+pub mod verifier;
+
+// Only the prover maps over whole collections
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod parallel;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod instruction_test;
@@ -1,25 +1,120 @@
+use lc3_zkvm::commit::{memory_root, program_id};
+use lc3_zkvm::io::IoTape;
 use lc3_zkvm::memory::Memory;
+use lc3_zkvm::merkle::{from_hex, to_hex};
+use lc3_zkvm::proof::ProofOptions;
+use lc3_zkvm::prover::prove;
+use lc3_zkvm::receipt::Receipt;
 use lc3_zkvm::register::{Register, RegisterFile};
-use lc3_zkvm::utils::{execute_program, load_obj_file};
+use lc3_zkvm::trace::RegisterState;
+use lc3_zkvm::utils::{
+    execute_deterministic, execute_program, load_obj_file, load_obj_file_as_rom, Outcome,
+};
 use std::env;
+use std::fs;
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+const USAGE: &str = "Usage:
+  lc3-zkvm <program.obj>
+  lc3-zkvm run <program.obj>
+  lc3-zkvm prove <program.obj> [--input <file>] [--max-cycles <n>] [--zero-knowledge] [--rom]
+      -o <receipt.bin>
+  lc3-zkvm verify <receipt.bin> --program-id <hex>";
 
-    if args.len() < 2 {
-        return Err("Usage: program <path_to_obj_file>".into());
+type Error = Box<dyn std::error::Error>;
+
+/// Cycle budget of `prove` without `--max-cycles`
+const DEFAULT_MAX_CYCLES: usize = 1 << 20;
+
+/// Positional arguments, and the options given with their values
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    options: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Args<'a> {
+    /// Split arguments into positional ones, `options` taking a value and `flags`
+    fn parse(args: &'a [String], options: &[&str], flags: &[&str]) -> Result<Self, Error> {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if flags.contains(&arg.as_str()) {
+                parsed.options.push((arg.as_str(), ""));
+            } else if options.contains(&arg.as_str()) {
+                let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+                parsed.options.push((arg.as_str(), value.as_str()));
+            } else if arg.starts_with('-') {
+                return Err(format!("Unknown option {}\n{}", arg, USAGE).into());
+            } else {
+                parsed.positional.push(arg.as_str());
+            }
+        }
+        Ok(parsed)
     }
 
-    let obj_file_path = &args[1];
+    /// Value of the last occurrence of an option, under any of its names
+    fn option(&self, names: &[&str]) -> Option<&'a str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(name, _)| names.contains(name))
+            .map(|(_, value)| *value)
+    }
 
-    let mut memory = Memory::new();
-    let mut registers = RegisterFile::new();
+    /// The single positional argument
+    fn path(&self) -> Result<&'a str, Error> {
+        match self.positional[..] {
+            [path] => Ok(path),
+            _ => Err(USAGE.into()),
+        }
+    }
+}
 
-    // Load the LC3 object file
-    let origin = load_obj_file(obj_file_path, &mut memory)?;
+type CommandResult = Result<(), Error>;
 
-    // Set the PC to the program's origin
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("run") => run(&args[1..]),
+        Some("prove") => prove_command(&args[1..]),
+        Some("verify") => verify_command(&args[1..]),
+        Some(_) if args.len() == 1 => run(&args),
+        _ => Err(USAGE.into()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Load an object file, as a program ROM if `rom` is set, and point the PC at its origin
+fn load(path: &str, rom: bool) -> Result<(Memory, RegisterFile), Error> {
+    let mut memory = Memory::new();
+    let mut registers = RegisterFile::new();
+    let origin = if rom {
+        load_obj_file_as_rom(path, &mut memory)
+    } else {
+        load_obj_file(path, &mut memory)
+    }
+    .map_err(|e| format!("Cannot load {}: {}", path, e))?;
     registers.write(Register::PC, origin);
+    Ok((memory, registers))
+}
+
+fn run(args: &[String]) -> CommandResult {
+    let path = Args::parse(args, &[], &[])?.path()?;
+    let (mut memory, mut registers) = load(path, false)?;
+    let entry = RegisterState::capture(&registers);
+    println!(
+        "Program ID: {}",
+        to_hex(&program_id(&memory_root(&memory), &entry))
+    );
 
     // Execute the program
     match execute_program(&mut memory, &mut registers) {
@@ -29,3 +124,66 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+fn prove_command(args: &[String]) -> CommandResult {
+    let args = Args::parse(
+        args,
+        &["--input", "--max-cycles", "-o", "--output"],
+        &["--zero-knowledge", "--rom"],
+    )?;
+    let path = args.path()?;
+    let output = args.option(&["-o", "--output"]).ok_or(USAGE)?;
+    let input = match args.option(&["--input"]) {
+        Some(file) => fs::read(file).map_err(|e| format!("Cannot read {}: {}", file, e))?,
+        None => Vec::new(),
+    };
+    let max_cycles = match args.option(&["--max-cycles"]) {
+        Some(n) => n.parse().map_err(|_| "--max-cycles must be a number")?,
+        None => DEFAULT_MAX_CYCLES,
+    };
+    let mut options = ProofOptions::default();
+    if args.option(&["--zero-knowledge"]).is_some() {
+        options = options.with_zero_knowledge();
+    }
+
+    let rom = args.option(&["--rom"]).is_some();
+    let (mut memory, mut registers) = load(path, rom)?;
+    let initial_memory = memory.clone();
+    let mut tape = IoTape::new(input);
+    let execution = execute_deterministic(&mut memory, &mut registers, &mut tape, max_cycles)
+        .map_err(|e| format!("Execution failed: {}", e))?;
+    if execution.outcome == Outcome::OutOfCycles {
+        println!(
+            "Out of cycles after {} cycles; proving the run up to the cutoff",
+            max_cycles
+        );
+    }
+    let trace = execution.trace;
+    let (public_inputs, proof) =
+        prove(&initial_memory, &trace, &options).map_err(|e| format!("Proving failed: {}", e))?;
+    let receipt = Receipt::new(public_inputs, proof);
+    fs::write(output, receipt.to_bytes()).map_err(|e| format!("Cannot write {}: {}", output, e))?;
+
+    println!("Program ID: {}", to_hex(&receipt.program_id()));
+    println!("Steps: {}", trace.len());
+    println!("Journal: {:?}", String::from_utf8_lossy(receipt.journal()));
+    println!("Receipt written to {}", output);
+    Ok(())
+}
+
+fn verify_command(args: &[String]) -> CommandResult {
+    let args = Args::parse(args, &["--program-id"], &[])?;
+    let path = args.path()?;
+    let hex = args.option(&["--program-id"]).ok_or(USAGE)?;
+    let expected = from_hex(hex).ok_or("Program ID must be 64 hex digits")?;
+    let bytes = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let receipt = Receipt::from_bytes(&bytes).map_err(|e| format!("Invalid receipt: {}", e))?;
+    receipt
+        .verify(&expected)
+        .map_err(|e| format!("Verification failed: {}", e))?;
+
+    println!("Receipt verified");
+    println!("Exit status: {:?}", receipt.exit_status());
+    println!("Journal: {:?}", String::from_utf8_lossy(receipt.journal()));
+    Ok(())
+}
@@ -8,6 +8,11 @@
 //! - The memory is implemented as a fixed-size array of 65,536 16-bit unsigned integers.
 //! - Memory operations include reading, writing, and clearing.
 //! - The module implements the `Index` and `IndexMut` traits for convenient array-like access.
+//! - Accesses made through `read` and `write` can be recorded as [`MemoryAccess`] entries, which
+//!   is how the executor builds its per-step execution trace. Array-like indexing is not recorded.
+//! - A range of memory can be marked as a read-only [`ProgramRom`] holding the program code. The
+//!   executor then fetches instructions from it only, and stops with an error if the program
+//!   writes into it; `write` itself does not check the range, so that loaders can fill it.
 //!
 //! ## Usage
 //! Create a new memory instance:
@@ -24,32 +29,153 @@
 //! let value = memory[0x3000];
 //! ```
 
-use std::ops::{Index, IndexMut};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ops::{Index, IndexMut};
 
 pub const MEMORY_SIZE: usize = 65536; // 2^16, as LC3 uses 16-bit addressing
 
+/// A single recorded memory access.
+///
+/// A read leaves the word unchanged, so `old_value == new_value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub address: u16,
+    pub old_value: u16,
+    pub new_value: u16,
+    pub is_write: bool,
+}
+
+/// A read-only range of memory holding the program code: `length` words from `start` on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramRom {
+    pub start: u16,
+    pub length: u16,
+}
+
+impl ProgramRom {
+    /// Check that the ROM holds at least one word and fits in the address space
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.length == 0 || self.start as usize + self.length as usize > MEMORY_SIZE {
+            return Err("Invalid program ROM");
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, address: u16) -> bool {
+        address.wrapping_sub(self.start) < self.length
+    }
+
+    /// Addresses of the ROM, in order
+    pub fn addresses(self) -> impl Iterator<Item = u16> {
+        (0..self.length).map(move |offset| self.start + offset)
+    }
+}
+
 pub struct Memory {
     data: [u16; MEMORY_SIZE],
+    log: RefCell<Option<Vec<MemoryAccess>>>,
+    rom: Option<ProgramRom>,
 }
 
 impl Memory {
     pub fn new() -> Self {
         Memory {
             data: [0; MEMORY_SIZE],
+            log: RefCell::new(None),
+            rom: None,
         }
     }
 
     pub fn read(&self, address: u16) -> u16 {
-        self.data[address as usize]
+        let value = self.data[address as usize];
+        self.record(MemoryAccess {
+            address,
+            old_value: value,
+            new_value: value,
+            is_write: false,
+        });
+        value
     }
 
     pub fn write(&mut self, address: u16, value: u16) {
+        let old_value = self.data[address as usize];
+        self.record(MemoryAccess {
+            address,
+            old_value,
+            new_value: value,
+            is_write: true,
+        });
         self.data[address as usize] = value;
     }
 
     pub fn clear(&mut self) {
         self.data = [0; MEMORY_SIZE];
     }
+
+    /// The non-zero words of memory as `(address, value)` pairs, in address order
+    pub fn image(&self) -> Vec<(u16, u16)> {
+        (0..MEMORY_SIZE)
+            .filter(|&address| self.data[address] != 0)
+            .map(|address| (address as u16, self.data[address]))
+            .collect()
+    }
+
+    /// Create a memory holding the given `(address, value)` pairs and zeros elsewhere
+    pub fn from_image(image: &[(u16, u16)]) -> Self {
+        let mut memory = Memory::new();
+        for &(address, value) in image {
+            memory.data[address as usize] = value;
+        }
+        memory
+    }
+
+    /// Treat the given range as a read-only program ROM, or stop treating any range as one
+    pub fn set_rom(&mut self, rom: Option<ProgramRom>) -> Result<(), &'static str> {
+        if let Some(rom) = &rom {
+            rom.validate()?;
+        }
+        self.rom = rom;
+        Ok(())
+    }
+
+    /// The program ROM, if instructions are fetched from one
+    pub fn rom(&self) -> Option<ProgramRom> {
+        self.rom
+    }
+
+    /// Start recording accesses made through `read` and `write`, discarding any previous log.
+    pub fn start_recording(&self) {
+        *self.log.borrow_mut() = Some(Vec::new());
+    }
+
+    /// Stop recording and return the accesses logged since `start_recording`.
+    pub fn stop_recording(&self) -> Vec<MemoryAccess> {
+        self.log.borrow_mut().take().unwrap_or_default()
+    }
+
+    fn record(&self, access: MemoryAccess) {
+        if let Some(log) = self.log.borrow_mut().as_mut() {
+            log.push(access);
+        }
+    }
+}
+
+impl Clone for Memory {
+    /// Copy the memory contents and the program ROM; the access log is not copied
+    fn clone(&self) -> Self {
+        Memory {
+            data: self.data,
+            log: RefCell::new(None),
+            rom: self.rom,
+        }
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Index<u16> for Memory {
@@ -69,6 +195,7 @@ impl IndexMut<u16> for Memory {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn test_memory_operations() {
@@ -82,9 +209,70 @@ mod tests {
         mem[0x3001] = 0x5678;
         assert_eq!(mem[0x3001], 0x5678);
 
+        // Test image
+        assert_eq!(mem.image(), vec![(0x3000, 0x1234), (0x3001, 0x5678)]);
+        assert_eq!(Memory::from_image(&mem.image())[0x3001], 0x5678);
+
         // Test clear
         mem.clear();
         assert_eq!(mem[0x3000], 0);
         assert_eq!(mem[0x3001], 0);
     }
+
+    #[test]
+    fn test_memory_recording() {
+        let mut mem = Memory::new();
+        mem.write(0x3000, 0x1111);
+
+        mem.start_recording();
+        mem.write(0x3000, 0x2222);
+        assert_eq!(mem.read(0x3000), 0x2222);
+        mem[0x3001] = 0x3333;
+        let log = mem.stop_recording();
+
+        assert_eq!(
+            log,
+            vec![
+                MemoryAccess {
+                    address: 0x3000,
+                    old_value: 0x1111,
+                    new_value: 0x2222,
+                    is_write: true,
+                },
+                MemoryAccess {
+                    address: 0x3000,
+                    old_value: 0x2222,
+                    new_value: 0x2222,
+                    is_write: false,
+                },
+            ]
+        );
+
+        // Nothing is recorded once recording stops
+        mem.read(0x3000);
+        assert!(mem.stop_recording().is_empty());
+    }
+
+    #[test]
+    fn test_program_rom() {
+        let rom = ProgramRom {
+            start: 0xFFFE,
+            length: 2,
+        };
+        assert_eq!(rom.validate(), Ok(()));
+        assert!(rom.contains(0xFFFF) && !rom.contains(0) && !rom.contains(0xFFFD));
+        assert_eq!(rom.addresses().collect::<Vec<_>>(), [0xFFFE, 0xFFFF]);
+
+        let mut mem = Memory::new();
+        assert_eq!(
+            mem.set_rom(Some(ProgramRom { length: 3, ..rom })),
+            Err("Invalid program ROM")
+        );
+        assert_eq!(
+            mem.set_rom(Some(ProgramRom { length: 0, ..rom })),
+            Err("Invalid program ROM")
+        );
+        assert_eq!(mem.set_rom(Some(rom)), Ok(()));
+        assert_eq!(mem.clone().rom(), Some(rom));
+    }
 }
@@ -0,0 +1,283 @@
+//! Merkle Tree Module
+//!
+//! This module implements the binary Merkle tree used to commit to trace and FRI evaluations and to
+//! memory images.
+//!
+//! ## Design
+//! - Leaves are digests supplied by the caller; the number of leaves must be a power of two.
+//! - Internal nodes are `merge(left, right)` for a [`Hasher`]. The trace and FRI commitments use
+//!   [`Sha256Hasher`], where `merge` is `SHA-256(left || right)`; memory images can also be
+//!   committed with the algebraic [`PoseidonHasher`](crate::poseidon::PoseidonHasher) or
+//!   [`Poseidon2Hasher`](crate::poseidon2::Poseidon2Hasher).
+//! - An authentication path lists the sibling digests from the leaf level up to (but excluding)
+//!   the root.
+//! - Rows of committed polynomial evaluations are hashed into leaves with [`hash_row`], or with
+//!   [`hash_salted_row`] for hiding commitments: a fresh random salt per row keeps the digests of
+//!   unopened rows from confirming guesses of their values.
+//! - Trees with mostly identical leaves, such as a memory image, can be built sparsely: only the
+//!   nodes above a non-default leaf are hashed.
+
+use crate::field::FieldElement;
+use crate::parallel;
+use alloc::{format, string::String, vec, vec::Vec};
+use core::marker::PhantomData;
+use sha2::{Digest as _, Sha256};
+
+/// A 32-byte SHA-256 digest
+pub type Digest = [u8; 32];
+
+/// Hash arbitrary bytes into a digest
+pub fn hash_bytes(bytes: &[u8]) -> Digest {
+    Sha256::digest(bytes).into()
+}
+
+/// Hash two child nodes into their parent
+pub fn hash_pair(left: &Digest, right: &Digest) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Hash a row of field elements into a leaf digest
+pub fn hash_row<E: FieldElement>(values: &[E]) -> Digest {
+    let mut bytes = Vec::with_capacity(values.len() * 8);
+    for value in values {
+        value.write_bytes(&mut bytes);
+    }
+    hash_bytes(&bytes)
+}
+
+/// Hash a row of field elements and its salt into a leaf digest
+pub fn hash_salted_row<E: FieldElement>(values: &[E], salt: &Digest) -> Digest {
+    let mut bytes = Vec::with_capacity(values.len() * 8 + salt.len());
+    for value in values {
+        value.write_bytes(&mut bytes);
+    }
+    bytes.extend_from_slice(salt);
+    hash_bytes(&bytes)
+}
+
+/// Hex encoding of a digest
+pub fn to_hex(digest: &Digest) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parse a digest from its hex encoding
+pub fn from_hex(hex: &str) -> Option<Digest> {
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut digest = [0; 32];
+    for (byte, pair) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(core::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// A 2-to-1 compression function a Merkle tree can be built with
+pub trait Hasher {
+    /// Hash two child nodes into their parent
+    fn merge(left: &Digest, right: &Digest) -> Digest;
+}
+
+/// SHA-256 Merkle hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn merge(left: &Digest, right: &Digest) -> Digest {
+        hash_pair(left, right)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree<H = Sha256Hasher> {
+    /// `layers[0]` holds the leaves, the last layer holds the root
+    layers: Vec<Vec<Digest>>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Build a tree over the given leaf digests
+    pub fn new(leaves: Vec<Digest>) -> Self {
+        assert!(
+            leaves.len().is_power_of_two(),
+            "number of leaves must be a power of two"
+        );
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let children = layers.last().unwrap();
+            let parents = parallel::map_indices(children.len() / 2, |i| {
+                H::merge(&children[2 * i], &children[2 * i + 1])
+            });
+            layers.push(parents);
+        }
+        MerkleTree {
+            layers,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Build a tree of `num_leaves` leaves that all equal `default_leaf` except the given ones.
+    ///
+    /// Only the nodes above the given leaves are hashed; all other nodes of a layer share the root
+    /// of a default subtree.
+    pub fn sparse(num_leaves: usize, default_leaf: Digest, leaves: &[(usize, Digest)]) -> Self {
+        assert!(
+            num_leaves.is_power_of_two(),
+            "number of leaves must be a power of two"
+        );
+        let mut layer = vec![default_leaf; num_leaves];
+        let mut dirty = Vec::with_capacity(leaves.len());
+        for &(index, leaf) in leaves {
+            layer[index] = leaf;
+            dirty.push(index);
+        }
+
+        let mut layers = vec![layer];
+        let mut default = default_leaf;
+        while layers.last().unwrap().len() > 1 {
+            let children = layers.last().unwrap();
+            default = H::merge(&default, &default);
+            let mut parents = vec![default; children.len() / 2];
+            for index in dirty.iter_mut() {
+                *index /= 2;
+            }
+            dirty.sort_unstable();
+            dirty.dedup();
+            for &index in &dirty {
+                parents[index] = H::merge(&children[2 * index], &children[2 * index + 1]);
+            }
+            layers.push(parents);
+        }
+        MerkleTree {
+            layers,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Replace the leaf at `index` and rehash the nodes above it
+    pub fn update(&mut self, index: usize, leaf: Digest) {
+        let mut index = index;
+        self.layers[0][index] = leaf;
+        for level in 1..self.layers.len() {
+            index /= 2;
+            let (left, right) = (
+                self.layers[level - 1][2 * index],
+                self.layers[level - 1][2 * index + 1],
+            );
+            self.layers[level][index] = H::merge(&left, &right);
+        }
+    }
+
+    pub fn leaf(&self, index: usize) -> Digest {
+        self.layers[0][index]
+    }
+
+    pub fn root(&self) -> Digest {
+        self.layers.last().unwrap()[0]
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    /// Authentication path for the leaf at `index`
+    pub fn prove(&self, index: usize) -> Vec<Digest> {
+        let mut path = Vec::with_capacity(self.layers.len() - 1);
+        let mut index = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            path.push(layer[index ^ 1]);
+            index >>= 1;
+        }
+        path
+    }
+
+    /// Root of the tree in which `leaf` sits at `index` with the given authentication path, or
+    /// `None` if `index` does not fit the path
+    pub fn root_from_path(index: usize, leaf: &Digest, path: &[Digest]) -> Option<Digest> {
+        let mut node = *leaf;
+        let mut index = index;
+        for sibling in path {
+            node = if index & 1 == 0 {
+                H::merge(&node, sibling)
+            } else {
+                H::merge(sibling, &node)
+            };
+            index >>= 1;
+        }
+        (index == 0).then_some(node)
+    }
+
+    /// Check that `leaf` sits at `index` in the tree with the given root
+    pub fn verify(root: &Digest, index: usize, leaf: &Digest, path: &[Digest]) -> bool {
+        Self::root_from_path(index, leaf, path) == Some(*root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merkle_tree() {
+        let leaves: Vec<Digest> = (0u8..8).map(|i| hash_bytes(&[i])).collect();
+        let tree: MerkleTree = MerkleTree::new(leaves.clone());
+        assert_eq!(tree.num_leaves(), 8);
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let path = tree.prove(i);
+            assert_eq!(path.len(), 3);
+            assert!(MerkleTree::<Sha256Hasher>::verify(
+                &tree.root(),
+                i,
+                leaf,
+                &path
+            ));
+        }
+
+        let path = tree.prove(3);
+        assert!(!MerkleTree::<Sha256Hasher>::verify(
+            &tree.root(),
+            2,
+            &leaves[3],
+            &path
+        ));
+        assert!(!MerkleTree::<Sha256Hasher>::verify(
+            &tree.root(),
+            3,
+            &leaves[2],
+            &path
+        ));
+
+        let root = tree.root();
+        assert_eq!(from_hex(&to_hex(&root)), Some(root));
+        assert_eq!(from_hex(&to_hex(&root).to_uppercase()), Some(root));
+        assert_eq!(from_hex(&to_hex(&root)[2..]), None);
+        assert_eq!(from_hex(&"zz".repeat(32)), None);
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree() {
+        let default = hash_bytes(b"default");
+        let mut leaves = vec![default; 16];
+        leaves[3] = hash_bytes(b"three");
+        leaves[12] = hash_bytes(b"twelve");
+        let tree: MerkleTree = MerkleTree::new(leaves.clone());
+        let mut sparse: MerkleTree =
+            MerkleTree::sparse(16, default, &[(3, leaves[3]), (12, leaves[12])]);
+        assert_eq!(sparse, tree);
+
+        // Updating a leaf matches rebuilding the tree
+        leaves[7] = hash_bytes(b"seven");
+        sparse.update(7, leaves[7]);
+        assert_eq!(sparse, MerkleTree::new(leaves.clone()));
+        assert!(MerkleTree::<Sha256Hasher>::verify(
+            &sparse.root(),
+            7,
+            &leaves[7],
+            &sparse.prove(7)
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+//! Parallel Module
+//!
+//! This module runs the data-parallel loops of the prover, on all cores with rayon when the
+//! `parallel` feature is enabled and sequentially otherwise.
+//!
+//! ## Design
+//! - Every helper computes each output from its own index or chunk only, and writes it at a fixed
+//!   place, so results do not depend on the number of threads or on how work is scheduled. A
+//!   parallel build produces the same proofs as a sequential one.
+//! - [`for_each_chunk`] splits its slice into chunks of a fixed [`CHUNK_SIZE`], which lets loops
+//!   that step a domain point by multiplication pay for one exponentiation per chunk only.
+
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of elements handled together by [`for_each_chunk`]
+pub(crate) const CHUNK_SIZE: usize = 1024;
+
+/// `items.iter().map(f).collect()`
+pub(crate) fn map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return items.iter().map(f).collect();
+}
+
+/// `(0..count).map(f).collect()`
+pub(crate) fn map_indices<U, F>(count: usize, f: F) -> Vec<U>
+where
+    U: Send,
+    F: Fn(usize) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return (0..count).into_par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return (0..count).map(f).collect();
+}
+
+/// Call `f(start, chunk)` on every chunk of [`CHUNK_SIZE`] elements of `values`, where `start` is
+/// the index of the chunk's first element
+pub(crate) fn for_each_chunk<T, F>(values: &mut [T], f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    let chunks = values.par_chunks_mut(CHUNK_SIZE);
+    #[cfg(not(feature = "parallel"))]
+    let chunks = values.chunks_mut(CHUNK_SIZE);
+    chunks
+        .enumerate()
+        .for_each(|(i, chunk)| f(i * CHUNK_SIZE, chunk));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_helpers_preserve_order() {
+        let items: Vec<usize> = (0..3 * CHUNK_SIZE + 5).collect();
+        assert_eq!(map(&items, |i| 2 * i), map_indices(items.len(), |i| 2 * i));
+
+        let mut values = vec![0; items.len()];
+        for_each_chunk(&mut values, |start, chunk| {
+            for (i, value) in chunk.iter_mut().enumerate() {
+                *value = start + i;
+            }
+        });
+        assert_eq!(values, items);
+    }
+}
@@ -0,0 +1,295 @@
+//! Poseidon Hash Module
+//!
+//! This module implements the Poseidon permutation over the Goldilocks field, an arithmetization
+//! friendly hash whose Merkle trees can later be opened inside a proof far more cheaply than
+//! SHA-256 ones.
+//!
+//! ## Design
+//! - The state has [`WIDTH`] = 12 elements: a rate of 8 and a capacity of 4. The S-box is `x^7`,
+//!   with 8 full rounds and 22 partial rounds, the instance used for Goldilocks by Plonky2.
+//! - Round constants are generated with the Grain LFSR of the Poseidon paper, seeded with the
+//!   field size, width and round numbers. The MDS matrix is the Cauchy matrix
+//!   `M[i][j] = 1 / (i + j + WIDTH)`.
+//!   Both are built on first use into a shared static, which is why the module needs the `std`
+//!   feature.
+//! - A digest is four field elements, encoded as 32 little-endian bytes. Two digests are
+//!   compressed by permuting `left || right || 0` and keeping the first four elements.
+//! - Arbitrary field elements are hashed with a sponge that absorbs 8 elements per permutation,
+//!   with the input length in the capacity for domain separation.
+//! - The sponge and the compression are generic over a [`Permutation`] of the state, so that
+//!   [`poseidon2`](crate::poseidon2) reuses them with the Poseidon2 permutation.
+
+use crate::field::{Felt, FieldElement, MODULUS};
+use crate::merkle::{Digest, Hasher};
+use std::sync::OnceLock;
+
+/// Number of state elements
+pub const WIDTH: usize = 12;
+
+/// Number of state elements absorbed per permutation
+pub const RATE: usize = 8;
+
+/// Number of field elements in a digest
+pub const DIGEST_ELEMENTS: usize = 4;
+
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 22;
+const NUM_ROUNDS: usize = FULL_ROUNDS + PARTIAL_ROUNDS;
+
+struct Parameters {
+    round_constants: Vec<[Felt; WIDTH]>,
+    mds: [[Felt; WIDTH]; WIDTH],
+}
+
+/// The Grain LFSR used by the Poseidon reference implementation to generate round constants
+pub(crate) struct Grain {
+    state: [bool; 80],
+}
+
+impl Grain {
+    /// Seed the LFSR with the instance parameters and discard the first 160 bits
+    pub(crate) fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut bits = Vec::with_capacity(80);
+        let mut push = |value: u64, count: usize| {
+            for i in (0..count).rev() {
+                bits.push(value >> i & 1 == 1);
+            }
+        };
+        push(1, 2); // prime field
+        push(0, 4); // x^alpha S-box
+        push(64, 12); // field size in bits
+        push(width as u64, 12);
+        push(full_rounds as u64, 10);
+        push(partial_rounds as u64, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut grain = Grain {
+            state: bits.try_into().unwrap(),
+        };
+        for _ in 0..160 {
+            grain.next_bit();
+        }
+        grain
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    /// Output bits are taken in pairs: the second bit is kept if the first one is set
+    fn filtered_bit(&mut self) -> bool {
+        loop {
+            let keep = self.next_bit();
+            let bit = self.next_bit();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Next field element, by rejection sampling 64-bit big-endian values
+    pub(crate) fn next_felt(&mut self) -> Felt {
+        loop {
+            let value = (0..64).fold(0u64, |acc, _| acc << 1 | self.filtered_bit() as u64);
+            if value < MODULUS {
+                return Felt::new(value);
+            }
+        }
+    }
+}
+
+fn parameters() -> &'static Parameters {
+    static PARAMETERS: OnceLock<Parameters> = OnceLock::new();
+    PARAMETERS.get_or_init(|| {
+        let mut grain = Grain::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS);
+        let round_constants = (0..NUM_ROUNDS)
+            .map(|_| std::array::from_fn(|_| grain.next_felt()))
+            .collect();
+        let mds = std::array::from_fn(|i| {
+            std::array::from_fn(|j| Felt::new((i + j + WIDTH) as u64).inverse())
+        });
+        Parameters {
+            round_constants,
+            mds,
+        }
+    })
+}
+
+fn sbox(x: Felt) -> Felt {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    x4 * x2 * x
+}
+
+/// A permutation of a [`WIDTH`]-element state
+pub trait Permutation {
+    fn permute(state: &mut [Felt; WIDTH]);
+}
+
+/// The Poseidon permutation, as a [`Permutation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Poseidon;
+
+impl Permutation for Poseidon {
+    fn permute(state: &mut [Felt; WIDTH]) {
+        permute(state)
+    }
+}
+
+/// Apply the Poseidon permutation to `state`
+pub fn permute(state: &mut [Felt; WIDTH]) {
+    let parameters = parameters();
+    let half_full = FULL_ROUNDS / 2;
+    for (round, constants) in parameters.round_constants.iter().enumerate() {
+        for (value, constant) in state.iter_mut().zip(constants) {
+            *value += *constant;
+        }
+        if round < half_full || round >= half_full + PARTIAL_ROUNDS {
+            for value in state.iter_mut() {
+                *value = sbox(*value);
+            }
+        } else {
+            state[0] = sbox(state[0]);
+        }
+        let input = *state;
+        for (value, row) in state.iter_mut().zip(&parameters.mds) {
+            *value = row
+                .iter()
+                .zip(&input)
+                .fold(Felt::ZERO, |acc, (m, x)| acc + *m * *x);
+        }
+    }
+}
+
+/// Hash a sequence of field elements with the Poseidon sponge
+pub fn hash_elements(elements: &[Felt]) -> [Felt; DIGEST_ELEMENTS] {
+    sponge_hash::<Poseidon>(elements)
+}
+
+/// Compress two digests into one
+pub fn compress(
+    left: &[Felt; DIGEST_ELEMENTS],
+    right: &[Felt; DIGEST_ELEMENTS],
+) -> [Felt; DIGEST_ELEMENTS] {
+    truncated_permutation::<Poseidon>(left, right)
+}
+
+/// Hash a sequence of field elements with the sponge over `P`
+pub(crate) fn sponge_hash<P: Permutation>(elements: &[Felt]) -> [Felt; DIGEST_ELEMENTS] {
+    let mut state = [Felt::ZERO; WIDTH];
+    state[RATE] = Felt::new(elements.len() as u64);
+    for chunk in elements.chunks(RATE) {
+        for (value, element) in state.iter_mut().zip(chunk) {
+            *value += *element;
+        }
+        P::permute(&mut state);
+    }
+    if elements.is_empty() {
+        P::permute(&mut state);
+    }
+    std::array::from_fn(|i| state[i])
+}
+
+/// Compress two digests into one by permuting `left || right || 0` with `P`
+pub(crate) fn truncated_permutation<P: Permutation>(
+    left: &[Felt; DIGEST_ELEMENTS],
+    right: &[Felt; DIGEST_ELEMENTS],
+) -> [Felt; DIGEST_ELEMENTS] {
+    let mut state = [Felt::ZERO; WIDTH];
+    state[..DIGEST_ELEMENTS].copy_from_slice(left);
+    state[DIGEST_ELEMENTS..2 * DIGEST_ELEMENTS].copy_from_slice(right);
+    P::permute(&mut state);
+    std::array::from_fn(|i| state[i])
+}
+
+/// Decode a digest into field elements; non-canonical limbs are reduced
+pub fn digest_to_elements(digest: &Digest) -> [Felt; DIGEST_ELEMENTS] {
+    std::array::from_fn(|i| {
+        Felt::new(u64::from_le_bytes(
+            digest[8 * i..8 * i + 8].try_into().unwrap(),
+        ))
+    })
+}
+
+/// Encode field elements as a digest
+pub fn elements_to_digest(elements: &[Felt; DIGEST_ELEMENTS]) -> Digest {
+    let mut bytes = Vec::with_capacity(32);
+    for element in elements {
+        element.write_bytes(&mut bytes);
+    }
+    bytes.try_into().unwrap()
+}
+
+/// Poseidon Merkle hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoseidonHasher;
+
+impl Hasher for PoseidonHasher {
+    fn merge(left: &Digest, right: &Digest) -> Digest {
+        elements_to_digest(&compress(
+            &digest_to_elements(left),
+            &digest_to_elements(right),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_constants() {
+        let parameters = parameters();
+        assert_eq!(parameters.round_constants.len(), NUM_ROUNDS);
+
+        // The generator is deterministic and produces distinct constants
+        let mut grain = Grain::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS);
+        assert_eq!(grain.next_felt(), parameters.round_constants[0][0]);
+        assert_ne!(
+            parameters.round_constants[0][0],
+            parameters.round_constants[0][1]
+        );
+
+        // The Cauchy matrix is invertible: its first column times 1 / M[0][0] is not a multiple
+        // of the second
+        let mds = &parameters.mds;
+        assert_ne!(mds[1][0] / mds[0][0], mds[1][1] / mds[0][1]);
+    }
+
+    #[test]
+    fn test_poseidon_hash() {
+        let a = hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(3)]);
+        assert_eq!(
+            a,
+            hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(3)])
+        );
+        assert_ne!(
+            a,
+            hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(4)])
+        );
+
+        // Trailing zeros change the length and therefore the hash
+        assert_ne!(
+            a,
+            hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(3), Felt::ZERO])
+        );
+        let long: Vec<Felt> = (0..20).map(Felt::new).collect();
+        assert_ne!(hash_elements(&long), hash_elements(&long[..19]));
+        assert_ne!(hash_elements(&[]), hash_elements(&[Felt::ZERO]));
+
+        let left = [Felt::new(1); DIGEST_ELEMENTS];
+        let right = [Felt::new(2); DIGEST_ELEMENTS];
+        assert_ne!(compress(&left, &right), compress(&right, &left));
+
+        let digest = elements_to_digest(&a);
+        assert_eq!(digest_to_elements(&digest), a);
+        assert_eq!(
+            PoseidonHasher::merge(&digest, &digest),
+            elements_to_digest(&compress(&a, &a))
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Poseidon2 Hash Module
+//!
+//! This module implements the Poseidon2 permutation over the Goldilocks field. Poseidon2 replaces
+//! the dense MDS matrix of [Poseidon](crate::poseidon) with cheap linear layers, which makes it
+//! faster to compute and cheaper to arithmetize, in particular inside a recursive verifier.
+//!
+//! ## Design
+//! - The instance is the Goldilocks instance of width 12 of the Poseidon2 reference
+//!   implementation (HorizenLabs/poseidon2, published as the `zkhash` crate): S-box `x^7`, 8 full
+//!   rounds and 22 partial rounds.
+//! - Round constants come from the Grain LFSR of the Poseidon paper, seeded as for Poseidon, with
+//!   12 constants drawn per full round and one per partial round. This reproduces the published
+//!   constants. The diagonal of the internal matrix is copied from the reference implementation.
+//! - Full rounds use the external matrix `circ(2 * M4, M4, M4)`, where `M4` is the 4x4 matrix of
+//!   the paper, and the permutation starts with one multiplication by it. Partial rounds use the
+//!   internal matrix `1 + diag(d)`: every element becomes `d_i * x_i + sum(x)`.
+//! - The tests check the permutation against the test vector of the reference implementation.
+//! - Digests, the sponge and the 2-to-1 compression are those of [`poseidon`](crate::poseidon)
+//!   with the Poseidon2 permutation. [`Poseidon2Hasher`] merges Merkle nodes, for instance of
+//!   memory image commitments, and
+//!   [`Poseidon2Sponge`](crate::transcript::Poseidon2Sponge) backs transcripts.
+
+use crate::field::Felt;
+use crate::merkle::{Digest, Hasher};
+use crate::poseidon::{
+    digest_to_elements, elements_to_digest, sponge_hash, truncated_permutation, Grain, Permutation,
+    DIGEST_ELEMENTS, WIDTH,
+};
+use std::sync::OnceLock;
+
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 22;
+
+/// `d` of the internal matrix `1 + diag(d)`
+const INTERNAL_DIAGONAL: [u64; WIDTH] = [
+    0xc3b6c08e23ba9300,
+    0xd84b5de94a324fb6,
+    0x0d0c371c5b35b84f,
+    0x7964f570e7188037,
+    0x5daf18bbd996604b,
+    0x6743bc47b9595257,
+    0x5528b9362c59bb70,
+    0xac45e25b7127b68b,
+    0xa2077d7dfbb606b5,
+    0xf3faac6faee378ae,
+    0x0c6388b51545e883,
+    0xd27dbb6944917b60,
+];
+
+struct RoundConstants {
+    full: Vec<[Felt; WIDTH]>,
+    partial: Vec<Felt>,
+}
+
+fn round_constants() -> &'static RoundConstants {
+    static CONSTANTS: OnceLock<RoundConstants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let mut grain = Grain::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS);
+        let full_rounds = |grain: &mut Grain| -> Vec<[Felt; WIDTH]> {
+            (0..FULL_ROUNDS / 2)
+                .map(|_| std::array::from_fn(|_| grain.next_felt()))
+                .collect()
+        };
+        let mut full = full_rounds(&mut grain);
+        let partial = (0..PARTIAL_ROUNDS).map(|_| grain.next_felt()).collect();
+        full.extend(full_rounds(&mut grain));
+        RoundConstants { full, partial }
+    })
+}
+
+fn sbox(x: Felt) -> Felt {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    x4 * x2 * x
+}
+
+/// Multiply each 4-element block by `M4 = [[5, 7, 1, 3], [4, 6, 1, 1], [1, 3, 5, 7], [1, 1, 4, 6]]`
+fn apply_m4(block: &mut [Felt]) {
+    let t0 = block[0] + block[1];
+    let t1 = block[2] + block[3];
+    let t2 = block[1] + block[1] + t1;
+    let t3 = block[3] + block[3] + t0;
+    let t4 = t1 + t1 + t1 + t1 + t3;
+    let t5 = t0 + t0 + t0 + t0 + t2;
+    block[0] = t3 + t5;
+    block[1] = t5;
+    block[2] = t2 + t4;
+    block[3] = t4;
+}
+
+/// Multiply by the external matrix `circ(2 * M4, M4, M4)`
+fn external_layer(state: &mut [Felt; WIDTH]) {
+    for block in state.chunks_mut(4) {
+        apply_m4(block);
+    }
+    let sums: [Felt; 4] = std::array::from_fn(|i| {
+        state
+            .iter()
+            .skip(i)
+            .step_by(4)
+            .fold(Felt::ZERO, |a, x| a + *x)
+    });
+    for (i, value) in state.iter_mut().enumerate() {
+        *value += sums[i % 4];
+    }
+}
+
+/// Multiply by the internal matrix `1 + diag(d)`
+fn internal_layer(state: &mut [Felt; WIDTH]) {
+    let sum = state.iter().fold(Felt::ZERO, |acc, x| acc + *x);
+    for (value, d) in state.iter_mut().zip(INTERNAL_DIAGONAL) {
+        *value = *value * Felt::new(d) + sum;
+    }
+}
+
+/// Apply the Poseidon2 permutation to `state`
+pub fn permute(state: &mut [Felt; WIDTH]) {
+    let constants = round_constants();
+    let (first, last) = constants.full.split_at(FULL_ROUNDS / 2);
+    let full_round = |state: &mut [Felt; WIDTH], round_constants: &[Felt; WIDTH]| {
+        for (value, constant) in state.iter_mut().zip(round_constants) {
+            *value = sbox(*value + *constant);
+        }
+        external_layer(state);
+    };
+
+    external_layer(state);
+    for round_constants in first {
+        full_round(state, round_constants);
+    }
+    for constant in &constants.partial {
+        state[0] = sbox(state[0] + *constant);
+        internal_layer(state);
+    }
+    for round_constants in last {
+        full_round(state, round_constants);
+    }
+}
+
+/// The Poseidon2 permutation, as a [`Permutation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Poseidon2;
+
+impl Permutation for Poseidon2 {
+    fn permute(state: &mut [Felt; WIDTH]) {
+        permute(state)
+    }
+}
+
+/// Hash a sequence of field elements with the Poseidon2 sponge
+pub fn hash_elements(elements: &[Felt]) -> [Felt; DIGEST_ELEMENTS] {
+    sponge_hash::<Poseidon2>(elements)
+}
+
+/// Compress two digests into one
+pub fn compress(
+    left: &[Felt; DIGEST_ELEMENTS],
+    right: &[Felt; DIGEST_ELEMENTS],
+) -> [Felt; DIGEST_ELEMENTS] {
+    truncated_permutation::<Poseidon2>(left, right)
+}
+
+/// Poseidon2 Merkle hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Poseidon2Hasher;
+
+impl Hasher for Poseidon2Hasher {
+    fn merge(left: &Digest, right: &Digest) -> Digest {
+        elements_to_digest(&compress(
+            &digest_to_elements(left),
+            &digest_to_elements(right),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_vector() {
+        // Published test vector of the reference implementation: the permutation of 0, 1, ..., 11
+        let mut state = std::array::from_fn(|i| Felt::new(i as u64));
+        permute(&mut state);
+        let expected = [
+            0x01eaef96bdf1c0c1,
+            0x1f0d2cc525b2540c,
+            0x6282c1dfe1e0358d,
+            0xe780d721f698e1e6,
+            0x280c0b6f753d833b,
+            0x1b942dd5023156ab,
+            0x43f0df3fcccb8398,
+            0xe8e8190585489025,
+            0x56bdbf72f77ada22,
+            0x7911c32bf9dcd705,
+            0xec467926508fbe67,
+            0x6a50450ddf85a6ed,
+        ];
+        assert_eq!(state, expected.map(Felt::new));
+
+        // The first published round constant
+        assert_eq!(round_constants().full[0][0], Felt::new(0x13dcf33aba214f46));
+    }
+
+    #[test]
+    fn test_poseidon2_hash() {
+        let a = hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(3)]);
+        assert_ne!(
+            a,
+            hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(4)])
+        );
+        assert_ne!(
+            a,
+            crate::poseidon::hash_elements(&[Felt::new(1), Felt::new(2), Felt::new(3)])
+        );
+
+        let left = [Felt::new(1); DIGEST_ELEMENTS];
+        let right = [Felt::new(2); DIGEST_ELEMENTS];
+        assert_ne!(compress(&left, &right), compress(&right, &left));
+        let digest = elements_to_digest(&a);
+        assert_eq!(
+            Poseidon2Hasher::merge(&digest, &digest),
+            elements_to_digest(&compress(&a, &a))
+        );
+    }
+}
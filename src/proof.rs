@@ -0,0 +1,577 @@
+//! Proof Module
+//!
+//! This module defines the data exchanged between the STARK prover and verifier: the public inputs
+//! of a proven run, the proof parameters and the proof itself.
+
+use crate::air::{Air, BoundaryConstraint, Chip};
+use crate::commit::{memory_root, program_id};
+#[cfg(feature = "std")]
+use crate::field::evaluate_on_coset;
+use crate::field::{evaluate_polynomial, intt, Felt, FieldElement, QuadExt, TWO_ADICITY};
+use crate::fri::{FriOptions, FriProof};
+use crate::memory::{Memory, ProgramRom};
+use crate::merkle::{hash_row, hash_salted_row, Digest};
+use crate::trace::RegisterState;
+use crate::transcript::{Sponge, Transcript};
+use alloc::{vec, vec::Vec};
+
+/// Commitment to the machine state at a segment boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateCommitment {
+    pub state: RegisterState,
+    /// Memory commitment root, see [`memory_root`]
+    pub memory_root: Digest,
+}
+
+/// Statement proven about an LC3 run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicInputs {
+    /// Merkle root of the initial memory image, see [`memory_root`]
+    pub memory_root: Digest,
+    /// Non-zero words of the initial memory image, in address order
+    pub memory_image: Vec<(u16, u16)>,
+    /// Input bytes read by `GETC` and `IN`, in order
+    pub input: Vec<u8>,
+    /// Output journal: the bytes written by `OUT`, `PUTS` and `PUTSP`, in order
+    pub output: Vec<u8>,
+    /// Register state when execution starts
+    pub initial_state: RegisterState,
+    /// Register state after `HALT`, or when the run pauses
+    pub final_state: RegisterState,
+    /// Final value of every word that is non-zero in the initial image or accessed by the run, in
+    /// address order
+    pub final_memory_image: Vec<(u16, u16)>,
+    /// Whether the run ends with `HALT`, rather than pausing at the end of a segment
+    pub halted: bool,
+    /// The program ROM instructions are fetched from, whose words are those of the memory image
+    pub rom: Option<ProgramRom>,
+}
+
+impl PublicInputs {
+    /// Program ID of the run: commitment to its initial memory and entry registers, see
+    /// [`program_id`]
+    pub fn program_id(&self) -> Digest {
+        program_id(&self.memory_root, &self.initial_state)
+    }
+
+    /// Check that the memory image matches the memory root
+    pub fn check_memory_image(&self) -> Result<(), &'static str> {
+        if memory_root(&Memory::from_image(&self.memory_image)) != self.memory_root {
+            return Err("Memory image does not match the memory root");
+        }
+        Ok(())
+    }
+
+    /// Check that the initial and final register states hold condition flags the LC3 can set,
+    /// which the AIR only constrains in its flag columns
+    pub fn check_states(&self) -> Result<(), &'static str> {
+        self.initial_state.check_cond()?;
+        self.final_state.check_cond()
+    }
+
+    /// Check that the program ROM is valid and that the run left it unchanged
+    pub fn check_rom(&self) -> Result<(), &'static str> {
+        let Some(rom) = self.rom else {
+            return Ok(());
+        };
+        rom.validate()?;
+        let in_rom = |&&(address, value): &&(u16, u16)| rom.contains(address) && value != 0;
+        let initial = self.memory_image.iter().filter(in_rom);
+        if !initial.eq(self.final_memory_image.iter().filter(in_rom)) {
+            return Err("Program ROM changed during the run");
+        }
+        Ok(())
+    }
+
+    /// Commitment to the registers and memory when the run starts
+    pub fn initial_commitment(&self) -> StateCommitment {
+        StateCommitment {
+            state: self.initial_state,
+            memory_root: self.memory_root,
+        }
+    }
+
+    /// Commitment to the registers and memory when the run halts or pauses
+    pub fn final_commitment(&self) -> StateCommitment {
+        StateCommitment {
+            state: self.final_state,
+            memory_root: memory_root(&Memory::from_image(&self.final_memory_image)),
+        }
+    }
+
+    /// Start the Fiat-Shamir transcript of a proof about these public inputs
+    pub(crate) fn transcript<S: Sponge>(
+        &self,
+        trace_length: usize,
+        options: &ProofOptions,
+    ) -> Transcript<S> {
+        let mut transcript = Transcript::new(b"lc3-zkvm");
+        transcript.absorb_digest("initial memory root", &self.memory_root);
+        for (label, state) in [
+            ("initial state", &self.initial_state),
+            ("final state", &self.final_state),
+        ] {
+            let mut values: Vec<Felt> = state.gprs.iter().map(|gpr| Felt::from(*gpr)).collect();
+            values.push(Felt::from(state.pc));
+            values.push(Felt::from(state.cond));
+            transcript.absorb_elements(label, &values);
+        }
+        transcript.absorb_digest("final memory root", &self.final_commitment().memory_root);
+        transcript.absorb_bytes("halted", &[self.halted as u8]);
+        let rom = self.rom.map_or(Vec::new(), |rom| {
+            [rom.start, rom.length]
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect()
+        });
+        transcript.absorb_bytes("program rom", &rom);
+        transcript.absorb_bytes("public input", &self.input);
+        transcript.absorb_bytes("public output", &self.output);
+        let parameters = [
+            trace_length,
+            options.blowup_factor,
+            options.num_queries,
+            options.folding_factor,
+            options.grinding_bits as usize,
+            options.zero_knowledge as usize,
+        ];
+        let mut bytes = Vec::with_capacity(8 * parameters.len());
+        for parameter in parameters {
+            bytes.extend_from_slice(&(parameter as u64).to_le_bytes());
+        }
+        transcript.absorb_bytes("proof parameters", &bytes);
+        transcript
+    }
+}
+
+/// Parameters trading proof size and proving time for soundness
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofOptions {
+    /// Ratio between the low-degree extension domain and the trace length
+    pub blowup_factor: usize,
+    /// Number of positions at which the verifier spot-checks the committed polynomials
+    pub num_queries: usize,
+    /// Factor by which each FRI round reduces the degree
+    pub folding_factor: usize,
+    /// Proof-of-work bits ground before the query positions are drawn
+    pub grinding_bits: u32,
+    /// Blind the trace polynomials, mask the composition polynomial and salt the row commitments,
+    /// so that the proof reveals nothing about the trace beyond the public inputs
+    pub zero_knowledge: bool,
+}
+
+impl ProofOptions {
+    pub fn new(
+        blowup_factor: usize,
+        num_queries: usize,
+        folding_factor: usize,
+        grinding_bits: u32,
+    ) -> Self {
+        ProofOptions {
+            blowup_factor,
+            num_queries,
+            folding_factor,
+            grinding_bits,
+            zero_knowledge: false,
+        }
+    }
+
+    /// The same options in zero-knowledge mode
+    pub fn with_zero_knowledge(mut self) -> Self {
+        self.zero_knowledge = true;
+        self
+    }
+
+    /// Number of random coefficients added to every trace polynomial.
+    ///
+    /// A trace polynomial is evaluated at `z`, at `z * g` and, through the first FRI layer, at
+    /// `folding_factor` points per query; it takes one more random coefficient than evaluations
+    /// for those to be uniformly random. Saturates at `usize::MAX` for options that fail
+    /// [`ProofOptions::validate`].
+    pub fn blinding_degree(&self) -> usize {
+        if self.zero_knowledge {
+            self.num_queries
+                .checked_mul(self.folding_factor)
+                .and_then(|degree| degree.checked_add(2))
+                .unwrap_or(usize::MAX)
+        } else {
+            0
+        }
+    }
+
+    /// Smallest trace length for which the blinded composition polynomial fits its chunks, or
+    /// `usize::MAX`, which no trace reaches, if that length does not fit in a `usize`
+    pub fn min_trace_length(&self, max_constraint_degree: usize) -> usize {
+        max_constraint_degree
+            .checked_mul(self.blinding_degree())
+            .and_then(|degree| degree.checked_add(1))
+            .and_then(usize::checked_next_power_of_two)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Check that the options describe a valid protocol instance
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if !self.blowup_factor.is_power_of_two() {
+            return Err("Blowup factor must be a power of two");
+        }
+        self.fri_options().validate()
+    }
+
+    /// Size of the low-degree extension domain of a trace of `trace_length` rows, if it fits in
+    /// the two-adic subgroup of the field
+    pub fn lde_domain_size(&self, trace_length: usize) -> Option<usize> {
+        trace_length
+            .checked_mul(self.blowup_factor)
+            .filter(|&size| size as u64 <= 1 << TWO_ADICITY)
+    }
+
+    /// Number of composition chunks: blinding raises the composition degree by one chunk
+    pub fn num_composition_chunks<A: Air>(&self, air: &A) -> usize {
+        air.num_composition_chunks() + self.zero_knowledge as usize
+    }
+
+    /// Number of committed composition columns: the chunks, followed in zero-knowledge mode by a
+    /// random column masking the DEEP composition polynomial
+    pub fn num_composition_columns<A: Air>(&self, air: &A) -> usize {
+        self.num_composition_chunks(air) + self.zero_knowledge as usize
+    }
+
+    /// FRI parameters. Blinded trace polynomials have degree below `2n`, so in zero-knowledge mode
+    /// FRI proves that bound, with half the blowup factor.
+    pub fn fri_options(&self) -> FriOptions {
+        let blowup_factor = if self.zero_knowledge {
+            self.blowup_factor / 2
+        } else {
+            self.blowup_factor
+        };
+        FriOptions {
+            blowup_factor,
+            folding_factor: self.folding_factor,
+            num_queries: self.num_queries,
+            grinding_bits: self.grinding_bits,
+            max_remainder_degree: 8,
+        }
+    }
+
+    /// Conjectured security level in bits of a proof over a trace of `trace_length` rows. Every
+    /// challenge of the proof is drawn from the quadratic extension.
+    pub fn conjectured_security(&self, trace_length: usize) -> u32 {
+        self.fri_options()
+            .conjectured_security::<QuadExt>(trace_length * self.blowup_factor)
+    }
+}
+
+impl Default for ProofOptions {
+    fn default() -> Self {
+        ProofOptions::new(8, 32, 4, 16)
+    }
+}
+
+/// Opening of one row of a committed table, with its Merkle authentication path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowOpening<E> {
+    pub values: Vec<E>,
+    pub path: Vec<Digest>,
+    /// Salt of the row's leaf, in zero-knowledge mode
+    pub salt: Option<Digest>,
+}
+
+impl<E: FieldElement> RowOpening<E> {
+    /// Merkle leaf of the row
+    pub fn leaf(&self) -> Digest {
+        match &self.salt {
+            Some(salt) => hash_salted_row(&self.values, salt),
+            None => hash_row(&self.values),
+        }
+    }
+}
+
+/// Evaluations of the trace and composition polynomials at the out-of-domain point `z`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OodFrame {
+    /// Main and auxiliary trace polynomials at `z`
+    pub current: Vec<QuadExt>,
+    /// Main and auxiliary trace polynomials at `z * g`, where `g` generates the trace domain
+    pub next: Vec<QuadExt>,
+    /// Composition columns at `z`: the chunks, then the random column in zero-knowledge mode
+    pub composition: Vec<QuadExt>,
+}
+
+impl OodFrame {
+    pub(crate) fn absorb_into<S: Sponge>(&self, transcript: &mut Transcript<S>) {
+        transcript.absorb_elements("ood trace at z", &self.current);
+        transcript.absorb_elements("ood trace at z * g", &self.next);
+        transcript.absorb_elements("ood composition", &self.composition);
+    }
+}
+
+/// A STARK proof of a trace table. The proof of an LC3 run also holds the proofs of the chip
+/// tables connected to the LC3 table by the bus.
+///
+/// The main columns are base field elements. Everything derived from the challenges, which are
+/// drawn from the quadratic extension, is made of extension elements: the auxiliary columns, the
+/// composition and DEEP polynomials and their out-of-domain values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub options: ProofOptions,
+    /// Number of rows of the trace table
+    pub trace_length: usize,
+    pub trace_root: Digest,
+    /// Commitment to the auxiliary columns, if the AIR has any
+    pub aux_root: Option<Digest>,
+    /// Values of the bus columns on the last row
+    pub bus_sums: Vec<QuadExt>,
+    pub composition_root: Digest,
+    pub ood_frame: OodFrame,
+    pub trace_openings: Vec<RowOpening<Felt>>,
+    pub aux_openings: Vec<RowOpening<QuadExt>>,
+    pub composition_openings: Vec<RowOpening<QuadExt>>,
+    pub fri_proof: FriProof<QuadExt>,
+    /// Proofs of the chip tables, in chip order
+    pub chips: Vec<ChipProof>,
+}
+
+/// Proof of a chip table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipProof {
+    pub chip: Chip,
+    pub proof: Proof,
+}
+
+/// Absorb the chip and the length of its trace table, before its trace root
+pub(crate) fn absorb_chip<S: Sponge>(
+    transcript: &mut Transcript<S>,
+    chip: Chip,
+    trace_length: usize,
+) {
+    let mut bytes = vec![chip.id()];
+    bytes.extend_from_slice(&(trace_length as u64).to_le_bytes());
+    transcript.absorb_bytes("chip", &bytes);
+}
+
+/// Sum of the bus claims of the tables of a proof, zero if the bus balances
+pub(crate) fn bus_balance<'a>(bus_sums: impl IntoIterator<Item = &'a Vec<QuadExt>>) -> QuadExt {
+    bus_sums
+        .into_iter()
+        .flatten()
+        .fold(QuadExt::ZERO, |acc, sum| acc + *sum)
+}
+
+/// The boundary constraints of `air`, and the constraints pinning its bus columns to their claimed
+/// values on the last row
+pub(crate) fn boundary_constraints<A: Air>(
+    air: &A,
+    challenges: &[QuadExt],
+    bus_sums: &[QuadExt],
+) -> Vec<BoundaryConstraint> {
+    let last = air.trace_length() - 1;
+    let mut constraints = air.boundary_constraints(challenges);
+    for (&column, &value) in air.bus_columns().iter().zip(bus_sums) {
+        constraints.push(BoundaryConstraint {
+            column,
+            row: last,
+            value,
+        });
+    }
+    constraints
+}
+
+/// The periodic columns of an AIR, as polynomials in `x^(n / period)` for trace length `n`
+pub(crate) struct PeriodicColumns {
+    trace_length: usize,
+    polynomials: Vec<Vec<Felt>>,
+}
+
+impl PeriodicColumns {
+    pub(crate) fn new<A: Air>(air: &A) -> Result<Self, &'static str> {
+        let trace_length = air.trace_length();
+        let polynomials = air
+            .periodic_columns()
+            .into_iter()
+            .map(|mut values| {
+                if !values.len().is_power_of_two() || values.len() > trace_length {
+                    return Err("Period does not divide the trace length");
+                }
+                intt(&mut values);
+                Ok(values)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(PeriodicColumns {
+            trace_length,
+            polynomials,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn len(&self) -> usize {
+        self.polynomials.len()
+    }
+
+    fn exponent(&self, polynomial: &[Felt]) -> u64 {
+        (self.trace_length / polynomial.len()) as u64
+    }
+
+    /// Values of the columns at `x`
+    pub(crate) fn evaluate_at<E: FieldElement>(&self, x: E) -> Vec<E> {
+        self.polynomials
+            .iter()
+            .map(|poly| evaluate_polynomial(poly, x.pow(self.exponent(poly))))
+            .collect()
+    }
+
+    /// Values of the columns over the LDE domain `offset * <w>` with the given blowup factor.
+    /// Column `k` repeats with period `lde[k].len()`: its value at `offset * w^i` is
+    /// `lde[k][i % lde[k].len()]`.
+    #[cfg(feature = "std")]
+    pub(crate) fn evaluate_on_lde(&self, offset: Felt, blowup_factor: usize) -> Vec<Vec<Felt>> {
+        self.polynomials
+            .iter()
+            .map(|poly| {
+                evaluate_on_coset(
+                    poly,
+                    offset.pow(self.exponent(poly)),
+                    poly.len() * blowup_factor,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Random coefficients combining all constraint quotients into the composition polynomial
+pub(crate) struct ConstraintCoefficients {
+    transition: Vec<QuadExt>,
+    boundary: Vec<QuadExt>,
+}
+
+impl ConstraintCoefficients {
+    pub(crate) fn draw<A: Air, S: Sponge>(
+        transcript: &mut Transcript<S>,
+        air: &A,
+        boundary_constraints: &[BoundaryConstraint],
+    ) -> Self {
+        let transition = (0..air.num_transition_constraints())
+            .map(|_| transcript.challenge("transition coefficient"))
+            .collect();
+        let boundary = (0..boundary_constraints.len())
+            .map(|_| transcript.challenge("boundary coefficient"))
+            .collect();
+        ConstraintCoefficients {
+            transition,
+            boundary,
+        }
+    }
+
+    /// Evaluate the composition polynomial at `x` from the trace values at `x` and `x * g`
+    pub(crate) fn evaluate<A: Air>(
+        &self,
+        air: &A,
+        boundary_constraints: &[BoundaryConstraint],
+        challenges: &[QuadExt],
+        x: QuadExt,
+        current: &[QuadExt],
+        next: &[QuadExt],
+    ) -> QuadExt {
+        let n = air.trace_length();
+        let g = Felt::two_adic_root(n.trailing_zeros());
+
+        let mut evaluations = vec![QuadExt::ZERO; self.transition.len()];
+        air.evaluate_transition(current, next, challenges, &mut evaluations);
+        let transition = evaluations
+            .iter()
+            .zip(&self.transition)
+            .fold(QuadExt::ZERO, |acc, (value, coefficient)| {
+                acc + *value * *coefficient
+            });
+        // Transition constraints hold on every row except the last
+        let g_last = QuadExt::from(g.pow(n as u64 - 1));
+        let mut result = transition * (x - g_last) / (x.pow(n as u64) - QuadExt::ONE);
+
+        // Boundary quotients sharing a row share a denominator
+        let mut rows: Vec<usize> = boundary_constraints.iter().map(|c| c.row).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        for row in rows {
+            let numerator = boundary_constraints
+                .iter()
+                .zip(&self.boundary)
+                .filter(|(constraint, _)| constraint.row == row)
+                .fold(QuadExt::ZERO, |acc, (constraint, coefficient)| {
+                    acc + (current[constraint.column] - constraint.value) * *coefficient
+                });
+            result += numerator / (x - QuadExt::from(g.pow(row as u64)));
+        }
+        result
+    }
+}
+
+/// Draw the out-of-domain point from the quadratic extension, rejecting points of the trace and
+/// LDE domains
+pub(crate) fn draw_ood_point<S: Sponge>(
+    transcript: &mut Transcript<S>,
+    n: usize,
+    lde_size: usize,
+) -> QuadExt {
+    let offset_power = QuadExt::from(Felt::GENERATOR.pow(lde_size as u64));
+    loop {
+        let z: QuadExt = transcript.challenge("ood point");
+        if z.pow(n as u64) != QuadExt::ONE && z.pow(lde_size as u64) != offset_power {
+            return z;
+        }
+    }
+}
+
+/// Random coefficients of the DEEP composition polynomial
+pub(crate) struct DeepCoefficients {
+    /// Coefficients of `(T(x) - T(z)) / (x - z)` per trace column
+    current: Vec<QuadExt>,
+    /// Coefficients of `(T(x) - T(z * g)) / (x - z * g)` per trace column
+    next: Vec<QuadExt>,
+    /// Coefficients of `(H(x) - H(z)) / (x - z)` per composition column
+    composition: Vec<QuadExt>,
+}
+
+impl DeepCoefficients {
+    pub(crate) fn draw<S: Sponge>(
+        transcript: &mut Transcript<S>,
+        width: usize,
+        num_composition_columns: usize,
+    ) -> Self {
+        let mut draw = |label: &'static str, count: usize| -> Vec<QuadExt> {
+            (0..count).map(|_| transcript.challenge(label)).collect()
+        };
+        let current = draw("deep coefficient at z", width);
+        let next = draw("deep coefficient at z * g", width);
+        let composition = draw("deep composition coefficient", num_composition_columns);
+        DeepCoefficients {
+            current,
+            next,
+            composition,
+        }
+    }
+
+    /// Evaluate the DEEP composition polynomial at `x` from the committed values at `x`, with the
+    /// main trace values embedded into the extension
+    pub(crate) fn evaluate(
+        &self,
+        x: Felt,
+        z: QuadExt,
+        g: Felt,
+        trace_row: &[QuadExt],
+        composition_row: &[QuadExt],
+        ood_frame: &OodFrame,
+    ) -> QuadExt {
+        let x = QuadExt::from(x);
+        let x_minus_z_inv = (x - z).inverse();
+        let x_minus_zg_inv = (x - z.mul_base(g)).inverse();
+
+        let mut result = QuadExt::ZERO;
+        for (i, value) in trace_row.iter().enumerate() {
+            result += self.current[i] * (*value - ood_frame.current[i]) * x_minus_z_inv;
+            result += self.next[i] * (*value - ood_frame.next[i]) * x_minus_zg_inv;
+        }
+        for (i, value) in composition_row.iter().enumerate() {
+            result += self.composition[i] * (*value - ood_frame.composition[i]) * x_minus_z_inv;
+        }
+        result
+    }
+}
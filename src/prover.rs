@@ -0,0 +1,906 @@
+//! STARK Prover Module
+//!
+//! This module proves that an LC3 execution trace satisfies the [`Lc3Air`] constraints, producing a
+//! [`Proof`] that [`verifier::verify`](crate::verifier::verify) checks against the public inputs.
+//!
+//! ## Protocol
+//! 1. Interpolate every trace column over the trace domain `<g>` of size `n`, evaluate it over the
+//!    low-degree extension (LDE) domain `offset * <w>` of size `n * blowup_factor`, and commit to
+//!    the LDE rows in a Merkle tree. Then draw the auxiliary challenges, build the auxiliary
+//!    columns and commit to them the same way. In zero-knowledge mode, every column polynomial
+//!    `t` is first blinded as `t + Z_H * r`, where `Z_H = x^n - 1` vanishes on the trace domain
+//!    and `r` is random of degree below [`ProofOptions::blinding_degree`], and every Merkle leaf
+//!    is salted with fresh randomness.
+//! 2. Combine all constraint quotients with random coefficients into the composition polynomial,
+//!    split it into chunks of degree `< n` and commit to their LDE rows. In zero-knowledge mode,
+//!    the chunks are masked without changing the polynomial they add up to, and a random column
+//!    is committed with them to mask the DEEP composition polynomial and the FRI layers.
+//! 3. Evaluate the trace polynomials at a random out-of-domain point `z` and at `z * g`, and the
+//!    composition columns at `z`.
+//! 4. Build the DEEP composition polynomial, which is low-degree only if the claimed out-of-domain
+//!    evaluations are correct, and prove its low degree with FRI, folding by the configured
+//!    folding factor and grinding the configured proof-of-work.
+//! 5. Open the committed rows at the FRI query positions.
+//!
+//! A run also has a table for every [`Chip`] it uses: the [memory chip](crate::air::memory)
+//! always, the [ALU chip](crate::air::alu) if it runs `ADD`, `AND` or `NOT`, the
+//! [I/O chip](crate::air::io) if it runs `GETC`, `OUT`, `PUTS`, `IN` or `PUTSP`, the
+//! [branch chip](crate::air::branch) if it runs `BR`, `JMP` or `JSR`, and the
+//! [SHA-256 chip](crate::air::sha256) if it hashes with `TRAP x27`. The main columns of all tables
+//! are committed before the auxiliary challenges are drawn, so that the tables share the
+//! challenges of the [bus](crate::air::bus); each table then goes through steps 2 to 5 on its
+//! own, and the chip proofs are attached to the LC3 proof in [`Chip`] order.
+//!
+//! All challenges are derived with the Fiat-Shamir [`Transcript`]. Except for the query positions,
+//! they are drawn from the quadratic extension [`QuadExt`]: drawn from the 64-bit base field, they
+//! would cap the security of every proof below 64 bits.
+
+use crate::air::{
+    build_padded_trace_table, bus, Air, Chip, Lc3Air, TraceTable, MAX_CONSTRAINT_DEGREE,
+};
+use crate::commit::memory_root;
+use crate::field::{
+    evaluate_on_coset, evaluate_polynomial, interpolate_coset, intt, Felt, FieldElement, QuadExt,
+    MODULUS,
+};
+use crate::fri::FriProver;
+use crate::memory::Memory;
+use crate::merkle::{hash_row, hash_salted_row, Digest, MerkleTree};
+use crate::parallel;
+use crate::proof::{
+    absorb_chip, boundary_constraints, bus_balance, draw_ood_point, ChipProof,
+    ConstraintCoefficients, DeepCoefficients, OodFrame, PeriodicColumns, Proof, ProofOptions,
+    PublicInputs, RowOpening,
+};
+use crate::trace::ExecutionTrace;
+use crate::transcript::{Sponge, Transcript};
+
+/// Prove an LC3 run that started from `initial_memory` and produced `trace`
+pub fn prove(
+    initial_memory: &Memory,
+    trace: &ExecutionTrace,
+    options: &ProofOptions,
+) -> Result<(PublicInputs, Proof), &'static str> {
+    options.validate()?;
+    let public_inputs = PublicInputs {
+        memory_root: memory_root(initial_memory),
+        memory_image: initial_memory.image(),
+        input: trace.input.clone(),
+        output: trace.output.clone(),
+        initial_state: trace.initial_state().ok_or("Empty execution trace")?,
+        final_state: trace.final_state().ok_or("Empty execution trace")?,
+        final_memory_image: trace.final_memory_image(&initial_memory.image()),
+        halted: trace.halted,
+        rom: trace.rom,
+    };
+
+    let table = build_padded_trace_table(trace, options.min_trace_length(MAX_CONSTRAINT_DEGREE))?;
+    let mut transcript: Transcript = public_inputs.transcript(table.length(), options);
+    let proof = prove_tables(&public_inputs, trace, &table, options, &mut transcript)?;
+    Ok((public_inputs, proof))
+}
+
+/// Prove that the LC3 table of a run and the tables of its chips satisfy their AIRs and balance
+/// on the bus.
+///
+/// The transcript must already have absorbed the public inputs.
+pub(crate) fn prove_tables<S: Sponge>(
+    public_inputs: &PublicInputs,
+    trace: &ExecutionTrace,
+    table: &TraceTable,
+    options: &ProofOptions,
+    transcript: &mut Transcript<S>,
+) -> Result<Proof, &'static str> {
+    let mut chip_tables = Vec::new();
+    let hiding = options.zero_knowledge.then_some(table.length());
+    for chip in Chip::ALL {
+        let min_length = options.min_trace_length(chip.max_constraint_degree());
+        let chip_table = chip.build_trace_table(trace, table, public_inputs, min_length, hiding)?;
+        if let Some(chip_table) = chip_table {
+            let chip_air = chip.air(chip_table.length(), public_inputs);
+            chip_tables.push((chip, chip_air, chip_table));
+        }
+    }
+    let air = Lc3Air::new(table.length(), public_inputs.clone());
+
+    // All tables are committed before the shared challenges are drawn
+    let mut lc3 = TableProver::commit(&air, table, options, transcript)?;
+    let mut chips = Vec::with_capacity(chip_tables.len());
+    for (chip, chip_air, chip_table) in &chip_tables {
+        absorb_chip(transcript, *chip, chip_table.length());
+        let prover = TableProver::commit(chip_air, chip_table, options, transcript)?;
+        chips.push((*chip, prover));
+    }
+    let challenges = draw_aux_challenges(transcript, bus::SHARED_CHALLENGES);
+    lc3.commit_aux(&challenges, transcript)?;
+    for (_, prover) in &mut chips {
+        prover.commit_aux(&challenges, transcript)?;
+    }
+    let bus_sums = core::iter::once(&lc3.bus_sums).chain(chips.iter().map(|(_, p)| &p.bus_sums));
+    if bus_balance(bus_sums) + bus::public_sum(public_inputs, &challenges) != QuadExt::ZERO {
+        return Err("Bus messages do not balance");
+    }
+    let mut proof = lc3.prove(transcript)?;
+    for (chip, prover) in chips {
+        proof.chips.push(ChipProof {
+            chip,
+            proof: prover.prove(transcript)?,
+        });
+    }
+    Ok(proof)
+}
+
+/// A committed set of polynomials: coefficients, LDE and the Merkle tree over the LDE rows, with
+/// the salts of the rows if the commitment is hiding
+struct CommittedPolynomials<E> {
+    coefficients: Vec<Vec<E>>,
+    lde: Vec<Vec<E>>,
+    salts: Option<Vec<Digest>>,
+    tree: MerkleTree,
+}
+
+impl<E: FieldElement> CommittedPolynomials<E> {
+    fn commit(
+        coefficients: Vec<Vec<E>>,
+        lde_size: usize,
+        options: &ProofOptions,
+    ) -> Result<Self, &'static str> {
+        let lde: Vec<Vec<E>> = parallel::map(&coefficients, |poly| {
+            evaluate_on_coset(poly, Felt::GENERATOR, lde_size)
+        });
+        let salts = if options.zero_knowledge {
+            Some(random_digests(lde_size)?)
+        } else {
+            None
+        };
+        let leaves = parallel::map_indices(lde_size, |i| {
+            let row: Vec<E> = lde.iter().map(|column| column[i]).collect();
+            match &salts {
+                Some(salts) => hash_salted_row(&row, &salts[i]),
+                None => hash_row(&row),
+            }
+        });
+        Ok(CommittedPolynomials {
+            coefficients,
+            lde,
+            salts,
+            tree: MerkleTree::new(leaves),
+        })
+    }
+
+    fn open(&self, position: usize) -> RowOpening<E> {
+        RowOpening {
+            values: self.lde.iter().map(|column| column[position]).collect(),
+            path: self.tree.prove(position),
+            salt: self.salts.as_ref().map(|salts| salts[position]),
+        }
+    }
+
+    /// Values of the polynomials at a point of the extension
+    fn evaluate_at(&self, x: QuadExt) -> Vec<QuadExt>
+    where
+        QuadExt: From<E>,
+    {
+        self.coefficients
+            .iter()
+            .map(|poly| evaluate_polynomial(poly, x))
+            .collect()
+    }
+}
+
+/// Interpolate trace columns over the trace domain
+fn interpolate_columns<E: FieldElement>(columns: &[Vec<E>]) -> Vec<Vec<E>> {
+    parallel::map(columns, |column| {
+        let mut coefficients = column.clone();
+        intt(&mut coefficients);
+        coefficients
+    })
+}
+
+/// Uniformly random field elements from the operating system
+fn random_elements<E: FieldElement>(count: usize) -> Result<Vec<E>, &'static str> {
+    let mut felts = Vec::with_capacity(count * E::EXTENSION_DEGREE);
+    let mut bytes = [0; 8];
+    while felts.len() < count * E::EXTENSION_DEGREE {
+        getrandom::getrandom(&mut bytes).map_err(|_| "Failed to generate randomness")?;
+        let value = u64::from_le_bytes(bytes);
+        if value < MODULUS {
+            felts.push(Felt::new(value));
+        }
+    }
+    Ok(felts
+        .chunks_exact(E::EXTENSION_DEGREE)
+        .map(E::from_base_elements)
+        .collect())
+}
+
+/// Uniformly random digests from the operating system
+fn random_digests(count: usize) -> Result<Vec<Digest>, &'static str> {
+    let mut bytes = vec![0; count * 32];
+    getrandom::getrandom(&mut bytes).map_err(|_| "Failed to generate randomness")?;
+    Ok(bytes
+        .chunks_exact(32)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect())
+}
+
+/// Add `(x^n - 1) * r(x)` to every polynomial, with a fresh random `r` of degree `< degree`
+fn blind_columns<E: FieldElement>(
+    columns: &mut [Vec<E>],
+    n: usize,
+    degree: usize,
+) -> Result<(), &'static str> {
+    for coefficients in columns.iter_mut() {
+        let randomness = random_elements(degree)?;
+        coefficients.resize(n + degree, E::ZERO);
+        for (j, r) in randomness.into_iter().enumerate() {
+            coefficients[j] -= r;
+            coefficients[n + j] += r;
+        }
+    }
+    Ok(())
+}
+
+/// Mask the composition chunks of degree `< n` and append a random column of degree `< 2n`.
+///
+/// Chunk `i` becomes `H_i + x^n * r_(i+1) - r_i`, for random `r_i` of degree `< n` with `r_0` and
+/// `r_m` zero, so that `sum(x^(i * n) * H_i)` is unchanged. Blinded trace polynomials already
+/// have degree below `2n`, the bound FRI proves in zero-knowledge mode.
+fn mask_composition<E: FieldElement>(
+    chunks: &mut Vec<Vec<E>>,
+    n: usize,
+) -> Result<(), &'static str> {
+    for chunk in chunks.iter_mut() {
+        chunk.resize(2 * n, E::ZERO);
+    }
+    for i in 1..chunks.len() {
+        for (j, r) in random_elements::<E>(n)?.into_iter().enumerate() {
+            chunks[i - 1][n + j] += r;
+            chunks[i][j] -= r;
+        }
+    }
+    chunks.push(random_elements(2 * n)?);
+    Ok(())
+}
+
+/// Interpolate trace columns over the trace domain and blind them as the options require
+fn trace_polynomials<E: FieldElement>(
+    columns: &[Vec<E>],
+    options: &ProofOptions,
+) -> Result<Vec<Vec<E>>, &'static str> {
+    let mut polynomials = interpolate_columns(columns);
+    if options.zero_knowledge {
+        let n = columns.first().map_or(0, Vec::len);
+        blind_columns(&mut polynomials, n, options.blinding_degree())?;
+    }
+    Ok(polynomials)
+}
+
+/// Prove that `table` satisfies `air`.
+///
+/// The transcript must already have absorbed the public inputs.
+pub fn prove_air<A: Air, S: Sponge>(
+    air: &A,
+    table: &TraceTable,
+    options: &ProofOptions,
+    transcript: &mut Transcript<S>,
+) -> Result<Proof, &'static str> {
+    let mut prover = TableProver::commit(air, table, options, transcript)?;
+    let challenges = draw_aux_challenges(transcript, air.num_aux_challenges());
+    prover.commit_aux(&challenges, transcript)?;
+    if bus_balance([&prover.bus_sums]) != QuadExt::ZERO {
+        return Err("Bus messages do not balance");
+    }
+    prover.prove(transcript)
+}
+
+/// Draw the auxiliary challenges from the quadratic extension, shared by all tables of a proof
+fn draw_aux_challenges<S: Sponge>(transcript: &mut Transcript<S>, count: usize) -> Vec<QuadExt> {
+    (0..count)
+        .map(|_| transcript.challenge("aux challenge"))
+        .collect()
+}
+
+/// A table on its way to a [`Proof`]. Proving a table takes three steps, so that the tables of a
+/// proof can share the auxiliary challenges: [`TableProver::commit`] commits to the main columns,
+/// [`TableProver::commit_aux`] to the auxiliary columns built from the challenges drawn after all
+/// main commitments, and [`TableProver::prove`] runs the rest of the protocol.
+struct TableProver<'a, A> {
+    air: &'a A,
+    table: &'a TraceTable,
+    options: &'a ProofOptions,
+    trace: CommittedPolynomials<Felt>,
+    aux: Option<CommittedPolynomials<QuadExt>>,
+    challenges: Vec<QuadExt>,
+    bus_sums: Vec<QuadExt>,
+}
+
+impl<'a, A: Air> TableProver<'a, A> {
+    /// Commit to the main columns
+    fn commit<S: Sponge>(
+        air: &'a A,
+        table: &'a TraceTable,
+        options: &'a ProofOptions,
+        transcript: &mut Transcript<S>,
+    ) -> Result<Self, &'static str> {
+        let n = air.trace_length();
+        if table.length() != n || table.width() != air.trace_width() {
+            return Err("Trace table does not match the AIR");
+        }
+        options.validate()?;
+        if options.lde_domain_size(n).is_none() {
+            return Err("Low-degree extension domain too large");
+        }
+        if options.blowup_factor < air.max_constraint_degree() + options.zero_knowledge as usize {
+            return Err("Blowup factor is smaller than the constraint degree");
+        }
+        if n < options.min_trace_length(air.max_constraint_degree()) {
+            return Err("Trace too short for zero-knowledge");
+        }
+
+        let trace = CommittedPolynomials::commit(
+            trace_polynomials(&table.columns, options)?,
+            n * options.blowup_factor,
+            options,
+        )?;
+        transcript.absorb_digest("trace root", &trace.tree.root());
+        Ok(TableProver {
+            air,
+            table,
+            options,
+            trace,
+            aux: None,
+            challenges: Vec::new(),
+            bus_sums: Vec::new(),
+        })
+    }
+
+    /// Build the auxiliary columns from the challenges, commit to them and claim the bus sums
+    fn commit_aux<S: Sponge>(
+        &mut self,
+        challenges: &[QuadExt],
+        transcript: &mut Transcript<S>,
+    ) -> Result<(), &'static str> {
+        let air = self.air;
+        self.challenges = challenges[..air.num_aux_challenges()].to_vec();
+        let aux_columns = air.build_aux_columns(self.table, &self.challenges);
+        if aux_columns.len() != air.aux_width() {
+            return Err("Auxiliary columns do not match the AIR");
+        }
+        if aux_columns.is_empty() {
+            return Ok(());
+        }
+        let aux = CommittedPolynomials::commit(
+            trace_polynomials(&aux_columns, self.options)?,
+            air.trace_length() * self.options.blowup_factor,
+            self.options,
+        )?;
+        transcript.absorb_digest("aux root", &aux.tree.root());
+        self.aux = Some(aux);
+
+        let last = air.trace_length() - 1;
+        self.bus_sums = air
+            .bus_columns()
+            .iter()
+            .map(|&column| aux_columns[column - air.trace_width()][last])
+            .collect();
+        if !self.bus_sums.is_empty() {
+            transcript.absorb_elements("bus sums", &self.bus_sums);
+        }
+        Ok(())
+    }
+
+    /// Commit to the composition polynomial, evaluate out of domain, run FRI and open the queries
+    fn prove<S: Sponge>(self, transcript: &mut Transcript<S>) -> Result<Proof, &'static str> {
+        let TableProver {
+            air,
+            options,
+            trace,
+            aux,
+            challenges,
+            bus_sums,
+            ..
+        } = self;
+        let n = air.trace_length();
+        let blowup = options.blowup_factor;
+        let lde_size = n * blowup;
+        let main_width = air.trace_width();
+        let width = main_width + air.aux_width();
+
+        // 2. Commit to the composition polynomial
+        let boundary_constraints = boundary_constraints(air, &challenges, &bus_sums);
+        let constraint_coefficients =
+            ConstraintCoefficients::draw(transcript, air, &boundary_constraints);
+        let lde_root = Felt::two_adic_root(lde_size.trailing_zeros());
+        let offset = Felt::GENERATOR;
+
+        // The main columns are embedded into the extension, next to the auxiliary columns
+        let aux_ldes: &[Vec<QuadExt>] = aux.as_ref().map_or(&[], |aux| &aux.lde);
+        let read_row = |i: usize, row: &mut [QuadExt]| {
+            for (cell, lde) in row.iter_mut().zip(&trace.lde) {
+                *cell = QuadExt::from(lde[i]);
+            }
+            for (cell, lde) in row[main_width..].iter_mut().zip(aux_ldes) {
+                *cell = lde[i];
+            }
+        };
+        let periodic = PeriodicColumns::new(air)?;
+        let periodic_ldes = periodic.evaluate_on_lde(offset, blowup);
+        let mut composition = vec![QuadExt::ZERO; lde_size];
+        parallel::for_each_chunk(&mut composition, |start, chunk| {
+            let mut current = vec![QuadExt::ZERO; width + periodic.len()];
+            let mut next = current.clone();
+            let mut x = offset * lde_root.pow(start as u64);
+            for (i, value) in (start..).zip(chunk) {
+                read_row(i, &mut current);
+                read_row((i + blowup) % lde_size, &mut next);
+                for (column, lde) in periodic_ldes.iter().enumerate() {
+                    current[width + column] = QuadExt::from(lde[i % lde.len()]);
+                    next[width + column] = QuadExt::from(lde[(i + blowup) % lde.len()]);
+                }
+                *value = constraint_coefficients.evaluate(
+                    air,
+                    &boundary_constraints,
+                    &challenges,
+                    QuadExt::from(x),
+                    &current,
+                    &next,
+                );
+                x *= lde_root;
+            }
+        });
+
+        let num_chunks = options.num_composition_chunks(air);
+        let composition_coefficients = interpolate_coset(&composition, offset);
+        if composition_coefficients[num_chunks * n..]
+            .iter()
+            .any(|c| *c != QuadExt::ZERO)
+        {
+            return Err("Trace does not satisfy the AIR constraints");
+        }
+        let mut chunks = composition_coefficients[..num_chunks * n]
+            .chunks(n)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        if options.zero_knowledge {
+            mask_composition(&mut chunks, n)?;
+        }
+        let composition = CommittedPolynomials::commit(chunks, lde_size, options)?;
+        transcript.absorb_digest("composition root", &composition.tree.root());
+
+        // 3. Out-of-domain evaluations
+        let z = draw_ood_point(transcript, n, lde_size);
+        let g = Felt::two_adic_root(n.trailing_zeros());
+        let zg = z.mul_base(g);
+        let aux_at = |x: QuadExt| aux.as_ref().map_or(Vec::new(), |aux| aux.evaluate_at(x));
+        let ood_frame = OodFrame {
+            current: [trace.evaluate_at(z), aux_at(z)].concat(),
+            next: [trace.evaluate_at(zg), aux_at(zg)].concat(),
+            composition: composition.evaluate_at(z),
+        };
+        ood_frame.absorb_into(transcript);
+
+        // 4. DEEP composition and FRI
+        let deep_coefficients =
+            DeepCoefficients::draw(transcript, width, options.num_composition_columns(air));
+        let mut deep = vec![QuadExt::ZERO; lde_size];
+        parallel::for_each_chunk(&mut deep, |start, chunk| {
+            let mut trace_row = vec![QuadExt::ZERO; width];
+            let mut composition_row = vec![QuadExt::ZERO; composition.lde.len()];
+            let mut x = offset * lde_root.pow(start as u64);
+            for (i, value) in (start..).zip(chunk) {
+                read_row(i, &mut trace_row);
+                for (cell, lde) in composition_row.iter_mut().zip(&composition.lde) {
+                    *cell = lde[i];
+                }
+                *value =
+                    deep_coefficients.evaluate(x, z, g, &trace_row, &composition_row, &ood_frame);
+                x *= lde_root;
+            }
+        });
+
+        let fri_options = options.fri_options();
+        let fri_prover = FriProver::commit(deep, offset, &fri_options, transcript);
+
+        // 5. Queries
+        let positions =
+            transcript.challenge_indices("query positions", options.num_queries, lde_size);
+        let fri_proof = fri_prover.prove(&positions);
+
+        Ok(Proof {
+            options: *options,
+            trace_length: n,
+            trace_root: trace.tree.root(),
+            aux_root: aux.as_ref().map(|aux| aux.tree.root()),
+            bus_sums,
+            composition_root: composition.tree.root(),
+            ood_frame,
+            trace_openings: positions.iter().map(|&p| trace.open(p)).collect(),
+            aux_openings: aux.as_ref().map_or(Vec::new(), |aux| {
+                positions.iter().map(|&p| aux.open(p)).collect()
+            }),
+            composition_openings: positions.iter().map(|&p| composition.open(p)).collect(),
+            fri_proof,
+            chips: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::air::{bitwise, MIN_TRACE_LENGTH};
+    use crate::io::IoTape;
+    use crate::memory::ProgramRom;
+    use crate::receipt::Receipt;
+    use crate::register::{Register, RegisterFile};
+    use crate::transcript::{PoseidonSponge, Sha256Sponge};
+    use crate::utils::{execute_program, execute_program_with_io, sha256_message_words};
+    use crate::verifier::{verify, verify_tables};
+
+    fn run(program: &[u16]) -> (Memory, ExecutionTrace) {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        registers.write(Register::PC, 0x3000);
+        let initial_memory = memory.clone();
+        let trace = execute_program(&mut memory, &mut registers).unwrap();
+        (initial_memory, trace)
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (memory, trace) = run(&[
+            0b0101_000_000_1_00000, // AND R0, R0, #0
+            0b0001_000_000_1_00101, // ADD R0, R0, #5
+            0b0001_001_000_000_000, // ADD R1, R0, R0
+            0xF025,                 // HALT
+        ]);
+        let options = ProofOptions::new(4, 16, 4, 8);
+        let (public_inputs, proof) = prove(&memory, &trace, &options).unwrap();
+        assert_eq!(public_inputs.final_state.gprs[1], 10);
+        assert_eq!(verify(&public_inputs, &proof), Ok(()));
+
+        // The options are bound to the proof
+        let mut forged = proof.clone();
+        forged.options.grinding_bits = 0;
+        assert!(verify(&public_inputs, &forged).is_err());
+
+        // A different claimed final state is rejected
+        let mut forged = public_inputs.clone();
+        forged.final_state.gprs[1] = 11;
+        assert!(verify(&forged, &proof).is_err());
+
+        // So is a proof for a different program
+        let mut forged = public_inputs.clone();
+        forged.memory_root[0] ^= 1;
+        assert!(verify(&forged, &proof).is_err());
+
+        // Or a different output journal
+        let mut forged = public_inputs.clone();
+        forged.output.push(b'!');
+        assert!(verify(&forged, &proof).is_err());
+
+        // And a memory image that does not match the program ID
+        let mut forged = public_inputs.clone();
+        forged.memory_image[0].1 ^= 1;
+        assert!(verify(&forged, &proof).is_err());
+    }
+
+    #[test]
+    fn test_program_rom() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        let program = [
+            0b0101_000_000_1_00000, // AND R0, R0, #0
+            0b0001_000_000_1_00101, // ADD R0, R0, #5
+            0b0011_000_000000001,   // ST R0, #1 -> 0x3004
+            0xF025,                 // HALT
+        ];
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        let rom = ProgramRom {
+            start: 0x3000,
+            length: 4,
+        };
+        memory.set_rom(Some(rom)).unwrap();
+        registers.write(Register::PC, 0x3000);
+        let initial_memory = memory.clone();
+        let trace = execute_program(&mut memory, &mut registers).unwrap();
+        assert_eq!(trace.rom, Some(rom));
+
+        let options = ProofOptions::new(4, 16, 4, 8);
+        let (public_inputs, proof) = prove(&initial_memory, &trace, &options).unwrap();
+        assert_eq!(public_inputs.rom, Some(rom));
+        assert_eq!(verify(&public_inputs, &proof), Ok(()));
+        let receipt = Receipt::new(public_inputs.clone(), proof.clone());
+        let decoded = Receipt::from_bytes(&receipt.to_bytes()).unwrap();
+        assert_eq!(decoded.verify(&public_inputs.program_id()), Ok(()));
+
+        // The ROM is bound to the proof
+        let mut forged = public_inputs.clone();
+        forged.rom = None;
+        assert!(verify(&forged, &proof).is_err());
+        let mut forged = public_inputs.clone();
+        forged.rom = Some(ProgramRom { length: 3, ..rom });
+        assert!(verify(&forged, &proof).is_err());
+
+        // And must be left unchanged
+        let mut forged = public_inputs.clone();
+        forged.final_memory_image[0].1 ^= 1;
+        assert_eq!(
+            verify(&forged, &proof),
+            Err("Program ROM changed during the run")
+        );
+    }
+
+    #[test]
+    fn test_zero_knowledge() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        memory.write(0x3000, 0xF026); // TRAP x26
+        memory.write(0x3001, 0b0001_000_000_1_00001); // ADD R0, R0, #1
+        memory.write(0x3002, 0xF025); // HALT
+        registers.write(Register::PC, 0x3000);
+        let initial_memory = memory.clone();
+        let mut tape = IoTape::new(Vec::new()).with_private_input(vec![41]);
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+
+        let options = ProofOptions::new(4, 8, 2, 4).with_zero_knowledge();
+        let (public_inputs, proof) = prove(&initial_memory, &trace, &options).unwrap();
+        assert_eq!(public_inputs.final_state.gprs[0], 42);
+        assert!(public_inputs.input.is_empty());
+        assert_eq!(
+            proof.trace_length,
+            options
+                .min_trace_length(MAX_CONSTRAINT_DEGREE)
+                .max(MIN_TRACE_LENGTH)
+        );
+        assert_eq!(verify(&public_inputs, &proof), Ok(()));
+
+        // The blinding is fresh for every proof
+        let (_, other) = prove(&initial_memory, &trace, &options).unwrap();
+        assert_ne!(proof.trace_root, other.trace_root);
+        assert_ne!(proof.ood_frame.current, other.ood_frame.current);
+        assert_eq!(verify(&public_inputs, &other), Ok(()));
+
+        // The mode is bound to the proof
+        let mut forged = proof.clone();
+        forged.options.zero_knowledge = false;
+        assert!(verify(&public_inputs, &forged).is_err());
+    }
+
+    #[test]
+    fn test_zero_knowledge_hides_the_alu_requests() {
+        let program = [
+            0xF026,                 // 3000 TRAP x26
+            0b0011_000_000000100,   // 3001 ST R0, #4
+            0b0010_001_000000011,   // 3002 LD R1, #3
+            0b0000_010_000000001,   // 3003 BRz #1
+            0b0001_000_000_1_00001, // 3004 ADD R0, R0, #1
+            0xF025,                 // 3005 HALT
+        ];
+        let prove_secret = |secret: u16, options: &ProofOptions| {
+            let mut memory = Memory::new();
+            let mut registers = RegisterFile::new();
+            for (i, word) in program.iter().enumerate() {
+                memory.write(0x3000 + i as u16, *word);
+            }
+            registers.write(Register::PC, 0x3000);
+            let initial_memory = memory.clone();
+            let mut tape = IoTape::new(Vec::new()).with_private_input(vec![secret]);
+            let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+            let (public_inputs, proof) = prove(&initial_memory, &trace, options).unwrap();
+            assert_eq!(verify(&public_inputs, &proof), Ok(()));
+            proof
+        };
+        let chip_lengths = |proof: &Proof| -> Vec<(Chip, usize)> {
+            let chips = proof.chips.iter();
+            chips
+                .map(|chip| (chip.chip, chip.proof.trace_length))
+                .collect()
+        };
+
+        // Only a non-zero secret makes an ALU request
+        let options = ProofOptions::new(4, 8, 2, 4);
+        let chips = |proof: &Proof| -> Vec<Chip> { proof.chips.iter().map(|c| c.chip).collect() };
+        assert_eq!(
+            chips(&prove_secret(0, &options)),
+            [Chip::Memory, Chip::Branch]
+        );
+        assert_eq!(
+            chips(&prove_secret(1, &options)),
+            [Chip::Alu, Chip::Memory, Chip::Branch]
+        );
+
+        // In zero-knowledge mode, both runs prove ALU, memory, I/O and branch tables as high as
+        // the LC3 table, and a bitwise table, although neither runs AND, NOT or an I/O trap
+        let options = options.with_zero_knowledge();
+        let (zero, one) = (prove_secret(0, &options), prove_secret(1, &options));
+        assert_eq!(zero.trace_length, one.trace_length);
+        assert_eq!(
+            chip_lengths(&zero),
+            [
+                (Chip::Alu, zero.trace_length),
+                (Chip::Bitwise, bitwise::TABLE_SIZE),
+                (Chip::Memory, zero.trace_length),
+                (Chip::Io, zero.trace_length),
+                (Chip::Branch, zero.trace_length)
+            ]
+        );
+        assert_eq!(chip_lengths(&zero), chip_lengths(&one));
+    }
+
+    #[test]
+    fn test_sha256_precompile() {
+        let mut program = vec![
+            0b1110_000_000001111,   // LEA R0, #15 -> 0x3010
+            0b0101_001_001_1_00000, // AND R1, R1, #0
+            0b0001_001_001_1_00001, // ADD R1, R1, #1
+            0b1110_010_000101100,   // LEA R2, #44 -> 0x3030
+            0xF027,                 // TRAP x27
+            0xF025,                 // HALT
+        ];
+        program.resize(0x10, 0);
+        program.extend(sha256_message_words(b"abc"));
+        let (memory, trace) = run(&program);
+        let options = ProofOptions::new(4, 16, 4, 8);
+        let (public_inputs, proof) = prove(&memory, &trace, &options).unwrap();
+        assert!(public_inputs.final_memory_image.contains(&(0x3030, 0xba78)));
+        let chips: Vec<Chip> = proof.chips.iter().map(|chip| chip.chip).collect();
+        assert_eq!(
+            chips,
+            [Chip::Sha256, Chip::Alu, Chip::Bitwise, Chip::Memory]
+        );
+        assert_eq!(verify(&public_inputs, &proof), Ok(()));
+        let receipt = Receipt::new(public_inputs.clone(), proof.clone());
+        assert_eq!(Receipt::from_bytes(&receipt.to_bytes()), Ok(receipt));
+
+        // Another digest in memory
+        let mut forged = public_inputs.clone();
+        let word = forged
+            .final_memory_image
+            .iter_mut()
+            .find(|(address, _)| *address == 0x3031)
+            .unwrap();
+        word.1 ^= 1;
+        assert!(verify(&forged, &proof).is_err());
+
+        // Without a chip, nothing receives the messages sent to it
+        for chip in 0..4 {
+            let mut forged = proof.clone();
+            forged.chips.remove(chip);
+            assert_eq!(
+                verify(&public_inputs, &forged),
+                Err("Bus messages do not balance")
+            );
+        }
+        let mut forged = proof.clone();
+        forged.chips.swap(0, 1);
+        assert_eq!(
+            verify(&public_inputs, &forged),
+            Err("Unexpected chip proofs")
+        );
+        let mut forged = proof.clone();
+        forged.chips[0].proof.bus_sums[0] += QuadExt::ONE;
+        assert_eq!(
+            verify(&public_inputs, &forged),
+            Err("Bus messages do not balance")
+        );
+        let mut forged = proof.clone();
+        forged.chips[0].proof.trace_length *= 2;
+        assert!(verify(&public_inputs, &forged).is_err());
+
+        // The same hash, with the code fetched from a program ROM
+        let mut memory = memory.clone();
+        let rom = ProgramRom {
+            start: 0x3000,
+            length: 6,
+        };
+        memory.set_rom(Some(rom)).unwrap();
+        let mut registers = RegisterFile::new();
+        registers.write(Register::PC, 0x3000);
+        let initial_memory = memory.clone();
+        let trace = execute_program(&mut memory, &mut registers).unwrap();
+        let (public_inputs, proof) = prove(&initial_memory, &trace, &options).unwrap();
+        assert!(public_inputs.final_memory_image.contains(&(0x3030, 0xba78)));
+        assert_eq!(verify(&public_inputs, &proof), Ok(()));
+    }
+
+    /// Prove and verify with transcripts over `S`, and compare their histories step by step
+    fn check_transcripts<S: Sponge>(memory: &Memory, trace: &ExecutionTrace) {
+        let options = ProofOptions::new(4, 8, 4, 4);
+        let image = memory.image();
+        let min_length = options.min_trace_length(MAX_CONSTRAINT_DEGREE);
+        let table = build_padded_trace_table(trace, min_length).unwrap();
+        let public_inputs = PublicInputs {
+            memory_root: memory_root(memory),
+            memory_image: image.clone(),
+            input: trace.input.clone(),
+            output: trace.output.clone(),
+            initial_state: trace.initial_state().unwrap(),
+            final_state: trace.final_state().unwrap(),
+            final_memory_image: trace.final_memory_image(&image),
+            halted: trace.halted,
+            rom: trace.rom,
+        };
+
+        let mut prover_transcript = public_inputs.transcript::<S>(table.length(), &options);
+        let proof = prove_tables(
+            &public_inputs,
+            trace,
+            &table,
+            &options,
+            &mut prover_transcript,
+        )
+        .unwrap();
+        let mut verifier_transcript = public_inputs.transcript::<S>(table.length(), &options);
+        assert_eq!(
+            verify_tables(&public_inputs, &proof, &mut verifier_transcript),
+            Ok(())
+        );
+
+        let prover_history = prover_transcript.history();
+        let verifier_history = verifier_transcript.history();
+        for (step, (p, v)) in prover_history.iter().zip(verifier_history).enumerate() {
+            assert_eq!(p, v, "transcripts diverge at step {}", step);
+        }
+        assert_eq!(prover_history.len(), verifier_history.len());
+    }
+
+    #[test]
+    fn test_transcript_absorption_order() {
+        let (memory, trace) = run(&[
+            0b1110_000_000000011, // LEA R0, #3
+            0xF025,               // HALT
+        ]);
+        check_transcripts::<Sha256Sponge>(&memory, &trace);
+        check_transcripts::<PoseidonSponge>(&memory, &trace);
+    }
+
+    #[test]
+    fn test_conjectured_security() {
+        // 32 queries * 3 bits + 16 grinding bits, capped by 128 bits of challenge less log2 of the
+        // 2^19 points of the LDE domain
+        let options = ProofOptions::default();
+        assert_eq!(options.conjectured_security(1 << 16), 109);
+        // In zero-knowledge mode, FRI runs at half the blowup factor
+        assert_eq!(
+            options.with_zero_knowledge().conjectured_security(1 << 16),
+            80
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_proofs_are_deterministic() {
+        let (memory, trace) = run(&[
+            0b0010_001_000000011,    // LD R1, #3
+            0b0001_000_000_0_00_001, // ADD R0, R0, R1
+            0b0001_001_001_1_11111,  // ADD R1, R1, #-1
+            0b0000_001_111111101,    // BRp #-3
+            0xF025,                  // HALT
+            100,
+        ]);
+        let options = ProofOptions::new(4, 16, 4, 8);
+        let prove_with_threads = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            // Memory is not Sync, so every pool gets its own copy, boxed to keep it off the stack
+            let (memory, trace, options) = (Box::new(memory.clone()), &trace, &options);
+            pool.install(move || prove(&memory, trace, options))
+                .unwrap()
+        };
+        let (public_inputs, proof) = prove_with_threads(1);
+        assert_eq!(
+            prove_with_threads(4),
+            (public_inputs.clone(), proof.clone())
+        );
+        assert_eq!(verify(&public_inputs, &proof), Ok(()));
+    }
+}
@@ -0,0 +1,707 @@
+//! Receipt Module
+//!
+//! This module defines the [`Receipt`] of a proven run, the unit that is stored and shipped between
+//! machines, and its binary format.
+//!
+//! ## Format
+//! A receipt starts with the magic bytes `LC3R` and a `u16` format version, currently
+//! [`VERSION`]. Integers are little-endian, field elements are `u64`s in `[0, p)`, extension
+//! elements `a + b * u` are the field elements `a` and `b`, digests are 32 bytes, booleans are one
+//! byte (0 or 1), and a list is a `u32` count followed by its items. Version 1 continues with:
+//! 1. The public inputs: initial memory root, exit status (0 = halted, 1 = paused), initial memory
+//!    image as a list of `(u16 address, u16 value)` pairs, input and journal as lists of bytes,
+//!    initial and final register states as ten `u16`s (R0-R7, PC, COND), final memory image as a
+//!    list of pairs, and the program ROM as a boolean followed by its start and length as `u16`s
+//!    if present.
+//! 2. The proof options: blowup factor, number of queries, folding factor and grinding bits as
+//!    `u32`s, then the zero-knowledge flag.
+//! 3. The proof: trace length as a `u32`, trace root, auxiliary root as a boolean followed by the
+//!    root if present, bus sums as a list of extension elements, composition root, the
+//!    out-of-domain frame as three lists of extension elements, the trace, auxiliary and
+//!    composition openings as lists of rows (each a list of values and a list of path digests,
+//!    followed by the salt digest of the row in zero-knowledge proofs), and the FRI proof: layer
+//!    roots, remainder, proof-of-work nonce as a `u64`, and for every query a list of layer
+//!    openings laid out like unsalted rows. Trace rows hold field elements; auxiliary and
+//!    composition rows, the remainder and the FRI layers hold extension elements.
+//! 4. The chip proofs, as a list of a `u8` chip ID (0 = SHA-256, 1 = ALU, 2 = bitwise,
+//!    3 = memory, 4 = I/O, 5 = branch) followed by a proof laid out as in 3, without its chip
+//!    proofs. Chip proofs share the proof options of the receipt.
+//!
+//! Decoding rejects other magic bytes, unknown versions, truncated data, trailing bytes and values
+//! out of range.
+
+use crate::air::Chip;
+use crate::field::{Felt, FieldElement, MODULUS};
+use crate::fri::{FriLayerOpening, FriProof};
+use crate::memory::ProgramRom;
+use crate::merkle::Digest;
+use crate::proof::{ChipProof, OodFrame, Proof, ProofOptions, PublicInputs, RowOpening};
+use crate::register::R_COUNT;
+use crate::trace::RegisterState;
+use crate::verifier::verify;
+use alloc::vec::Vec;
+
+/// Magic bytes opening every receipt
+pub const MAGIC: [u8; 4] = *b"LC3R";
+
+/// Version of the format written by [`Receipt::to_bytes`]
+pub const VERSION: u16 = 1;
+
+/// How a proven run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The run executed `HALT`
+    Halted,
+    /// The run paused at the end of a segment
+    Paused,
+}
+
+/// A proof of a run together with the statement it proves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    pub public_inputs: PublicInputs,
+    pub proof: Proof,
+}
+
+impl Receipt {
+    pub fn new(public_inputs: PublicInputs, proof: Proof) -> Self {
+        Receipt {
+            public_inputs,
+            proof,
+        }
+    }
+
+    /// Program ID: commitment to the initial memory image and entry registers
+    pub fn program_id(&self) -> Digest {
+        self.public_inputs.program_id()
+    }
+
+    /// Input bytes read by the run
+    pub fn input(&self) -> &[u8] {
+        &self.public_inputs.input
+    }
+
+    /// Output journal of the run
+    pub fn journal(&self) -> &[u8] {
+        &self.public_inputs.output
+    }
+
+    pub fn exit_status(&self) -> ExitStatus {
+        if self.public_inputs.halted {
+            ExitStatus::Halted
+        } else {
+            ExitStatus::Paused
+        }
+    }
+
+    /// Verify the proof, and that it is a proof of a run of the program `program_id`
+    pub fn verify(&self, program_id: &Digest) -> Result<(), &'static str> {
+        if self.program_id() != *program_id {
+            return Err("Receipt is for another program");
+        }
+        verify(&self.public_inputs, &self.proof)
+    }
+
+    /// Encode the receipt in the current format version
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer(Vec::new());
+        writer.0.extend_from_slice(&MAGIC);
+        writer.u16(VERSION);
+        writer.public_inputs(&self.public_inputs);
+        writer.proof(&self.proof);
+        writer.0
+    }
+
+    /// Decode a receipt
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = Reader(bytes);
+        if reader.take(MAGIC.len()).map_err(|_| "Not a receipt")? != MAGIC {
+            return Err("Not a receipt");
+        }
+        if reader.u16()? != VERSION {
+            return Err("Unsupported receipt version");
+        }
+        let receipt = Receipt {
+            public_inputs: reader.public_inputs()?,
+            proof: reader.proof()?,
+        };
+        if !reader.0.is_empty() {
+            return Err("Trailing bytes after receipt");
+        }
+        Ok(receipt)
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: usize) {
+        self.0.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn digest(&mut self, digest: &Digest) {
+        self.0.extend_from_slice(digest);
+    }
+
+    fn list<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.u32(items.len());
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.list(bytes, |w, byte| w.u8(*byte));
+    }
+
+    fn elements<E: FieldElement>(&mut self, elements: &[E]) {
+        let mut base = Vec::with_capacity(E::EXTENSION_DEGREE);
+        self.list(elements, |w, element| {
+            base.clear();
+            element.write_base_elements(&mut base);
+            for felt in &base {
+                w.u64(felt.as_u64());
+            }
+        });
+    }
+
+    fn digests(&mut self, digests: &[Digest]) {
+        self.list(digests, Self::digest);
+    }
+
+    fn image(&mut self, image: &[(u16, u16)]) {
+        self.list(image, |w, &(address, value)| {
+            w.u16(address);
+            w.u16(value);
+        });
+    }
+
+    fn state(&mut self, state: &RegisterState) {
+        for gpr in state.gprs {
+            self.u16(gpr);
+        }
+        self.u16(state.pc);
+        self.u16(state.cond);
+    }
+
+    fn row<E: FieldElement>(&mut self, values: &[E], path: &[Digest]) {
+        self.elements(values);
+        self.digests(path);
+    }
+
+    fn opening<E: FieldElement>(&mut self, opening: &RowOpening<E>) {
+        self.row(&opening.values, &opening.path);
+        if let Some(salt) = &opening.salt {
+            self.digest(salt);
+        }
+    }
+
+    fn public_inputs(&mut self, public_inputs: &PublicInputs) {
+        self.digest(&public_inputs.memory_root);
+        self.u8(!public_inputs.halted as u8);
+        self.image(&public_inputs.memory_image);
+        self.bytes(&public_inputs.input);
+        self.bytes(&public_inputs.output);
+        self.state(&public_inputs.initial_state);
+        self.state(&public_inputs.final_state);
+        self.image(&public_inputs.final_memory_image);
+        self.u8(public_inputs.rom.is_some() as u8);
+        if let Some(rom) = &public_inputs.rom {
+            self.u16(rom.start);
+            self.u16(rom.length);
+        }
+    }
+
+    fn proof(&mut self, proof: &Proof) {
+        let options = &proof.options;
+        self.u32(options.blowup_factor);
+        self.u32(options.num_queries);
+        self.u32(options.folding_factor);
+        self.u32(options.grinding_bits as usize);
+        self.u8(options.zero_knowledge as u8);
+        self.proof_body(proof);
+        self.list(&proof.chips, |w, chip| {
+            w.u8(chip.chip.id());
+            w.proof_body(&chip.proof);
+        });
+    }
+
+    /// A proof without its options and chip proofs
+    fn proof_body(&mut self, proof: &Proof) {
+        self.u32(proof.trace_length);
+        self.digest(&proof.trace_root);
+        self.u8(proof.aux_root.is_some() as u8);
+        if let Some(aux_root) = &proof.aux_root {
+            self.digest(aux_root);
+        }
+        self.elements(&proof.bus_sums);
+        self.digest(&proof.composition_root);
+        self.elements(&proof.ood_frame.current);
+        self.elements(&proof.ood_frame.next);
+        self.elements(&proof.ood_frame.composition);
+        self.list(&proof.trace_openings, Self::opening);
+        for openings in [&proof.aux_openings, &proof.composition_openings] {
+            self.list(openings, Self::opening);
+        }
+
+        let fri = &proof.fri_proof;
+        self.digests(&fri.layer_roots);
+        self.elements(&fri.remainder);
+        self.u64(fri.pow_nonce);
+        self.list(&fri.queries, |w, layers| {
+            w.list(layers, |w, opening| w.row(&opening.values, &opening.path));
+        });
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], &'static str> {
+        if self.0.len() < count {
+            return Err("Truncated receipt");
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, &'static str> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("Invalid boolean in receipt"),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, &'static str> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<usize, &'static str> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn u64(&mut self) -> Result<u64, &'static str> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn digest(&mut self) -> Result<Digest, &'static str> {
+        Ok(self.take(32)?.try_into().unwrap())
+    }
+
+    fn list<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, &'static str>,
+    ) -> Result<Vec<T>, &'static str> {
+        let count = self.u32()?;
+        // Every item takes at least one byte, which bounds the allocation
+        let mut items = Vec::with_capacity(count.min(self.0.len()));
+        for _ in 0..count {
+            items.push(read(self)?);
+        }
+        Ok(items)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, &'static str> {
+        self.list(Self::u8)
+    }
+
+    fn felt(&mut self) -> Result<Felt, &'static str> {
+        let value = self.u64()?;
+        if value >= MODULUS {
+            return Err("Invalid field element in receipt");
+        }
+        Ok(Felt::new(value))
+    }
+
+    fn element<E: FieldElement>(&mut self) -> Result<E, &'static str> {
+        let mut base = [Felt::ZERO; 2];
+        for felt in &mut base[..E::EXTENSION_DEGREE] {
+            *felt = self.felt()?;
+        }
+        Ok(E::from_base_elements(&base[..E::EXTENSION_DEGREE]))
+    }
+
+    fn elements<E: FieldElement>(&mut self) -> Result<Vec<E>, &'static str> {
+        self.list(Self::element)
+    }
+
+    fn digests(&mut self) -> Result<Vec<Digest>, &'static str> {
+        self.list(Self::digest)
+    }
+
+    fn image(&mut self) -> Result<Vec<(u16, u16)>, &'static str> {
+        self.list(|r| Ok((r.u16()?, r.u16()?)))
+    }
+
+    fn state(&mut self) -> Result<RegisterState, &'static str> {
+        let mut gprs = [0; R_COUNT];
+        for gpr in gprs.iter_mut() {
+            *gpr = self.u16()?;
+        }
+        let state = RegisterState {
+            gprs,
+            pc: self.u16()?,
+            cond: self.u16()?,
+        };
+        state.check_cond()?;
+        Ok(state)
+    }
+
+    fn opening<E: FieldElement>(&mut self, salted: bool) -> Result<RowOpening<E>, &'static str> {
+        Ok(RowOpening {
+            values: self.elements()?,
+            path: self.digests()?,
+            salt: if salted { Some(self.digest()?) } else { None },
+        })
+    }
+
+    fn public_inputs(&mut self) -> Result<PublicInputs, &'static str> {
+        let memory_root = self.digest()?;
+        let halted = !self.bool()?;
+        Ok(PublicInputs {
+            memory_root,
+            memory_image: self.image()?,
+            input: self.bytes()?,
+            output: self.bytes()?,
+            initial_state: self.state()?,
+            final_state: self.state()?,
+            final_memory_image: self.image()?,
+            halted,
+            rom: if self.bool()? {
+                Some(ProgramRom {
+                    start: self.u16()?,
+                    length: self.u16()?,
+                })
+            } else {
+                None
+            },
+        })
+    }
+
+    fn proof(&mut self) -> Result<Proof, &'static str> {
+        let options = ProofOptions {
+            blowup_factor: self.u32()?,
+            num_queries: self.u32()?,
+            folding_factor: self.u32()?,
+            grinding_bits: self.u32()? as u32,
+            zero_knowledge: self.bool()?,
+        };
+        let mut proof = self.proof_body(&options)?;
+        proof.chips = self.list(|r| {
+            let chip = Chip::from_id(r.u8()?).ok_or("Unknown chip in receipt")?;
+            Ok(ChipProof {
+                chip,
+                proof: r.proof_body(&options)?,
+            })
+        })?;
+        Ok(proof)
+    }
+
+    /// A proof without its chip proofs, made with `options`
+    fn proof_body(&mut self, options: &ProofOptions) -> Result<Proof, &'static str> {
+        let trace_length = self.u32()?;
+        let trace_root = self.digest()?;
+        let aux_root = if self.bool()? {
+            Some(self.digest()?)
+        } else {
+            None
+        };
+        let bus_sums = self.elements()?;
+        let composition_root = self.digest()?;
+        let ood_frame = OodFrame {
+            current: self.elements()?,
+            next: self.elements()?,
+            composition: self.elements()?,
+        };
+        let salted = options.zero_knowledge;
+        let trace_openings = self.list(|r| r.opening(salted))?;
+        let aux_openings = self.list(|r| r.opening(salted))?;
+        let composition_openings = self.list(|r| r.opening(salted))?;
+        let fri_proof = FriProof {
+            layer_roots: self.digests()?,
+            remainder: self.elements()?,
+            pow_nonce: self.u64()?,
+            queries: self.list(|r| {
+                r.list(|r| {
+                    let row = r.opening(false)?;
+                    Ok(FriLayerOpening {
+                        values: row.values,
+                        path: row.path,
+                    })
+                })
+            })?,
+        };
+        Ok(Proof {
+            options: *options,
+            trace_length,
+            trace_root,
+            aux_root,
+            bus_sums,
+            composition_root,
+            ood_frame,
+            trace_openings,
+            aux_openings,
+            composition_openings,
+            fri_proof,
+            chips: Vec::new(),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::field::QuadExt;
+    use crate::io::IoTape;
+    use crate::memory::Memory;
+    use crate::prover::prove;
+    use crate::register::{Register, RegisterFile};
+    use crate::utils::execute_program_with_io;
+
+    fn receipt() -> Receipt {
+        receipt_from(&RegisterState {
+            pc: 0x3000,
+            ..Default::default()
+        })
+    }
+
+    /// Proves a run that echoes an input byte, starting from `entry`
+    fn receipt_from(entry: &RegisterState) -> Receipt {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        memory.write(0x3000, 0b1111_0000_00100000); // GETC
+        memory.write(0x3001, 0b1111_0000_00100001); // OUT
+        memory.write(0x3002, 0xF025); // HALT
+        entry.restore(&mut registers);
+        let initial_memory = memory.clone();
+        let mut tape = IoTape::new(b"k".to_vec());
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        let (public_inputs, proof) =
+            prove(&initial_memory, &trace, &ProofOptions::new(4, 8, 4, 4)).unwrap();
+        Receipt::new(public_inputs, proof)
+    }
+
+    #[test]
+    fn test_receipt_round_trip() {
+        let receipt = receipt();
+        assert_eq!(receipt.journal(), b"k");
+        assert_eq!(receipt.exit_status(), ExitStatus::Halted);
+        let program_id = receipt.program_id();
+        assert_eq!(receipt.verify(&program_id), Ok(()));
+        assert_eq!(
+            receipt.verify(&[0; 32]),
+            Err("Receipt is for another program")
+        );
+
+        let bytes = receipt.to_bytes();
+        assert_eq!(bytes[..4], MAGIC);
+        let decoded = Receipt::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, receipt);
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.verify(&program_id), Ok(()));
+
+        // The same program entered past GETC, with a chosen R0, has another ID
+        let mut gprs = [0; R_COUNT];
+        gprs[0] = u16::from(b'A');
+        let skipped = receipt_from(&RegisterState {
+            gprs,
+            pc: 0x3001,
+            cond: 0,
+        });
+        assert_eq!(skipped.journal(), b"A");
+        assert_eq!(
+            skipped.public_inputs.memory_root,
+            receipt.public_inputs.memory_root
+        );
+        assert_eq!(skipped.verify(&skipped.program_id()), Ok(()));
+        assert_eq!(
+            skipped.verify(&program_id),
+            Err("Receipt is for another program")
+        );
+    }
+
+    /// Proves a run that reads a private word, computes with it and clears it before halting
+    fn private_receipt(secret: u16) -> Receipt {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        memory.write(0x3000, 0xF026); // TRAP x26
+        memory.write(0x3001, 0b0001_001_000_000_000); // ADD R1, R0, R0
+        memory.write(0x3002, 0b0101_000_001_0_00_000); // AND R0, R1, R0
+        memory.write(0x3003, 0b0101_001_001_1_00000); // AND R1, R1, #0
+        memory.write(0x3004, 0b0101_000_000_1_00000); // AND R0, R0, #0
+        memory.write(0x3005, 0xF025); // HALT
+        registers.write(Register::PC, 0x3000);
+        let initial_memory = memory.clone();
+        let mut tape = IoTape::new(Vec::new()).with_private_input(vec![secret]);
+        let trace = execute_program_with_io(&mut memory, &mut registers, &mut tape).unwrap();
+        let options = ProofOptions::new(4, 8, 2, 4).with_zero_knowledge();
+        let (public_inputs, proof) = prove(&initial_memory, &trace, &options).unwrap();
+        Receipt::new(public_inputs, proof)
+    }
+
+    #[test]
+    fn test_zero_knowledge_receipts() {
+        let receipts = [private_receipt(0x1234), private_receipt(0xBEEF)];
+        let program_id = receipts[0].program_id();
+        for receipt in &receipts {
+            assert_eq!(receipt.verify(&program_id), Ok(()));
+            let decoded = Receipt::from_bytes(&receipt.to_bytes()).unwrap();
+            assert_eq!(&decoded, receipt);
+        }
+
+        // The runs differ only in their private input, which the public inputs do not show
+        let [first, second] = &receipts;
+        assert_eq!(first.public_inputs, second.public_inputs);
+
+        // The proofs have the same shape: the same options, the same number of openings, rows of
+        // the same widths, paths of the same lengths and salted leaves everywhere
+        let (a, b) = (&first.proof, &second.proof);
+        assert_eq!(first.to_bytes().len(), second.to_bytes().len());
+        assert_eq!(a.trace_length, b.trace_length);
+        assert_eq!(a.ood_frame.current.len(), b.ood_frame.current.len());
+        assert_eq!(a.ood_frame.composition.len(), b.ood_frame.composition.len());
+        fn assert_same_shape<E: FieldElement>(a: &[RowOpening<E>], b: &[RowOpening<E>]) {
+            assert_eq!(a.len(), b.len());
+            for (a, b) in a.iter().zip(b) {
+                assert_eq!(a.values.len(), b.values.len());
+                assert_eq!(a.path.len(), b.path.len());
+                assert!(a.salt.is_some() && b.salt.is_some());
+            }
+        }
+        assert_same_shape(&a.trace_openings, &b.trace_openings);
+        assert_same_shape(&a.aux_openings, &b.aux_openings);
+        assert_same_shape(&a.composition_openings, &b.composition_openings);
+
+        // The randomness is fresh: two proofs of the same run share no commitment or out-of-domain
+        // evaluation
+        let again = private_receipt(0x1234);
+        let (a, c) = (&first.proof, &again.proof);
+        assert_ne!(a.trace_root, c.trace_root);
+        assert_ne!(a.composition_root, c.composition_root);
+        let shared = |x: &[QuadExt], y: &[QuadExt]| x.iter().any(|value| y.contains(value));
+        assert!(!shared(&a.ood_frame.current, &c.ood_frame.current));
+        assert!(!shared(&a.ood_frame.composition, &c.ood_frame.composition));
+    }
+
+    #[test]
+    fn test_receipt_rejects_malformed_data() {
+        let bytes = receipt().to_bytes();
+
+        let mut other_version = bytes.clone();
+        other_version[4] = 2;
+        assert_eq!(
+            Receipt::from_bytes(&other_version),
+            Err("Unsupported receipt version")
+        );
+        let mut other_magic = bytes.clone();
+        other_magic[0] = b'X';
+        assert_eq!(Receipt::from_bytes(&other_magic), Err("Not a receipt"));
+        assert_eq!(Receipt::from_bytes(&bytes[..2]), Err("Not a receipt"));
+
+        for length in [6, 40, 100, bytes.len() / 2, bytes.len() - 1] {
+            assert_eq!(
+                Receipt::from_bytes(&bytes[..length]),
+                Err("Truncated receipt")
+            );
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Receipt::from_bytes(&trailing),
+            Err("Trailing bytes after receipt")
+        );
+
+        // The exit status byte follows the magic, the version and the memory root
+        let mut status = bytes.clone();
+        status[38] = 2;
+        assert_eq!(
+            Receipt::from_bytes(&status),
+            Err("Invalid boolean in receipt")
+        );
+
+        // A final COND with bits the AIR does not constrain
+        let mut forged = receipt();
+        forged.public_inputs.final_state.cond = 0xFF01;
+        assert_eq!(
+            Receipt::from_bytes(&forged.to_bytes()),
+            Err("Unsupported condition flags")
+        );
+        assert_eq!(
+            forged.verify(&forged.program_id()),
+            Err("Unsupported condition flags")
+        );
+    }
+
+    #[test]
+    fn test_receipt_rejects_hostile_options() {
+        let receipt = receipt();
+        let program_id = receipt.program_id();
+        let options = receipt.proof.options;
+        let hostile = [
+            (
+                ProofOptions {
+                    num_queries: u32::MAX as usize,
+                    ..options
+                },
+                "Too many FRI queries",
+            ),
+            (
+                ProofOptions {
+                    folding_factor: 1 << 31,
+                    ..options
+                },
+                "Folding factor too large",
+            ),
+            (
+                ProofOptions {
+                    blowup_factor: 12,
+                    ..options
+                },
+                "Blowup factor must be a power of two",
+            ),
+            (
+                ProofOptions {
+                    blowup_factor: 1 << 31,
+                    ..options
+                },
+                "Low-degree extension domain too large",
+            ),
+        ];
+        for (options, error) in hostile {
+            let mut forged = receipt.clone();
+            forged.proof.options = options;
+            for chip in &mut forged.proof.chips {
+                chip.proof.options = options;
+            }
+            let decoded = Receipt::from_bytes(&forged.to_bytes()).unwrap();
+            assert_eq!(decoded.verify(&program_id), Err(error));
+        }
+
+        // Blinding sizes saturate instead of overflowing
+        let hostile = ProofOptions {
+            num_queries: usize::MAX,
+            ..options
+        }
+        .with_zero_knowledge();
+        assert_eq!(hostile.min_trace_length(2), usize::MAX);
+    }
+}
@@ -30,6 +30,23 @@ pub enum Register {
     COND = 9,
 }
 
+// Helper function: Convert u16 to Register
+impl From<u16> for Register {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Register::R0,
+            1 => Register::R1,
+            2 => Register::R2,
+            3 => Register::R3,
+            4 => Register::R4,
+            5 => Register::R5,
+            6 => Register::R6,
+            7 => Register::R7,
+            _ => panic!("Invalid register number"),
+        }
+    }
+}
+
 /// Condition Flags
 pub mod condition_flags {
     /// Positive Flag
@@ -101,6 +118,12 @@ impl RegisterFile {
     }
 }
 
+impl Default for RegisterFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,212 @@
+//! LC3 Execution Trace Module
+//!
+//! This module defines the execution trace recorded by
+//! [`execute_program`](crate::utils::execute_program).
+//!
+//! ## Design
+//! - A trace is an ordered list of [`Step`]s, one per executed instruction, including the final
+//!   `HALT`.
+//! - Each step records the fetch address, the raw instruction and its decoded opcode.
+//! - The architectural state (R0-R7, PC and COND) is captured before and after the step.
+//! - Every memory access made while executing the step is recorded in program order. The
+//!   instruction fetch is always the first access of a step, unless the run fetches its
+//!   instructions from a [`ProgramRom`], which is not a memory access.
+//! - The trace also keeps the bytes the run read with `GETC`/`IN` and wrote with
+//!   `OUT`/`PUTS`/`PUTSP`, in order.
+//! - A trace either ends with `HALT` or pauses after a number of steps, when it is one segment of
+//!   a longer run (see [`continuation`](crate::continuation)).
+
+use crate::memory::{MemoryAccess, ProgramRom};
+use crate::opcode::Opcode;
+use crate::register::{condition_flags, Register, RegisterFile, R_COUNT};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// Snapshot of the LC3 register file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegisterState {
+    /// General purpose registers R0-R7
+    pub gprs: [u16; R_COUNT],
+    /// Program counter
+    pub pc: u16,
+    /// Condition flags
+    pub cond: u16,
+}
+
+impl RegisterState {
+    /// Capture the current contents of a register file
+    pub fn capture(registers: &RegisterFile) -> Self {
+        let mut gprs = [0; R_COUNT];
+        for (i, gpr) in gprs.iter_mut().enumerate() {
+            *gpr = registers.read(Register::from(i as u16));
+        }
+        RegisterState {
+            gprs,
+            pc: registers.read(Register::PC),
+            cond: registers.read(Register::COND),
+        }
+    }
+
+    /// Check that COND holds no flag, as before the first instruction setting one, or exactly one
+    /// of N, Z and P
+    pub fn check_cond(&self) -> Result<(), &'static str> {
+        let flags = [
+            0,
+            condition_flags::FL_POS,
+            condition_flags::FL_ZRO,
+            condition_flags::FL_NEG,
+        ];
+        if !flags.contains(&self.cond) {
+            return Err("Unsupported condition flags");
+        }
+        Ok(())
+    }
+
+    /// Write this snapshot back into a register file
+    pub fn restore(&self, registers: &mut RegisterFile) {
+        for (i, gpr) in self.gprs.iter().enumerate() {
+            registers.write(Register::from(i as u16), *gpr);
+        }
+        registers.write(Register::PC, self.pc);
+        registers.write(Register::COND, self.cond);
+    }
+}
+
+/// A single executed instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// Address the instruction was fetched from
+    pub pc: u16,
+    /// Raw 16-bit instruction
+    pub instruction: u16,
+    /// Decoded opcode
+    pub opcode: Opcode,
+    /// Register state before the instruction was fetched
+    pub before: RegisterState,
+    /// Register state after the instruction was executed
+    pub after: RegisterState,
+    /// Memory accesses in program order, starting with the instruction fetch if it is not from
+    /// the program ROM
+    pub memory_accesses: Vec<MemoryAccess>,
+}
+
+/// Complete per-step record of a program run
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecutionTrace {
+    pub steps: Vec<Step>,
+    /// Input bytes consumed by the run
+    pub input: Vec<u8>,
+    /// Output journal of the run
+    pub output: Vec<u8>,
+    /// Whether the run ended with `HALT`, rather than pausing
+    pub halted: bool,
+    /// The program ROM the run fetched its instructions from, if any
+    pub rom: Option<ProgramRom>,
+}
+
+impl ExecutionTrace {
+    pub fn new() -> Self {
+        ExecutionTrace::default()
+    }
+
+    /// Number of executed instructions
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Register state before the first step
+    pub fn initial_state(&self) -> Option<RegisterState> {
+        self.steps.first().map(|step| step.before)
+    }
+
+    /// Register state after the last step
+    pub fn final_state(&self) -> Option<RegisterState> {
+        self.steps.last().map(|step| step.after)
+    }
+
+    /// Final value of every word that is non-zero in `memory_image` or accessed by the run, in
+    /// address order
+    pub fn final_memory_image(&self, memory_image: &[(u16, u16)]) -> Vec<(u16, u16)> {
+        let mut words: BTreeMap<u16, u16> = memory_image.iter().copied().collect();
+        for access in self.memory_accesses() {
+            words.insert(access.address, access.new_value);
+        }
+        words.into_iter().collect()
+    }
+
+    /// All memory accesses of the run, in program order
+    pub fn memory_accesses(&self) -> impl Iterator<Item = &MemoryAccess> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.memory_accesses.iter())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::memory::Memory;
+    use crate::opcode::Opcode;
+    use crate::register::{condition_flags, Register, RegisterFile};
+    use crate::utils::execute_program;
+
+    #[test]
+    fn test_execution_trace() {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+
+        let program = [
+            0b0001_000_000_1_00101, // ADD R0, R0, #5
+            0b0011_000_000000010,   // ST R0, #2
+            0b1010_001_000000010,   // LDI R1, #2
+            0xF025,                 // HALT
+            0x0000,                 // data written by ST
+            0x3004,                 // pointer used by LDI
+        ];
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        registers.write(Register::PC, 0x3000);
+
+        let trace = execute_program(&mut memory, &mut registers).unwrap();
+        assert_eq!(trace.len(), 4);
+
+        let add = &trace.steps[0];
+        assert_eq!(add.pc, 0x3000);
+        assert_eq!(add.opcode, Opcode::OP_ADD);
+        assert_eq!(add.before.gprs[0], 0);
+        assert_eq!(add.after.gprs[0], 5);
+        assert_eq!(add.after.pc, 0x3001);
+        assert_eq!(add.after.cond, condition_flags::FL_POS);
+        assert_eq!(add.memory_accesses.len(), 1);
+
+        let st = &trace.steps[1];
+        assert_eq!(st.memory_accesses.len(), 2);
+        let write = st.memory_accesses[1];
+        assert_eq!(write.address, 0x3004);
+        assert_eq!(write.old_value, 0);
+        assert_eq!(write.new_value, 5);
+        assert!(write.is_write);
+
+        let ldi = &trace.steps[2];
+        let addresses: Vec<u16> = ldi.memory_accesses.iter().map(|a| a.address).collect();
+        assert_eq!(addresses, vec![0x3002, 0x3005, 0x3004]);
+        assert_eq!(ldi.after.gprs[1], 5);
+
+        let halt = &trace.steps[3];
+        assert_eq!(halt.opcode, Opcode::OP_TRAP);
+        assert_eq!(trace.final_state().unwrap().pc, 0x3004);
+        assert_eq!(trace.memory_accesses().count(), 7);
+        assert!(trace.halted);
+
+        let image = [(0x3000, 0b0001_000_000_1_00101), (0x4000, 1)];
+        let final_image = trace.final_memory_image(&image);
+        assert_eq!(final_image.len(), 7);
+        assert_eq!(final_image[0], (0x3000, 0b0001_000_000_1_00101));
+        assert!(final_image.contains(&(0x3004, 5)));
+        assert_eq!(final_image[6], (0x4000, 1));
+    }
+}
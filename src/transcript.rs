@@ -0,0 +1,341 @@
+//! Fiat-Shamir Transcript Module
+//!
+//! This module turns the interactive STARK protocol into a non-interactive one. The prover and the
+//! verifier absorb the same messages in the same order and therefore derive the same challenges.
+//!
+//! ## Design
+//! - Every message and every challenge carries a domain-separation label naming its protocol phase
+//!   (`"trace root"`, `"ood point"`, ...). The label is hashed together with the message, so a
+//!   value absorbed in one phase can never stand in for a value of another.
+//! - The hash is a [`Sponge`] backend: [`Sha256Sponge`] works on bytes, [`PoseidonSponge`] and
+//!   [`Poseidon2Sponge`] on field elements. All ratchet their state on every absorption and every
+//!   squeeze, so several challenges can be drawn between two absorptions.
+//! - The transcript keeps the history of labelled operations. Prover and verifier run on the same
+//!   [`Transcript`] type, and comparing their histories pinpoints the first step where their
+//!   absorption orders diverge.
+//! - Grinding looks for a nonce such that hashing it with the state gives a value with a given
+//!   number of leading zero bits, and absorbs the nonce.
+
+use crate::field::{Felt, FieldElement, MODULUS};
+use crate::merkle::{hash_bytes, Digest};
+#[cfg(feature = "std")]
+use crate::poseidon::{Permutation, Poseidon, RATE, WIDTH};
+#[cfg(feature = "std")]
+use crate::poseidon2::Poseidon2;
+#[cfg(feature = "std")]
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::marker::PhantomData;
+
+/// Hash backend of a [`Transcript`]
+pub trait Sponge {
+    /// Start from the given protocol seed
+    fn new(seed: &[u8]) -> Self;
+
+    /// Absorb a labelled byte message
+    fn absorb_bytes(&mut self, label: &str, bytes: &[u8]);
+
+    /// Absorb a labelled message of field elements
+    fn absorb_elements(&mut self, label: &str, elements: &[Felt]);
+
+    /// Squeeze a uniformly random base field element for a labelled challenge
+    fn squeeze(&mut self, label: &str) -> Felt;
+
+    /// Hash `nonce` with the current state, as a 64-bit value whose leading zeros are the work
+    fn proof_of_work(&self, nonce: u64) -> u64;
+}
+
+/// Byte-oriented backend: the state is a SHA-256 digest
+#[derive(Debug, Clone)]
+pub struct Sha256Sponge {
+    state: Digest,
+}
+
+impl Sha256Sponge {
+    /// `state = SHA-256(state || kind || len(label) || label || message)`
+    fn ratchet(&mut self, kind: u8, label: &str, message: &[u8]) {
+        let mut data = Vec::with_capacity(41 + label.len() + message.len());
+        data.extend_from_slice(&self.state);
+        data.push(kind);
+        data.extend_from_slice(&(label.len() as u64).to_le_bytes());
+        data.extend_from_slice(label.as_bytes());
+        data.extend_from_slice(message);
+        self.state = hash_bytes(&data);
+    }
+}
+
+impl Sponge for Sha256Sponge {
+    fn new(seed: &[u8]) -> Self {
+        Sha256Sponge {
+            state: hash_bytes(seed),
+        }
+    }
+
+    fn absorb_bytes(&mut self, label: &str, bytes: &[u8]) {
+        self.ratchet(0, label, bytes);
+    }
+
+    fn absorb_elements(&mut self, label: &str, elements: &[Felt]) {
+        let mut bytes = Vec::with_capacity(8 * elements.len());
+        for element in elements {
+            element.write_bytes(&mut bytes);
+        }
+        self.ratchet(1, label, &bytes);
+    }
+
+    fn squeeze(&mut self, label: &str) -> Felt {
+        loop {
+            self.ratchet(2, label, &[]);
+            let value = u64::from_le_bytes(self.state[..8].try_into().unwrap());
+            if value < MODULUS {
+                return Felt::new(value);
+            }
+        }
+    }
+
+    fn proof_of_work(&self, nonce: u64) -> u64 {
+        let mut data = Vec::with_capacity(40);
+        data.extend_from_slice(&self.state);
+        data.extend_from_slice(&nonce.to_le_bytes());
+        let digest = hash_bytes(&data);
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+/// Algebraic backend: a sponge over the Goldilocks field, with the Poseidon permutation by default.
+///
+/// Like the permutations, it needs the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct PoseidonSponge<P: Permutation = Poseidon> {
+    state: [Felt; WIDTH],
+    permutation: PhantomData<P>,
+}
+
+/// Algebraic backend with the Poseidon2 permutation
+#[cfg(feature = "std")]
+pub type Poseidon2Sponge = PoseidonSponge<Poseidon2>;
+
+#[cfg(feature = "std")]
+impl<P: Permutation> PoseidonSponge<P> {
+    /// Bytes packed seven to a field element, preceded by their length
+    fn pack_bytes(bytes: &[u8], out: &mut Vec<Felt>) {
+        out.push(Felt::new(bytes.len() as u64));
+        for chunk in bytes.chunks(7) {
+            let mut limb = [0; 8];
+            limb[..chunk.len()].copy_from_slice(chunk);
+            out.push(Felt::new(u64::from_le_bytes(limb)));
+        }
+    }
+
+    /// Absorb `kind || label || len(message) || message`, padded with zeros to the rate
+    fn ratchet(&mut self, kind: u64, label: &str, message: &[Felt]) {
+        let mut input = vec![Felt::new(kind)];
+        Self::pack_bytes(label.as_bytes(), &mut input);
+        input.push(Felt::new(message.len() as u64));
+        input.extend_from_slice(message);
+        for chunk in input.chunks(RATE) {
+            for (value, element) in self.state.iter_mut().zip(chunk) {
+                *value += *element;
+            }
+            P::permute(&mut self.state);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: Permutation + Clone> Sponge for PoseidonSponge<P> {
+    fn new(seed: &[u8]) -> Self {
+        let mut sponge = PoseidonSponge {
+            state: [Felt::ZERO; WIDTH],
+            permutation: PhantomData,
+        };
+        let mut input = Vec::new();
+        Self::pack_bytes(seed, &mut input);
+        sponge.ratchet(0, "seed", &input);
+        sponge
+    }
+
+    fn absorb_bytes(&mut self, label: &str, bytes: &[u8]) {
+        let mut input = Vec::new();
+        Self::pack_bytes(bytes, &mut input);
+        self.ratchet(1, label, &input);
+    }
+
+    fn absorb_elements(&mut self, label: &str, elements: &[Felt]) {
+        self.ratchet(2, label, elements);
+    }
+
+    fn squeeze(&mut self, label: &str) -> Felt {
+        self.ratchet(3, label, &[]);
+        self.state[0]
+    }
+
+    fn proof_of_work(&self, nonce: u64) -> u64 {
+        let mut sponge = self.clone();
+        sponge.ratchet(
+            4,
+            "proof of work",
+            &[Felt::new(nonce & 0xFFFF_FFFF), Felt::new(nonce >> 32)],
+        );
+        sponge.state[0].as_u64()
+    }
+}
+
+/// A labelled transcript operation, as recorded in the history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Absorb(&'static str),
+    Challenge(&'static str),
+}
+
+pub struct Transcript<S: Sponge = Sha256Sponge> {
+    sponge: S,
+    history: Vec<Operation>,
+}
+
+impl<S: Sponge> Transcript<S> {
+    /// Start a transcript bound to the given protocol seed
+    pub fn new(seed: &[u8]) -> Self {
+        Transcript {
+            sponge: S::new(seed),
+            history: Vec::new(),
+        }
+    }
+
+    /// Labelled operations performed so far, in order
+    pub fn history(&self) -> &[Operation] {
+        &self.history
+    }
+
+    pub fn absorb_bytes(&mut self, label: &'static str, bytes: &[u8]) {
+        self.history.push(Operation::Absorb(label));
+        self.sponge.absorb_bytes(label, bytes);
+    }
+
+    /// Absorb a Merkle root
+    pub fn absorb_digest(&mut self, label: &'static str, digest: &Digest) {
+        self.absorb_bytes(label, digest);
+    }
+
+    pub fn absorb_elements<E: FieldElement>(&mut self, label: &'static str, elements: &[E]) {
+        let mut base = Vec::with_capacity(elements.len());
+        for element in elements {
+            element.write_base_elements(&mut base);
+        }
+        self.history.push(Operation::Absorb(label));
+        self.sponge.absorb_elements(label, &base);
+    }
+
+    /// Draw a uniformly random base field element
+    pub fn challenge_felt(&mut self, label: &'static str) -> Felt {
+        self.history.push(Operation::Challenge(label));
+        self.sponge.squeeze(label)
+    }
+
+    /// Draw a uniformly random element of `E`, squeezing one base field element per coordinate
+    pub fn challenge<E: FieldElement>(&mut self, label: &'static str) -> E {
+        self.history.push(Operation::Challenge(label));
+        let coordinates: Vec<Felt> = (0..E::EXTENSION_DEGREE)
+            .map(|_| self.sponge.squeeze(label))
+            .collect();
+        E::from_base_elements(&coordinates)
+    }
+
+    /// Find a proof-of-work nonce with `bits` leading zero bits and absorb it
+    pub fn grind(&mut self, label: &'static str, bits: u32) -> u64 {
+        let nonce = (0..)
+            .find(|&nonce| self.sponge.proof_of_work(nonce).leading_zeros() >= bits)
+            .expect("no proof-of-work nonce found");
+        self.absorb_bytes(label, &nonce.to_le_bytes());
+        nonce
+    }
+
+    /// Check a proof-of-work nonce found by [`grind`](Self::grind) and absorb it
+    pub fn check_grinding(&mut self, label: &'static str, bits: u32, nonce: u64) -> bool {
+        let valid = self.sponge.proof_of_work(nonce).leading_zeros() >= bits;
+        self.absorb_bytes(label, &nonce.to_le_bytes());
+        valid
+    }
+
+    /// Draw `count` indices in `[0, domain_size)`; `domain_size` must be a power of two
+    pub fn challenge_indices(
+        &mut self,
+        label: &'static str,
+        count: usize,
+        domain_size: usize,
+    ) -> Vec<usize> {
+        assert!(domain_size.is_power_of_two());
+        self.history.push(Operation::Challenge(label));
+        (0..count)
+            .map(|_| self.sponge.squeeze(label).as_u64() as usize & (domain_size - 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::QuadExt;
+
+    fn check_determinism<S: Sponge>() {
+        let mut a = Transcript::<S>::new(b"test");
+        let mut b = Transcript::<S>::new(b"test");
+        a.absorb_elements("values", &[Felt::new(1), Felt::new(2)]);
+        b.absorb_elements("values", &[Felt::new(1), Felt::new(2)]);
+        assert_eq!(a.challenge_felt("alpha"), b.challenge_felt("alpha"));
+        assert_eq!(
+            a.challenge_indices("queries", 4, 64),
+            b.challenge_indices("queries", 4, 64)
+        );
+        assert_eq!(a.history(), b.history());
+
+        // Successive challenges differ and depend on what was absorbed
+        let mut c = Transcript::<S>::new(b"test");
+        c.absorb_elements("values", &[Felt::new(1), Felt::new(3)]);
+        assert_ne!(a.challenge_felt("alpha"), c.challenge_felt("alpha"));
+        assert_ne!(a.challenge_felt("alpha"), a.challenge_felt("alpha"));
+
+        // Labels separate domains, for messages as well as for challenges
+        let mut d = Transcript::<S>::new(b"test");
+        d.absorb_elements("other values", &[Felt::new(1), Felt::new(2)]);
+        let mut e = Transcript::<S>::new(b"test");
+        e.absorb_elements("values", &[Felt::new(1), Felt::new(2)]);
+        assert_ne!(d.challenge_felt("alpha"), e.challenge_felt("alpha"));
+        assert_ne!(d.challenge_felt("alpha"), e.challenge_felt("beta"));
+
+        // Extension elements are absorbed through their coordinates
+        let mut f = Transcript::<S>::new(b"test");
+        f.absorb_elements("values", &[QuadExt::new(Felt::new(1), Felt::new(2))]);
+        let mut g = Transcript::<S>::new(b"test");
+        g.absorb_elements("values", &[Felt::new(1), Felt::new(2)]);
+        assert_eq!(f.challenge_felt("alpha"), g.challenge_felt("alpha"));
+
+        // Grinding
+        let mut prover = Transcript::<S>::new(b"test");
+        let mut verifier = Transcript::<S>::new(b"test");
+        let nonce = prover.grind("nonce", 6);
+        assert!(verifier.check_grinding("nonce", 6, nonce));
+        assert_eq!(prover.challenge_felt("x"), verifier.challenge_felt("x"));
+    }
+
+    #[test]
+    fn test_transcript_determinism() {
+        check_determinism::<Sha256Sponge>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_poseidon_transcript_determinism() {
+        check_determinism::<PoseidonSponge>();
+        check_determinism::<Poseidon2Sponge>();
+
+        let mut a = Transcript::<Sha256Sponge>::new(b"test");
+        let mut b = Transcript::<PoseidonSponge>::new(b"test");
+        let mut c = Transcript::<Poseidon2Sponge>::new(b"test");
+        let alpha = b.challenge_felt("alpha");
+        assert_ne!(a.challenge_felt("alpha"), alpha);
+        assert_ne!(c.challenge_felt("alpha"), alpha);
+    }
+}
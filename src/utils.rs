@@ -1,70 +1,367 @@
-use crate::instruction::execute;
-use crate::memory::Memory;
+use crate::instruction::{execute_with_io, store_addresses};
+use crate::io::{Console, Io, IoTape, Recorder};
+use crate::memory::{Memory, ProgramRom};
 use crate::opcode::extract_opcode;
 use crate::register::{Register, RegisterFile};
+use crate::trace::{ExecutionTrace, RegisterState, Step};
 use std::fs::File;
 use std::io::{self, Read};
 
 /// Load an LC3 object file into memory
 pub fn load_obj_file(filename: &str, memory: &mut Memory) -> io::Result<u16> {
+    let (origin, words) = read_obj_file(filename)?;
+    for (address, word) in (origin..).zip(&words) {
+        memory.write(address, *word);
+    }
+    Ok(origin)
+}
+
+/// Load an LC3 object file into memory as a read-only program ROM, see [`Memory::set_rom`]
+pub fn load_obj_file_as_rom(filename: &str, memory: &mut Memory) -> io::Result<u16> {
+    let (origin, words) = read_obj_file(filename)?;
+    let length = u16::try_from(words.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid program ROM"))?;
+    memory
+        .set_rom(Some(ProgramRom {
+            start: origin,
+            length,
+        }))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    for (address, word) in (origin..).zip(&words) {
+        memory.write(address, *word);
+    }
+    Ok(origin)
+}
+
+/// Read the origin and the words of an LC3 object file
+fn read_obj_file(filename: &str) -> io::Result<(u16, Vec<u16>)> {
     let mut file = File::open(filename)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
 
-    let mut origin: u16 = 0x3000;
-    let mut i = 0;
+    // The origin comes first, followed by the words loaded from it on
+    let mut words = buffer
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+    let origin = words.next().unwrap_or(0x3000);
+    Ok((origin, words.collect()))
+}
 
-    // Read the origin
-    if buffer.len() >= 2 {
-        origin = u16::from_be_bytes([buffer[0], buffer[1]]);
-        // origin = origin.swap_bytes();
-        i = 2;
-    }
+/// Execute the loaded program until HALT on the console, recording every step
+pub fn execute_program(
+    memory: &mut Memory,
+    registers: &mut RegisterFile,
+) -> Result<ExecutionTrace, &'static str> {
+    execute_program_with_io(memory, registers, &mut Console)
+}
 
-    // Load program into memory
-    let mut address = origin;
-    while i < buffer.len() {
-        if i + 1 < buffer.len() {
-            let instruction = u16::from_be_bytes([buffer[i], buffer[i + 1]]);
+/// Execute the loaded program until HALT with trap routines using `io`, recording every step and
+/// the bytes read and written
+pub fn execute_program_with_io(
+    memory: &mut Memory,
+    registers: &mut RegisterFile,
+    io: &mut dyn Io,
+) -> Result<ExecutionTrace, &'static str> {
+    execute_segment(memory, registers, io, usize::MAX)
+}
 
-            memory.write(address, instruction);
+/// How a deterministic run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The program executed `HALT`
+    Halted,
+    /// The cycle budget ran out before `HALT`
+    OutOfCycles,
+}
 
-            // let opcode = extract_opcode(instruction);
-            // println!("loading image, address: 0x{:04X}, opcode: {:?}, instruction: {:04X}", address, opcode.unwrap(), instruction   );
+/// A deterministic run: how it ended, its trace, and the machine state it ended in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    pub outcome: Outcome,
+    pub trace: ExecutionTrace,
+    /// Registers after the last executed instruction
+    pub registers: RegisterState,
+    /// Memory after the last executed instruction, as from [`Memory::image`]
+    pub memory_image: Vec<(u16, u16)>,
+}
 
-            address += 1;
-            i += 2;
+/// Execute the loaded program for at most `max_cycles` cycles, one per instruction, reading its
+/// input from `tape` only.
+///
+/// The run depends only on the memory, the registers and the tape, so running it again gives the
+/// same [`Execution`], byte for byte, and so does proving it without zero-knowledge. A program that
+/// does not halt within the budget ends with [`Outcome::OutOfCycles`] instead of looping forever;
+/// `memory` and `registers` are left in the state at the cutoff, from which execution can resume.
+pub fn execute_deterministic(
+    memory: &mut Memory,
+    registers: &mut RegisterFile,
+    tape: &mut IoTape,
+    max_cycles: usize,
+) -> Result<Execution, &'static str> {
+    let trace = execute_segment(memory, registers, tape, max_cycles)?;
+    Ok(Execution {
+        outcome: if trace.halted {
+            Outcome::Halted
         } else {
-            break;
-        }
-    }
-
-    Ok(origin)
+            Outcome::OutOfCycles
+        },
+        trace,
+        registers: RegisterState::capture(registers),
+        memory_image: memory.image(),
+    })
 }
 
-/// Execute the loaded program
-pub fn execute_program(
+/// Execute at most `max_steps` instructions of the loaded program, stopping early at HALT.
+///
+/// The trace records whether the program halted; if not, execution can resume from the current
+/// registers and memory. If memory has a [`ProgramRom`], instructions are fetched from it, and
+/// fetching outside it or writing into it is an error. A store into the ROM is caught before it
+/// executes, so the ROM keeps its contents.
+pub fn execute_segment(
     memory: &mut Memory,
     registers: &mut RegisterFile,
-) -> Result<(), &'static str> {
+    io: &mut dyn Io,
+    max_steps: usize,
+) -> Result<ExecutionTrace, &'static str> {
+    let rom = memory.rom();
+    let mut trace = ExecutionTrace {
+        rom,
+        ..ExecutionTrace::new()
+    };
+    let mut io = Recorder::new(io);
     // println!("execute_program, PC: 0x{:04X}", registers.read(Register::PC));
-    loop {
+    while trace.len() < max_steps {
+        let before = RegisterState::capture(registers);
+        memory.start_recording();
+
         let pc = registers.read(Register::PC);
-        let raw_instruction = memory.read(pc);
+        let raw_instruction = match rom {
+            // A fetch from the ROM is not a memory access
+            Some(rom) if rom.contains(pc) => memory[pc],
+            Some(_) => {
+                memory.stop_recording();
+                return Err("Instruction fetch outside the program ROM");
+            }
+            None => memory.read(pc),
+        };
 
         // Increment PC
         registers.write(Register::PC, pc.wrapping_add(1));
 
-        if let Some(_opcode) = extract_opcode(raw_instruction) {
+        if let Some(opcode) = extract_opcode(raw_instruction) {
             // println!("execute_program, address: 0x{:04X}, opcode: {:?}", pc, opcode);
 
-            match execute(raw_instruction, registers, memory) {
+            // Stop before the store, so that the ROM keeps its contents
+            let writes_rom = |rom: ProgramRom| {
+                store_addresses(raw_instruction, registers, memory)
+                    .into_iter()
+                    .any(|address| rom.contains(address))
+            };
+            if rom.is_some_and(writes_rom) {
+                memory.stop_recording();
+                return Err("Write into the program ROM");
+            }
+            let result = execute_with_io(raw_instruction, registers, memory, &mut io);
+            let memory_accesses = memory.stop_recording();
+            trace.steps.push(Step {
+                pc,
+                instruction: raw_instruction,
+                opcode,
+                before,
+                after: RegisterState::capture(registers),
+                memory_accesses,
+            });
+
+            match result {
                 Ok(_) => {}
-                Err("HALT") => return Ok(()),
+                Err("HALT") => {
+                    trace.halted = true;
+                    break;
+                }
                 Err(e) => return Err(e),
             }
         } else {
+            memory.stop_recording();
             return Err("Invalid instruction");
         }
     }
+    trace.input = io.input;
+    trace.output = io.output;
+    Ok(trace)
+}
+
+/// Pad `message` for SHA-256 and pack it into the words `TRAP x27` hashes, two bytes per word with
+/// the first in the high byte. The result holds a whole number of 32-word blocks.
+pub fn sha256_message_words(message: &[u8]) -> Vec<u16> {
+    let mut bytes = message.to_vec();
+    bytes.push(0x80);
+    while bytes.len() % 64 != 56 {
+        bytes.push(0);
+    }
+    bytes.extend_from_slice(&(8 * message.len() as u64).to_be_bytes());
+    bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(program: &[u16]) -> (Memory, RegisterFile) {
+        let mut memory = Memory::new();
+        let mut registers = RegisterFile::new();
+        for (i, word) in program.iter().enumerate() {
+            memory.write(0x3000 + i as u16, *word);
+        }
+        registers.write(Register::PC, 0x3000);
+        (memory, registers)
+    }
+
+    fn run(program: &[u16], input: &[u8], max_cycles: usize) -> Execution {
+        let (mut memory, mut registers) = load(program);
+        let mut tape = IoTape::new(input.to_vec());
+        execute_deterministic(&mut memory, &mut registers, &mut tape, max_cycles).unwrap()
+    }
+
+    #[test]
+    fn test_cycle_limit() {
+        // Echo input bytes forever, counting them in R1
+        let program = [
+            0xF020,                 // GETC
+            0xF021,                 // OUT
+            0b0001_001_001_1_00001, // ADD R1, R1, #1
+            0b0000_111_111111100,   // BRnzp #-4
+        ];
+        let execution = run(&program, b"abc", 10);
+        assert_eq!(execution.outcome, Outcome::OutOfCycles);
+        assert_eq!(execution.trace.len(), 10);
+        assert!(!execution.trace.halted);
+        assert_eq!(execution.trace.output, b"abc");
+        assert_eq!(execution.registers.pc, 0x3002);
+        assert_eq!(execution.registers.gprs[1], 2);
+        assert_eq!(Some(execution.registers), execution.trace.final_state());
+
+        // Runs are reproducible
+        assert_eq!(run(&program, b"abc", 10), execution);
+
+        // With a larger budget, the program runs out of input instead
+        let (mut memory, mut registers) = load(&program);
+        let mut tape = IoTape::new(b"abc".to_vec());
+        assert_eq!(
+            execute_deterministic(&mut memory, &mut registers, &mut tape, 100),
+            Err("Input tape exhausted")
+        );
+    }
+
+    #[test]
+    fn test_halt_within_budget() {
+        let program = [
+            0b0001_000_000_1_00111, // ADD R0, R0, #7
+            0xF025,                 // HALT
+        ];
+        let execution = run(&program, b"", 2);
+        assert_eq!(execution.outcome, Outcome::Halted);
+        assert_eq!(execution.trace.len(), 2);
+        assert_eq!(execution.registers.gprs[0], 7);
+
+        let execution = run(&program, b"", 1);
+        assert_eq!(execution.outcome, Outcome::OutOfCycles);
+        assert_eq!(execution.registers.pc, 0x3001);
+        assert_eq!(run(&program, b"", 0).trace.len(), 0);
+    }
+
+    #[test]
+    fn test_program_rom() {
+        let run_rom = |program: &[u16]| {
+            let (mut memory, mut registers) = load(program);
+            let rom = ProgramRom {
+                start: 0x3000,
+                length: program.len() as u16,
+            };
+            memory.set_rom(Some(rom)).unwrap();
+            let mut tape = IoTape::new(Vec::new());
+            execute_deterministic(&mut memory, &mut registers, &mut tape, 10)
+        };
+
+        // Fetches from the ROM are not memory accesses, data reads of the ROM are
+        let program = [
+            0b0010_000_111111111, // LD R0, #-1
+            0xF025,               // HALT
+        ];
+        let execution = run_rom(&program).unwrap();
+        assert_eq!(execution.outcome, Outcome::Halted);
+        assert_eq!(execution.registers.gprs[0], program[0]);
+        let steps = &execution.trace.steps;
+        assert_eq!(steps[0].memory_accesses.len(), 1);
+        assert_eq!(steps[0].memory_accesses[0].address, 0x3000);
+        assert!(steps[1].memory_accesses.is_empty());
+
+        let program = [
+            0b0011_000_111111111, // ST R0, #-1
+            0xF025,               // HALT
+        ];
+        assert_eq!(run_rom(&program), Err("Write into the program ROM"));
+
+        let program = [
+            0b1100_000_000_000000, // JMP R0
+            0xF025,                // HALT
+        ];
+        assert_eq!(
+            run_rom(&program),
+            Err("Instruction fetch outside the program ROM")
+        );
+    }
+
+    #[test]
+    fn test_rom_write_leaves_memory_unchanged() {
+        let programs: [&[u16]; 4] = [
+            &[
+                0b0011_000_111111111, // ST R0, #-1
+                0xF025,               // HALT
+            ],
+            &[
+                0b1011_000_000000001, // STI R0, #1
+                0xF025,               // HALT
+                0x3000,
+            ],
+            &[
+                0b1110_001_111111111,  // LEA R1, #-1
+                0b0111_000_001_000001, // STR R0, R1, #1
+                0xF025,                // HALT
+            ],
+            &[
+                0b1110_010_111111111,   // LEA R2, #-1
+                0b0001_001_001_1_00001, // ADD R1, R1, #1
+                0xF027,                 // TRAP x27, digest at 0x3000
+                0xF025,                 // HALT
+            ],
+        ];
+        for program in programs {
+            let (mut memory, mut registers) = load(program);
+            let rom = ProgramRom {
+                start: 0x3000,
+                length: program.len() as u16,
+            };
+            memory.set_rom(Some(rom)).unwrap();
+            let image = memory.image();
+            let mut tape = IoTape::new(Vec::new());
+            assert_eq!(
+                execute_deterministic(&mut memory, &mut registers, &mut tape, 10),
+                Err("Write into the program ROM")
+            );
+            assert_eq!(memory.image(), image);
+        }
+    }
+
+    #[test]
+    fn test_sha256_message_words() {
+        assert_eq!(sha256_message_words(b"").len(), 32);
+        assert_eq!(sha256_message_words(&[0; 55]).len(), 32);
+        assert_eq!(sha256_message_words(&[0; 56]).len(), 64);
+        let words = sha256_message_words(b"abc");
+        assert_eq!(words[..2], [0x6162, 0x6380]);
+        assert_eq!(words[31], 24);
+    }
 }
@@ -0,0 +1,324 @@
+//! STARK Verifier Module
+//!
+//! This module checks proofs produced by the [`prover`](crate::prover). The verifier only needs the
+//! public inputs (initial memory root, initial and final register state, final memory image, input
+//! and output bytes) and the proof; it never sees the execution trace.
+//!
+//! ## Checks
+//! 1. Check the memory image against its root, the condition flags of the register states and, for
+//!    a run fetching from a program ROM, that the run left the ROM unchanged. Replay the
+//!    Fiat-Shamir transcript to re-derive every challenge.
+//! 2. Check that the composition polynomial, evaluated from the out-of-domain trace values,
+//!    matches the committed composition chunks at `z`.
+//! 3. For every query position, authenticate the opened trace and composition rows against their
+//!    Merkle roots, compute the DEEP composition value and hand it to the FRI verifier.
+//!
+//! The chip proofs attached to a proof are checked table by table in the order they were proven,
+//! after checking that the bus claims of the tables and the messages the verifier sends from the
+//! public inputs add up to zero.
+
+use crate::air::{bus, rom, Air, Lc3Air, MAX_TRACE_LENGTH, MIN_TRACE_LENGTH};
+use crate::field::{Felt, FieldElement, QuadExt};
+use crate::fri::FriVerifier;
+use crate::merkle::{Digest, MerkleTree, Sha256Hasher};
+use crate::proof::{
+    absorb_chip, boundary_constraints, bus_balance, draw_ood_point, ChipProof,
+    ConstraintCoefficients, DeepCoefficients, PeriodicColumns, Proof, ProofOptions, PublicInputs,
+    RowOpening,
+};
+use crate::transcript::{Sponge, Transcript};
+use alloc::vec::Vec;
+
+/// Verify a proof of an LC3 run against its public inputs
+pub fn verify(public_inputs: &PublicInputs, proof: &Proof) -> Result<(), &'static str> {
+    let n = proof.trace_length;
+    if !n.is_power_of_two() || !(MIN_TRACE_LENGTH..=MAX_TRACE_LENGTH).contains(&n) {
+        return Err("Invalid trace length");
+    }
+    proof.options.validate()?;
+    public_inputs.check_memory_image()?;
+    public_inputs.check_states()?;
+    public_inputs.check_rom()?;
+    if n < rom::min_trace_length(public_inputs.rom) {
+        return Err("Trace too short for the program ROM");
+    }
+    let mut transcript: Transcript = public_inputs.transcript(n, &proof.options);
+    verify_tables(public_inputs, proof, &mut transcript)
+}
+
+/// Verify that `proof` shows knowledge of an LC3 table and chip tables satisfying their AIRs and
+/// balancing on the bus.
+///
+/// The transcript must already have absorbed the public inputs.
+pub(crate) fn verify_tables<S: Sponge>(
+    public_inputs: &PublicInputs,
+    proof: &Proof,
+    transcript: &mut Transcript<S>,
+) -> Result<(), &'static str> {
+    if proof
+        .chips
+        .windows(2)
+        .any(|pair| pair[0].chip >= pair[1].chip)
+    {
+        return Err("Unexpected chip proofs");
+    }
+    let mut chip_airs = Vec::with_capacity(proof.chips.len());
+    for ChipProof {
+        chip,
+        proof: chip_proof,
+    } in &proof.chips
+    {
+        let chip_length = chip_proof.trace_length;
+        if chip_proof.options != proof.options || !chip_proof.chips.is_empty() {
+            return Err("Malformed chip proof");
+        }
+        if !chip_length.is_power_of_two()
+            || !(chip.min_trace_length()..=MAX_TRACE_LENGTH).contains(&chip_length)
+        {
+            return Err("Invalid chip trace length");
+        }
+        chip_airs.push(chip.air(chip_length, public_inputs));
+    }
+
+    // The tables, verified in the order they were proven
+    let air = Lc3Air::new(proof.trace_length, public_inputs.clone());
+    let mut lc3 = TableVerifier::new(&air, proof, transcript)?;
+    let mut chips = Vec::with_capacity(chip_airs.len());
+    for (chip_proof, chip_air) in proof.chips.iter().zip(&chip_airs) {
+        absorb_chip(transcript, chip_proof.chip, chip_proof.proof.trace_length);
+        chips.push(TableVerifier::new(chip_air, &chip_proof.proof, transcript)?);
+    }
+    let challenges = draw_aux_challenges(transcript, bus::SHARED_CHALLENGES);
+    lc3.absorb_aux(&challenges, transcript)?;
+    for chip in &mut chips {
+        chip.absorb_aux(&challenges, transcript)?;
+    }
+    let bus_sums = core::iter::once(&proof.bus_sums).chain(
+        proof
+            .chips
+            .iter()
+            .map(|chip_proof| &chip_proof.proof.bus_sums),
+    );
+    if bus_balance(bus_sums) + bus::public_sum(public_inputs, &challenges) != QuadExt::ZERO {
+        return Err("Bus messages do not balance");
+    }
+    lc3.verify(transcript)?;
+    for chip in chips {
+        chip.verify(transcript)?;
+    }
+    Ok(())
+}
+
+/// Verify that `proof` shows knowledge of a trace satisfying `air`.
+///
+/// The transcript must already have absorbed the public inputs.
+pub fn verify_air<A: Air, S: Sponge>(
+    air: &A,
+    proof: &Proof,
+    transcript: &mut Transcript<S>,
+) -> Result<(), &'static str> {
+    if !proof.chips.is_empty() {
+        return Err("Unexpected chip proofs");
+    }
+    let mut verifier = TableVerifier::new(air, proof, transcript)?;
+    let challenges = draw_aux_challenges(transcript, air.num_aux_challenges());
+    verifier.absorb_aux(&challenges, transcript)?;
+    if bus_balance([&proof.bus_sums]) != QuadExt::ZERO {
+        return Err("Bus messages do not balance");
+    }
+    verifier.verify(transcript)
+}
+
+/// Draw the auxiliary challenges from the quadratic extension, shared by all tables of a proof
+fn draw_aux_challenges<S: Sponge>(transcript: &mut Transcript<S>, count: usize) -> Vec<QuadExt> {
+    (0..count)
+        .map(|_| transcript.challenge("aux challenge"))
+        .collect()
+}
+
+/// A table proof being checked, in the same three steps as it was proven: [`TableVerifier::new`]
+/// replays the main commitment, [`TableVerifier::absorb_aux`] the auxiliary commitment and the
+/// bus claims, and [`TableVerifier::verify`] checks the rest of the proof.
+struct TableVerifier<'a, A> {
+    air: &'a A,
+    proof: &'a Proof,
+    challenges: Vec<QuadExt>,
+}
+
+impl<'a, A: Air> TableVerifier<'a, A> {
+    /// Check the shape of the proof and absorb the main commitment
+    fn new<S: Sponge>(
+        air: &'a A,
+        proof: &'a Proof,
+        transcript: &mut Transcript<S>,
+    ) -> Result<Self, &'static str> {
+        let options = &proof.options;
+        let n = air.trace_length();
+        if proof.trace_length != n {
+            return Err("Trace length does not match the AIR");
+        }
+        options.validate()?;
+        if options.lde_domain_size(n).is_none() {
+            return Err("Low-degree extension domain too large");
+        }
+        if options.blowup_factor < air.max_constraint_degree() + options.zero_knowledge as usize {
+            return Err("Invalid blowup factor");
+        }
+        if n < options.min_trace_length(air.max_constraint_degree()) {
+            return Err("Trace too short for zero-knowledge");
+        }
+        let width = air.trace_width() + air.aux_width();
+        let ood = &proof.ood_frame;
+        if ood.current.len() != width
+            || ood.next.len() != width
+            || ood.composition.len() != options.num_composition_columns(air)
+        {
+            return Err("Malformed out-of-domain frame");
+        }
+        if proof.bus_sums.len() != air.bus_columns().len() {
+            return Err("Unexpected number of bus sums");
+        }
+
+        transcript.absorb_digest("trace root", &proof.trace_root);
+        Ok(TableVerifier {
+            air,
+            proof,
+            challenges: Vec::new(),
+        })
+    }
+
+    /// Absorb the auxiliary commitment and the bus claims
+    fn absorb_aux<S: Sponge>(
+        &mut self,
+        challenges: &[QuadExt],
+        transcript: &mut Transcript<S>,
+    ) -> Result<(), &'static str> {
+        self.challenges = challenges[..self.air.num_aux_challenges()].to_vec();
+        match (&self.proof.aux_root, self.air.aux_width()) {
+            (None, 0) => {}
+            (Some(aux_root), width) if width > 0 => transcript.absorb_digest("aux root", aux_root),
+            _ => return Err("Unexpected auxiliary commitment"),
+        }
+        if !self.proof.bus_sums.is_empty() {
+            transcript.absorb_elements("bus sums", &self.proof.bus_sums);
+        }
+        Ok(())
+    }
+
+    /// Check the composition polynomial at the out-of-domain point, the queries and FRI
+    fn verify<S: Sponge>(self, transcript: &mut Transcript<S>) -> Result<(), &'static str> {
+        let TableVerifier {
+            air,
+            proof,
+            challenges,
+        } = self;
+        let options = &proof.options;
+        let n = air.trace_length();
+        let lde_size = n * options.blowup_factor;
+        let main_width = air.trace_width();
+        let aux_width = air.aux_width();
+        let width = main_width + aux_width;
+        let num_chunks = options.num_composition_chunks(air);
+        let num_composition_columns = options.num_composition_columns(air);
+        let ood = &proof.ood_frame;
+
+        // 1. Replay the rest of the transcript
+        let boundary_constraints = boundary_constraints(air, &challenges, &proof.bus_sums);
+        let constraint_coefficients =
+            ConstraintCoefficients::draw(transcript, air, &boundary_constraints);
+        transcript.absorb_digest("composition root", &proof.composition_root);
+        let z = draw_ood_point(transcript, n, lde_size);
+        ood.absorb_into(transcript);
+
+        // 2. Out-of-domain consistency
+        let g = Felt::two_adic_root(n.trailing_zeros());
+        let periodic = PeriodicColumns::new(air)?;
+        let mut current = ood.current.clone();
+        current.extend(periodic.evaluate_at(z));
+        let mut next = ood.next.clone();
+        next.extend(periodic.evaluate_at(z.mul_base(g)));
+        let expected = constraint_coefficients.evaluate(
+            air,
+            &boundary_constraints,
+            &challenges,
+            z,
+            &current,
+            &next,
+        );
+        let z_n = z.pow(n as u64);
+        let actual = ood.composition[..num_chunks]
+            .iter()
+            .rev()
+            .fold(QuadExt::ZERO, |acc, chunk| acc * z_n + *chunk);
+        if expected != actual {
+            return Err("Composition polynomial does not match the constraints at the OOD point");
+        }
+
+        // 3. Queries
+        let deep_coefficients = DeepCoefficients::draw(transcript, width, num_composition_columns);
+        let offset = Felt::GENERATOR;
+        let fri_options = options.fri_options();
+        let fri_verifier =
+            FriVerifier::new(&proof.fri_proof, lde_size, offset, &fri_options, transcript)?;
+        let positions =
+            transcript.challenge_indices("query positions", options.num_queries, lde_size);
+        let num_aux_openings = if aux_width == 0 { 0 } else { positions.len() };
+        if proof.trace_openings.len() != positions.len()
+            || proof.aux_openings.len() != num_aux_openings
+            || proof.composition_openings.len() != positions.len()
+        {
+            return Err("Unexpected number of query openings");
+        }
+
+        let lde_root = Felt::two_adic_root(lde_size.trailing_zeros());
+        let mut deep_values = Vec::with_capacity(positions.len());
+        for (i, ((&position, trace_row), composition_row)) in positions
+            .iter()
+            .zip(&proof.trace_openings)
+            .zip(&proof.composition_openings)
+            .enumerate()
+        {
+            check_opening(&proof.trace_root, position, trace_row, main_width, options)?;
+            let mut row: Vec<QuadExt> = trace_row.values.iter().map(|&v| v.into()).collect();
+            if let Some(aux_root) = &proof.aux_root {
+                let aux_row = &proof.aux_openings[i];
+                check_opening(aux_root, position, aux_row, aux_width, options)?;
+                row.extend_from_slice(&aux_row.values);
+            }
+            check_opening(
+                &proof.composition_root,
+                position,
+                composition_row,
+                num_composition_columns,
+                options,
+            )?;
+            let x = offset * lde_root.pow(position as u64);
+            deep_values.push(deep_coefficients.evaluate(
+                x,
+                z,
+                g,
+                &row,
+                &composition_row.values,
+                ood,
+            ));
+        }
+
+        fri_verifier.verify(&proof.fri_proof, &positions, &deep_values)
+    }
+}
+
+fn check_opening<E: FieldElement>(
+    root: &Digest,
+    position: usize,
+    opening: &RowOpening<E>,
+    width: usize,
+    options: &ProofOptions,
+) -> Result<(), &'static str> {
+    if opening.values.len() != width || opening.salt.is_some() != options.zero_knowledge {
+        return Err("Malformed row opening");
+    }
+    if !MerkleTree::<Sha256Hasher>::verify(root, position, &opening.leaf(), &opening.path) {
+        return Err("Invalid Merkle path for row opening");
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Verification of a receipt written by the native prover.
+//!
+//! `data/sha256.receipt` proves `data/sha256.obj`, which hashes "abc" with the SHA-256 trap and
+//! so needs both chips. The test runs without the `std` feature as well, which checks that the
+//! verifier alone can check receipts:
+//!
+//! ```text
+//! cargo test --no-default-features --test verify_receipt
+//! ```
+//!
+//! Any change to the proof system invalidates the receipt; regenerate it with
+//!
+//! ```text
+//! cargo run --release -- prove tests/data/sha256.obj -o tests/data/sha256.receipt
+//! ```
+//!
+//! and update [`PROGRAM_ID`] if the CLI prints another one.
+
+use lc3_zkvm::merkle::from_hex;
+use lc3_zkvm::receipt::{ExitStatus, Receipt};
+
+const RECEIPT: &[u8] = include_bytes!("data/sha256.receipt");
+
+const PROGRAM_ID: &str = "b53c89b4600107bffba8038846842caa2a03fc2152157dba32f8d1459663aea0";
+
+#[test]
+fn test_verify_native_receipt() {
+    let program_id = from_hex(PROGRAM_ID).unwrap();
+    let receipt = Receipt::from_bytes(RECEIPT).unwrap();
+    assert_eq!(receipt.verify(&program_id), Ok(()));
+    assert_eq!(receipt.exit_status(), ExitStatus::Halted);
+    assert!(receipt.journal().is_empty());
+
+    let mut other_id = program_id;
+    other_id[0] ^= 1;
+    assert!(receipt.verify(&other_id).is_err());
+
+    // A flipped byte in the middle of the proof
+    let mut bytes = RECEIPT.to_vec();
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 1;
+    if let Ok(forged) = Receipt::from_bytes(&bytes) {
+        assert!(forged.verify(&program_id).is_err());
+    }
+}
+
+/// The prover still writes the receipt above, byte for byte
+#[cfg(feature = "std")]
+#[test]
+fn test_receipt_is_up_to_date() {
+    use lc3_zkvm::io::IoTape;
+    use lc3_zkvm::memory::Memory;
+    use lc3_zkvm::prover::prove;
+    use lc3_zkvm::register::{Register, RegisterFile};
+    use lc3_zkvm::utils::{execute_deterministic, load_obj_file};
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sha256.obj");
+    let mut memory = Memory::new();
+    let mut registers = RegisterFile::new();
+    let origin = load_obj_file(path, &mut memory).unwrap();
+    registers.write(Register::PC, origin);
+    let initial_memory = memory.clone();
+    let mut tape = IoTape::new(Vec::new());
+    let execution = execute_deterministic(&mut memory, &mut registers, &mut tape, 1000).unwrap();
+    let (public_inputs, proof) =
+        prove(&initial_memory, &execution.trace, &Default::default()).unwrap();
+    assert_eq!(Receipt::new(public_inputs, proof).to_bytes(), RECEIPT);
+}